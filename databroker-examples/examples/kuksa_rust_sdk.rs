@@ -14,7 +14,7 @@
  */
 use kuksa_rust_sdk::kuksa::common;
 use kuksa_rust_sdk::kuksa::val::v1::KuksaClient;
-use kuksa_rust_sdk::kuksa::val::v2::KuksaClientV2;
+use kuksa_rust_sdk::kuksa::val::v2::{KuksaClientV2, SubscribeOptions};
 use kuksa_rust_sdk::sdv::databroker::v1::SDVClient;
 use kuksa_rust_sdk::{sdv_proto, v1_proto, v2_proto};
 use std::collections::HashMap;
@@ -33,13 +33,12 @@ async fn main() {
 }
 
 async fn execute_v2_calls(host: &'static str) {
-    let mut v2_client: KuksaClientV2 = KuksaClientV2::from_host(host);
+    let v2_client: KuksaClientV2 = KuksaClientV2::from_host(host);
 
     match common::ClientTraitV2::subscribe(
-        &mut v2_client,
+        &v2_client,
         vec!["Vehicle.Speed".to_owned()],
-        None,
-        None,
+        SubscribeOptions::default(),
     )
     .await
     {
@@ -66,7 +65,7 @@ async fn execute_v2_calls(host: &'static str) {
     }
 
     match common::ClientTraitV2::publish_value(
-        &mut v2_client,
+        &v2_client,
         "Vehicle.Speed".to_owned(),
         v2_proto::Value {
             typed_value: Some(v2_proto::value::TypedValue::Float(30.0)),
@@ -88,7 +87,7 @@ async fn execute_v2_calls(host: &'static str) {
         }
     }
 
-    match common::ClientTraitV2::get_value(&mut v2_client, "Vehicle.Speed".to_owned()).await {
+    match common::ClientTraitV2::get_value(&v2_client, "Vehicle.Speed".to_owned()).await {
         Ok(response) => {
             println!("Got value for Vehicle.Speed: {:?}", response);
         }
@@ -12,16 +12,13 @@
  *  SPDX-License-Identifier: Apache-2.0
  * ******************************************************************************
  */
+use kuksa_rust_sdk::kuksa::common::options::CancellationToken;
 use kuksa_rust_sdk::kuksa::common::ClientTraitV2;
-use kuksa_rust_sdk::kuksa::val::v2::KuksaClientV2;
-use kuksa_rust_sdk::v2_proto::open_provider_stream_request::Action;
-use kuksa_rust_sdk::v2_proto::signal_id::Signal;
+use kuksa_rust_sdk::kuksa::val::v2::actuator::ActuatorRuntime;
+use kuksa_rust_sdk::kuksa::val::v2::{KuksaClientV2, SubscribeOptions};
 use kuksa_rust_sdk::v2_proto::value::TypedValue;
-use kuksa_rust_sdk::v2_proto::{
-    open_provider_stream_response, OpenProviderStreamRequest, ProvideActuationRequest, SignalId,
-    Value,
-};
-use open_provider_stream_response::Action::BatchActuateStreamRequest;
+use kuksa_rust_sdk::v2_proto::Value;
+use tokio_stream::StreamExt;
 
 #[tokio::main]
 async fn main() {
@@ -63,7 +60,7 @@ async fn sample_get_signal(client: &mut KuksaClientV2) {
 async fn sample_get_signals(client: &mut KuksaClientV2) {
     let path_speed = "Vehicle.Speed".to_string();
     let path_average_speed = "Vehicle.AverageSpeed".to_string();
-    let signals = vec![path_speed.clone(), path_average_speed.clone()];
+    let signals = vec![path_speed.clone().into(), path_average_speed.clone().into()];
     let result = client.get_values(signals).await;
 
     match result {
@@ -110,50 +107,21 @@ async fn sample_publish_value(client: &mut KuksaClientV2) {
 }
 
 async fn sample_provide_actuation(client: &mut KuksaClientV2) {
-    let result = client.open_provider_stream(None).await;
-    match result {
-        Ok(mut stream) => {
-            let request = OpenProviderStreamRequest {
-                action: Some(Action::ProvideActuationRequest(ProvideActuationRequest {
-                    actuator_identifiers: vec![SignalId {
-                        signal: Some(Signal::Path("Vehicle.ADAS.ABS.IsEnabled".to_string())),
-                    }],
-                })),
-            };
-            match stream.sender.send(request).await {
-                Ok(_) => {
-                    println!("Successfully registered ProvideActuationRequest for Vehicle.ADAS.ABS.IsEnabled.");
-                }
-                Err(err) => {
-                    println!("Error: Could not send ProvideActuationRequest {:?}", err);
-                }
-            }
-
-            tokio::spawn(async move {
-                let result = stream.receiver_stream.message().await;
-                match result {
-                    Ok(option) => {
-                        let response = option.unwrap();
-                        if let Some(BatchActuateStreamRequest(batch_actuate_stream_request)) =
-                            response.action
-                        {
-                            let actuate_requests = batch_actuate_stream_request.actuate_requests;
-                            for actuate_request in actuate_requests {
-                                // execute actuate_requests
-                                println!("Received ActuateRequest: {:?}", actuate_request);
-                            }
-                        }
-                    }
-                    Err(err) => {
-                        println!("Error: Could not receive response {:?}", err);
-                    }
-                }
-            });
+    let client = client.clone();
+    let shutdown = CancellationToken::new();
+    tokio::spawn(async move {
+        let result = ActuatorRuntime::new()
+            .handler_fn("Vehicle.ADAS.ABS.IsEnabled", |enabled: bool| async move {
+                // execute actuate request
+                println!("Received ActuateRequest: {}", enabled);
+                Ok(())
+            })
+            .run(&client, shutdown)
+            .await;
+        if let Err(err) = result {
+            println!("Could not provide actuation: {}", err);
         }
-        Err(err) => {
-            println!("Could not open provider stream: {}", err);
-        }
-    }
+    });
 }
 
 async fn sample_subscribe(client: &mut KuksaClientV2) {
@@ -161,7 +129,7 @@ async fn sample_subscribe(client: &mut KuksaClientV2) {
     let path_avg_speed = "Vehicle.AverageSpeed".to_string();
     let signals = vec![path_speed.clone(), path_avg_speed.clone()];
 
-    let result = client.subscribe(signals, None, None).await;
+    let result = client.subscribe(signals, SubscribeOptions::default()).await;
     match result {
         Ok(mut streaming) => {
             tokio::spawn(async move {
@@ -195,45 +163,39 @@ async fn sample_subscribe_by_id(client: &mut KuksaClientV2) {
     let path_avg_speed = "Vehicle.AverageSpeed".to_string();
     let signals = vec![path_speed.clone(), path_avg_speed.clone()];
 
-    let signal_ids_result = client.resolve_ids_for_paths(signals).await;
-    match signal_ids_result {
-        Ok(path_ids_map) => {
-            let signal_ids: Vec<i32> = path_ids_map.values().cloned().collect();
-            let result = client.subscribe_by_id(signal_ids, None, None).await;
-            match result {
-                Ok(mut streaming) => {
-                    tokio::spawn(async move {
-                        let result = streaming.message().await;
-                        match result {
-                            Ok(option) => {
-                                let response = option.unwrap();
-                                if let Some(speed) =
-                                    response.entries.get(path_ids_map.get(&path_speed).unwrap())
-                                {
-                                    // do something with speed
-                                    println!("{}: {:?}", path_speed, speed);
-                                };
-                                if let Some(average_speed) = response
-                                    .entries
-                                    .get(path_ids_map.get(&path_avg_speed).unwrap())
-                                {
-                                    // do something with average_speed
-                                    println!("{}: {:?}", path_avg_speed, average_speed);
-                                };
-                            }
-                            Err(err) => {
-                                println!("Error: Could not receive response: {:?}", err);
-                            }
+    // Subscribes by id, but delivers the updates keyed by path
+    let result = client
+        .subscribe_resolved(signals, SubscribeOptions::default())
+        .await;
+    match result {
+        Ok(mut streaming) => {
+            tokio::spawn(async move {
+                match streaming.next().await {
+                    Some(Ok(values)) => {
+                        if let Some(speed) = values.get(&path_speed) {
+                            // do something with speed
+                            println!(
+                                "{} (id {}): {:?} {}",
+                                speed.path,
+                                speed.id,
+                                speed.datapoint.value,
+                                speed.unit.as_deref().unwrap_or_default()
+                            );
                         };
-                    });
-                }
-                Err(err) => {
-                    println!("Error subscribing to ids: {:?}", err);
-                }
-            }
+                        if let Some(average_speed) = values.get(&path_avg_speed) {
+                            // do something with average_speed
+                            println!("{}: {:?}", path_avg_speed, average_speed.datapoint);
+                        };
+                    }
+                    Some(Err(err)) => {
+                        println!("Error: Could not receive response: {:?}", err);
+                    }
+                    None => {}
+                };
+            });
         }
         Err(err) => {
-            println!("Error: Could not resolve ids for path: {:?}", err);
+            println!("Error subscribing to ids: {:?}", err);
         }
     }
 }
@@ -40,6 +40,7 @@ fn create_payload(value: &str, id: i32) -> proto::StreamDatapointsRequest {
     }
 }
 
+#[allow(clippy::result_large_err)]
 async fn run_streaming_set_test(iterations: i32, n_th_message: i32) {
     let connect = tonic::transport::Channel::from_static("http://127.0.0.1:55555")
         .connect()
@@ -18,6 +18,7 @@ use kuksa_rust_sdk::sdv_proto as proto;
 use std::time::Instant;
 
 #[tokio::main]
+#[allow(clippy::result_large_err)]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     match tonic::transport::Channel::from_static("http://127.0.0.1:55555")
         .connect()
@@ -0,0 +1,434 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+use super::failover::FailoverPolicy;
+use super::reconnect::ReconnectPolicy;
+use super::retry::RetryPolicy;
+use super::tls::TlsError;
+use super::token::TokenProvider;
+use super::{to_uri, Client, ClientError, TokenError};
+use std::sync::Arc;
+use std::time::Duration;
+use tonic::transport::Endpoint;
+
+/// Settings applied to the channel every time the [`Client`] (re-)connects.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ChannelConfig {
+    pub connect_timeout: Option<Duration>,
+    pub timeout: Option<Duration>,
+    pub keep_alive_interval: Option<Duration>,
+    pub keep_alive_timeout: Option<Duration>,
+    pub keep_alive_while_idle: Option<bool>,
+    pub tcp_nodelay: Option<bool>,
+    pub concurrency_limit: Option<usize>,
+    pub user_agent: Option<String>,
+    pub lazy: bool,
+}
+
+impl ChannelConfig {
+    pub fn apply(&self, mut endpoint: Endpoint) -> Result<Endpoint, String> {
+        if let Some(connect_timeout) = self.connect_timeout {
+            endpoint = endpoint.connect_timeout(connect_timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            endpoint = endpoint.timeout(timeout);
+        }
+        if let Some(interval) = self.keep_alive_interval {
+            endpoint = endpoint.http2_keep_alive_interval(interval);
+        }
+        if let Some(timeout) = self.keep_alive_timeout {
+            endpoint = endpoint.keep_alive_timeout(timeout);
+        }
+        if let Some(while_idle) = self.keep_alive_while_idle {
+            endpoint = endpoint.keep_alive_while_idle(while_idle);
+        }
+        if let Some(nodelay) = self.tcp_nodelay {
+            endpoint = endpoint.tcp_nodelay(nodelay);
+        }
+        if let Some(limit) = self.concurrency_limit {
+            endpoint = endpoint.concurrency_limit(limit);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            endpoint = endpoint
+                .user_agent(user_agent.clone())
+                .map_err(|err| format!("Invalid user agent {user_agent}: {err}"))?;
+        }
+        Ok(endpoint)
+    }
+}
+
+#[derive(Debug)]
+pub enum BuilderError {
+    InvalidUri(String),
+    InvalidToken(TokenError),
+    InvalidOption(String),
+    InvalidTls(TlsError),
+}
+
+impl std::error::Error for BuilderError {}
+impl std::fmt::Display for BuilderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuilderError::InvalidUri(msg) => f.pad(&format!("Invalid uri: {msg}")),
+            BuilderError::InvalidToken(err) => f.pad(&format!("Invalid token: {err}")),
+            BuilderError::InvalidOption(msg) => f.pad(msg),
+            BuilderError::InvalidTls(err) => f.pad(&format!("Invalid TLS configuration: {err}")),
+        }
+    }
+}
+
+/// Builder for [`Client`] and the protocol specific clients wrapping it.
+///
+/// ```no_run
+/// # use kuksa_rust_sdk::kuksa::common::builder::ClientBuilder;
+/// # use kuksa_rust_sdk::kuksa::val::v2::KuksaClientV2;
+/// # use std::time::Duration;
+/// let client: KuksaClientV2 = ClientBuilder::new("http://localhost:55555")
+///     .connect_timeout(Duration::from_secs(5))
+///     .timeout(Duration::from_secs(2))
+///     .build()
+///     .unwrap();
+/// ```
+///
+/// All settings are validated by [`ClientBuilder::build`].
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    address: String,
+    standby_addresses: Vec<String>,
+    failover_policy: FailoverPolicy,
+    token: Option<String>,
+    token_provider: Option<Arc<dyn TokenProvider>>,
+    scope_precheck: bool,
+    #[cfg(feature = "tls")]
+    tls_config: Option<tonic::transport::ClientTlsConfig>,
+    #[cfg(feature = "tls")]
+    tls: Option<super::tls::TlsConfig>,
+    reconnect_policy: Option<ReconnectPolicy>,
+    retry_policy: Option<RetryPolicy>,
+    channel_config: ChannelConfig,
+}
+
+impl ClientBuilder {
+    /// Creates a builder for the databroker at `address`, see [`to_uri`] for supported formats.
+    pub fn new(address: impl Into<String>) -> Self {
+        ClientBuilder {
+            address: address.into(),
+            standby_addresses: Vec::new(),
+            failover_policy: FailoverPolicy::default(),
+            token: None,
+            token_provider: None,
+            scope_precheck: false,
+            #[cfg(feature = "tls")]
+            tls_config: None,
+            #[cfg(feature = "tls")]
+            tls: None,
+            reconnect_policy: None,
+            retry_policy: None,
+            channel_config: ChannelConfig::default(),
+        }
+    }
+
+    /// Standby databrokers to fail over to, see [`Client::set_failover_endpoints`].
+    pub fn failover<S: Into<String>>(
+        mut self,
+        standby_addresses: impl IntoIterator<Item = S>,
+        policy: FailoverPolicy,
+    ) -> Self {
+        self.standby_addresses = standby_addresses.into_iter().map(Into::into).collect();
+        self.failover_policy = policy;
+        self
+    }
+
+    pub fn access_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Fetch access tokens from `token_provider`, see [`Client::set_token_provider`].
+    pub fn token_provider(mut self, token_provider: impl TokenProvider + 'static) -> Self {
+        self.token_provider = Some(Arc::new(token_provider));
+        self
+    }
+
+    /// See [`Client::set_scope_precheck`].
+    pub fn scope_precheck(mut self, enabled: bool) -> Self {
+        self.scope_precheck = enabled;
+        self
+    }
+
+    #[cfg(feature = "tls")]
+    pub fn tls_config(mut self, tls_config: tonic::transport::ClientTlsConfig) -> Self {
+        self.tls_config = Some(tls_config);
+        self
+    }
+
+    /// TLS settings, certificates and keys are loaded and validated by [`ClientBuilder::build`].
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self, tls: super::tls::TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    pub fn reconnect_policy(mut self, reconnect_policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = Some(reconnect_policy);
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Timeout for establishing the connection to the databroker.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.channel_config.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout applied to every request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.channel_config.timeout = Some(timeout);
+        self
+    }
+
+    /// Interval of HTTP/2 keepalive pings.
+    pub fn keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.channel_config.keep_alive_interval = Some(interval);
+        self
+    }
+
+    /// Time to wait for the acknowledgement of a keepalive ping before the connection
+    /// is considered dead. Requires a [`ClientBuilder::keep_alive_interval`].
+    pub fn keep_alive_timeout(mut self, timeout: Duration) -> Self {
+        self.channel_config.keep_alive_timeout = Some(timeout);
+        self
+    }
+
+    /// Send keepalive pings even if there are no active requests or streams.
+    pub fn keep_alive_while_idle(mut self, enabled: bool) -> Self {
+        self.channel_config.keep_alive_while_idle = Some(enabled);
+        self
+    }
+
+    pub fn tcp_nodelay(mut self, enabled: bool) -> Self {
+        self.channel_config.tcp_nodelay = Some(enabled);
+        self
+    }
+
+    /// Maximum number of concurrent requests on the channel.
+    pub fn concurrency_limit(mut self, limit: usize) -> Self {
+        self.channel_config.concurrency_limit = Some(limit);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.channel_config.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// If enabled the connection is established with the first request instead of
+    /// when the channel is created.
+    pub fn lazy(mut self, lazy: bool) -> Self {
+        self.channel_config.lazy = lazy;
+        self
+    }
+
+    /// Validates the settings and creates the client without connecting to the databroker.
+    pub fn build<C: From<Client>>(self) -> Result<C, BuilderError> {
+        let uri = to_uri(&self.address).map_err(BuilderError::InvalidUri)?;
+        let standby_uris = self
+            .standby_addresses
+            .iter()
+            .map(to_uri)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(BuilderError::InvalidUri)?;
+        self.validate(std::iter::once(&uri).chain(&standby_uris))?;
+
+        let client = Client::new(uri);
+        if !standby_uris.is_empty() {
+            client.set_failover_endpoints(standby_uris, self.failover_policy);
+        }
+        if let Some(token) = self.token {
+            client
+                .set_access_token(token)
+                .map_err(BuilderError::InvalidToken)?;
+        }
+        if let Some(token_provider) = self.token_provider {
+            client.set_shared_token_provider(token_provider);
+        }
+        #[cfg(feature = "tls")]
+        if let Some(tls_config) = self.tls_config {
+            client.set_tls_config(tls_config);
+        }
+        #[cfg(feature = "tls")]
+        if let Some(tls) = self.tls {
+            client.set_tls_config(tls.build().map_err(BuilderError::InvalidTls)?);
+        }
+        client.set_scope_precheck(self.scope_precheck);
+        client.set_reconnect_policy(self.reconnect_policy);
+        client.set_retry_policy(self.retry_policy);
+        client.state().channel_config = self.channel_config;
+        Ok(C::from(client))
+    }
+
+    /// Validates the settings, creates the client and connects it to the databroker.
+    pub async fn connect<C: From<Client>>(self) -> Result<C, ClientError> {
+        let client: Client = self.build().map_err(|err| match err {
+            BuilderError::InvalidToken(err) => ClientError::Token(err),
+            BuilderError::InvalidTls(err) => ClientError::Tls(err),
+            err => ClientError::Connection(format!("{err}")),
+        })?;
+        client.try_connect().await?;
+        Ok(C::from(client))
+    }
+
+    fn validate<'a>(
+        &self,
+        _uris: impl IntoIterator<Item = &'a http::Uri>,
+    ) -> Result<(), BuilderError> {
+        let config = &self.channel_config;
+        for (name, duration) in [
+            ("connect timeout", config.connect_timeout),
+            ("timeout", config.timeout),
+            ("keep alive interval", config.keep_alive_interval),
+            ("keep alive timeout", config.keep_alive_timeout),
+        ] {
+            if duration == Some(Duration::ZERO) {
+                return Err(BuilderError::InvalidOption(format!(
+                    "The {name} must be greater than zero"
+                )));
+            }
+        }
+        if config.keep_alive_interval.is_none()
+            && (config.keep_alive_timeout.is_some() || config.keep_alive_while_idle.is_some())
+        {
+            return Err(BuilderError::InvalidOption(
+                "Keep alive settings require a keep alive interval".to_owned(),
+            ));
+        }
+        if config.concurrency_limit == Some(0) {
+            return Err(BuilderError::InvalidOption(
+                "The concurrency limit must be greater than zero".to_owned(),
+            ));
+        }
+        if let Some(user_agent) = &config.user_agent {
+            http::HeaderValue::try_from(user_agent.as_str()).map_err(|err| {
+                BuilderError::InvalidOption(format!("Invalid user agent {user_agent}: {err}"))
+            })?;
+        }
+        #[cfg(all(feature = "tls", unix))]
+        if (self.tls_config.is_some() || self.tls.is_some())
+            && _uris.into_iter().any(super::uds::is_unix_socket_uri)
+        {
+            return Err(BuilderError::InvalidOption(
+                "TLS is not supported for Unix domain sockets".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test]
+    fn test_build_with_all_options() {
+        let client: Client = ClientBuilder::new("localhost:55555")
+            .access_token("token")
+            .connect_timeout(Duration::from_secs(5))
+            .timeout(Duration::from_secs(2))
+            .keep_alive_interval(Duration::from_secs(10))
+            .keep_alive_timeout(Duration::from_secs(20))
+            .keep_alive_while_idle(true)
+            .tcp_nodelay(true)
+            .concurrency_limit(16)
+            .user_agent("kuksa-test")
+            .lazy(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(client.get_uri(), "http://localhost:55555/");
+        assert!(client
+            .state()
+            .channel_config
+            .apply(Endpoint::from_static("http://localhost"))
+            .is_ok());
+    }
+
+    #[test_case(ClientBuilder::new(""); "empty uri")]
+    #[test_case(ClientBuilder::new("localhost:55555").access_token("invalid\ntoken"); "invalid token")]
+    #[test_case(ClientBuilder::new("localhost:55555").timeout(Duration::ZERO); "zero timeout")]
+    #[test_case(ClientBuilder::new("localhost:55555").connect_timeout(Duration::ZERO); "zero connect timeout")]
+    #[test_case(ClientBuilder::new("localhost:55555").keep_alive_timeout(Duration::from_secs(1)); "keep alive timeout without interval")]
+    #[test_case(ClientBuilder::new("localhost:55555").concurrency_limit(0); "zero concurrency limit")]
+    #[test_case(ClientBuilder::new("localhost:55555").user_agent("invalid\nagent"); "invalid user agent")]
+    #[test_case(ClientBuilder::new("localhost:55555").failover([""], FailoverPolicy::Priority); "empty standby uri")]
+    fn test_build_fails(builder: ClientBuilder) {
+        assert!(builder.build::<Client>().is_err());
+    }
+
+    #[test]
+    fn test_build_with_failover() {
+        let client: Client = ClientBuilder::new("primary:55555")
+            .failover(["standby:55555"], FailoverPolicy::HealthBased)
+            .build()
+            .unwrap();
+
+        assert_eq!(client.get_uri(), "http://primary:55555/");
+        assert_eq!(
+            client.get_endpoints(),
+            vec!["http://primary:55555/", "http://standby:55555/"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_keeps_token_errors() {
+        let result = ClientBuilder::new("localhost:55555")
+            .access_token("invalid\ntoken")
+            .connect::<Client>()
+            .await;
+        assert!(matches!(result, Err(ClientError::Token(_))));
+    }
+
+    #[cfg(feature = "tls")]
+    #[tokio::test]
+    async fn test_connect_keeps_tls_errors() {
+        let result = ClientBuilder::new("localhost:55555")
+            .tls(super::super::tls::TlsConfig::new().ca_certificate_file("/nonexistent/CA.pem"))
+            .connect::<Client>()
+            .await;
+        assert!(matches!(result, Err(ClientError::Tls(_))));
+    }
+
+    #[tokio::test]
+    async fn test_connect_reports_invalid_options_as_connection_errors() {
+        let result = ClientBuilder::new("localhost:55555")
+            .concurrency_limit(0)
+            .connect::<Client>()
+            .await;
+        assert!(matches!(result, Err(ClientError::Connection(_))));
+    }
+
+    #[tokio::test]
+    async fn test_lazy_client_does_not_connect_on_creation() {
+        let client: Client = ClientBuilder::new("localhost:1")
+            .lazy(true)
+            .build()
+            .unwrap();
+        assert!(client.try_connect().await.is_ok());
+    }
+}
@@ -19,8 +19,7 @@ use crate::kuksa::common::types::{
     GetResponseTypeV1, MetadataResponseSDVTypeV1, MetadataResponseTypeV1, MetadataResponseTypeV2,
     MultipleGetResponseTypeV2, MultipleUpdateActuationTypeV2, PathSDVTypeV1, PathTypeV1,
     PathsTypeV2, PublishResponseSDVTypeV1, PublishResponseTypeV1, SensorUpdateSDVTypeV1,
-    SensorUpdateTypeV1, SensorUpdateTypeV2, SubscribeResponseSDVTypeV1, SubscribeResponseTypeV1,
-    SubscribeResponseTypeV2, SubscribeSDVTypeV1, SubscribeTypeV1, UpdateActuationTypeV1,
+    SensorUpdateTypeV1, SensorUpdateTypeV2, SignalRef, SignalsTypeV2, UpdateActuationTypeV1,
 };
 use crate::proto::kuksa::val::v1 as protoV1;
 use crate::proto::kuksa::val::v2 as protoV2;
@@ -82,12 +81,6 @@ impl ConvertToSDV<PathSDVTypeV1> for PathTypeV1 {
     }
 }
 
-impl ConvertToSDV<SubscribeSDVTypeV1> for SubscribeTypeV1 {
-    fn convert_to_sdv(self) -> SubscribeSDVTypeV1 {
-        unimplemented!("SQL queries not supported anymore")
-    }
-}
-
 impl ConvertToSDV<PublishResponseSDVTypeV1> for PublishResponseTypeV1 {
     fn convert_to_sdv(self) -> PublishResponseSDVTypeV1 {
         SDVprotoV1::UpdateDatapointsReply {
@@ -120,21 +113,14 @@ impl ConvertToSDV<GetResponseSDVTypeV1> for GetResponseTypeV1 {
     }
 }
 
-impl ConvertToSDV<SubscribeResponseSDVTypeV1> for SubscribeResponseTypeV1 {
-    fn convert_to_sdv(self) -> SubscribeResponseSDVTypeV1 {
-        unimplemented!("Not possible to convert stream objects!")
-    }
-}
+// Stream objects and SQL queries cannot be converted, neither can kuksa.val.v1
+// metadata which lacks the id. The clients return ClientError::Unsupported instead.
 
 impl ConvertToSDV<ActuateResponseSDVTypeV1> for ActuateResponseTypeV1 {
     fn convert_to_sdv(self) -> ActuateResponseSDVTypeV1 {
-        unimplemented!("Not possible to convert stream objects!")
-    }
-}
-
-impl ConvertToSDV<MetadataResponseSDVTypeV1> for MetadataResponseTypeV1 {
-    fn convert_to_sdv(self) -> MetadataResponseSDVTypeV1 {
-        unimplemented!("Less information in metadata. It makes no sense to convert!")
+        SDVprotoV1::SetDatapointsReply {
+            errors: HashMap::new(),
+        }
     }
 }
 
@@ -242,12 +228,6 @@ impl ConvertToV1<PathTypeV1> for PathSDVTypeV1 {
     }
 }
 
-impl ConvertToV1<SubscribeTypeV1> for SubscribeSDVTypeV1 {
-    fn convert_to_v1(self) -> SubscribeTypeV1 {
-        unimplemented!("SQL queries not supported anymore")
-    }
-}
-
 impl ConvertToV1<PublishResponseTypeV1> for PublishResponseSDVTypeV1 {
     fn convert_to_v1(self) -> PublishResponseTypeV1 {}
 }
@@ -271,18 +251,8 @@ impl ConvertToV1<GetResponseTypeV1> for GetResponseSDVTypeV1 {
     }
 }
 
-// because the type of SubscribeResponse(SDV)TypeV1 and ProvideResponse(SDV)TypeV1 the implementation is only once needed.
-// Hence no impl ConvertToV1<ProvideResponseTypeV1> for ProvideResponseSDVTypeV1{}
-impl ConvertToV1<SubscribeResponseTypeV1> for SubscribeResponseSDVTypeV1 {
-    fn convert_to_v1(self) -> SubscribeResponseTypeV1 {
-        unimplemented!("Not possible to convert stream objects!")
-    }
-}
-
 impl ConvertToV1<ActuateResponseTypeV1> for ActuateResponseSDVTypeV1 {
-    fn convert_to_v1(self) -> ActuateResponseTypeV1 {
-        unimplemented!("Not possible to convert stream objects!")
-    }
+    fn convert_to_v1(self) -> ActuateResponseTypeV1 {}
 }
 
 impl ConvertToV1<MetadataResponseTypeV1> for MetadataResponseSDVTypeV1 {
@@ -420,7 +390,10 @@ impl ConvertToV1<Option<i64>> for Option<SDVprotoV1::ValueRestriction> {
                 Some(typed_value) => match typed_value {
                     SDVprotoV1::value_restriction::TypedValue::Int32(val) => Some(val as i64),
                     SDVprotoV1::value_restriction::TypedValue::Int64(val) => Some(val),
-                    _ => panic!("wrong datatype!"),
+                    other => {
+                        warn!("Ignoring value restriction of unexpected type {other:?}");
+                        None
+                    }
                 },
                 None => None,
             },
@@ -436,7 +409,10 @@ impl ConvertToV1<Option<f64>> for Option<SDVprotoV1::ValueRestriction> {
                 Some(typed_value) => match typed_value {
                     SDVprotoV1::value_restriction::TypedValue::Float(val) => Some(val as f64),
                     SDVprotoV1::value_restriction::TypedValue::Double(val) => Some(val),
-                    _ => panic!("wrong datatype!"),
+                    other => {
+                        warn!("Ignoring value restriction of unexpected type {other:?}");
+                        None
+                    }
                 },
                 None => None,
             },
@@ -452,7 +428,10 @@ impl ConvertToV1<Option<u64>> for Option<SDVprotoV1::ValueRestriction> {
                 Some(typed_value) => match typed_value {
                     SDVprotoV1::value_restriction::TypedValue::Uint32(val) => Some(val as u64),
                     SDVprotoV1::value_restriction::TypedValue::Uint64(val) => Some(val),
-                    _ => panic!("wrong datatype!"),
+                    other => {
+                        warn!("Ignoring value restriction of unexpected type {other:?}");
+                        None
+                    }
                 },
                 None => None,
             },
@@ -585,12 +564,6 @@ impl ConvertToV1<ActuateResponseTypeV1> for ActuateResponseTypeV2 {
     }
 }
 
-impl ConvertToV1<SubscribeResponseTypeV1> for SubscribeResponseTypeV2 {
-    fn convert_to_v1(self) -> SubscribeResponseTypeV1 {
-        unimplemented!("Not possible to convert stream objects!")
-    }
-}
-
 impl ConvertToV1<MetadataResponseTypeV1> for MetadataResponseTypeV2 {
     fn convert_to_v1(self) -> MetadataResponseTypeV1 {
         let transformed_vec = self
@@ -629,9 +602,8 @@ impl ConvertToV1<protoV1::Metadata> for protoV2::Metadata {
                                         )),
                                     })
                                 }
-                                protoV2::value::TypedValue::Bool(_) => {
-                                    panic!("Boolean values are not supported in ValueRestriction")
-                                }
+                                // Boolean values are not supported in ValueRestriction
+                                protoV2::value::TypedValue::Bool(_) => None,
                                 protoV2::value::TypedValue::Int32(val) => {
                                     Some(protoV1::ValueRestriction {
                                         r#type: Some(protoV1::value_restriction::Type::Signed(
@@ -711,9 +683,7 @@ impl ConvertToV1<protoV1::Metadata> for protoV2::Metadata {
                                         )),
                                     })
                                 }
-                                protoV2::value::TypedValue::BoolArray(_) => {
-                                    panic!("Boolean arrays are not supported in ValueRestriction")
-                                }
+                                protoV2::value::TypedValue::BoolArray(_) => None,
                                 protoV2::value::TypedValue::Int32Array(int32_array) => {
                                     Some(protoV1::ValueRestriction {
                                         r#type: Some(protoV1::value_restriction::Type::Signed(
@@ -815,7 +785,10 @@ impl ConvertToV1<Option<i64>> for Option<protoV2::Value> {
                 Some(typed_value) => match typed_value {
                     protoV2::value::TypedValue::Int32(val) => Some(val as i64),
                     protoV2::value::TypedValue::Int64(val) => Some(val),
-                    _ => panic!("wrong datatype!"),
+                    other => {
+                        warn!("Ignoring value restriction of unexpected type {other:?}");
+                        None
+                    }
                 },
                 None => None,
             },
@@ -831,7 +804,10 @@ impl ConvertToV1<Option<f64>> for Option<protoV2::Value> {
                 Some(typed_value) => match typed_value {
                     protoV2::value::TypedValue::Float(val) => Some(val as f64),
                     protoV2::value::TypedValue::Double(val) => Some(val),
-                    _ => panic!("wrong datatype!"),
+                    other => {
+                        warn!("Ignoring value restriction of unexpected type {other:?}");
+                        None
+                    }
                 },
                 None => None,
             },
@@ -847,7 +823,10 @@ impl ConvertToV1<Option<u64>> for Option<protoV2::Value> {
                 Some(typed_value) => match typed_value {
                     protoV2::value::TypedValue::Uint32(val) => Some(val as u64),
                     protoV2::value::TypedValue::Uint64(val) => Some(val),
-                    _ => panic!("wrong datatype!"),
+                    other => {
+                        warn!("Ignoring value restriction of unexpected type {other:?}");
+                        None
+                    }
                 },
                 None => None,
             },
@@ -1063,17 +1042,17 @@ impl ConvertToV2<MetadataTypeV2> for PathTypeV1 {
     }
 }
 
-impl ConvertToV2<SubscribeResponseTypeV2> for SubscribeResponseTypeV1 {
-    fn convert_to_v2(self) -> SubscribeResponseTypeV2 {
-        unimplemented!("Not possible to convert stream objects!")
+impl ConvertToV2<SignalsTypeV2> for PathTypeV1 {
+    fn convert_to_v2(self) -> SignalsTypeV2 {
+        self.into_iter().map(SignalRef::Path).collect()
     }
 }
 
 impl ConvertToV2<MultipleUpdateActuationTypeV2> for UpdateActuationTypeV1 {
     fn convert_to_v2(self) -> MultipleUpdateActuationTypeV2 {
-        let transformed_map: HashMap<String, protoV2::Value> = self
-            .iter()
-            .map(|(key, value)| (key.clone(), value.clone().convert_to_v2()))
+        let transformed_map: MultipleUpdateActuationTypeV2 = self
+            .into_iter()
+            .map(|(key, value)| (SignalRef::Path(key), value.convert_to_v2()))
             .collect();
         transformed_map
     }
@@ -1162,6 +1141,17 @@ mod tests {
         assert!(converted.errors.is_empty());
     }
 
+    // impl ConvertToSDV<ActuateResponseSDVTypeV1> for ActuateResponseTypeV1 {}
+    // impl ConvertToV1<ActuateResponseTypeV1> for ActuateResponseSDVTypeV1 {}
+    #[test]
+    fn test_convert_actuate_response_v1() {
+        let response: ActuateResponseTypeV1 = ();
+        let converted: ActuateResponseSDVTypeV1 = response.convert_to_sdv();
+        assert!(converted.errors.is_empty());
+
+        let _: ActuateResponseTypeV1 = converted.convert_to_v1();
+    }
+
     // impl ConvertToSDV<GetResponseSDVTypeV1> for GetResponseTypeV1 {}
     // only check one possibility since test for impl ConvertToSDV<Option<SDVprotoV1::datapoint::Value>> for protoV1::Datapoint {} covers that
     #[test]
@@ -1477,7 +1467,7 @@ mod tests {
 
     #[test]
     fn test_convert_to_v1_metadata_sdv() {
-        let metadata_cases = vec![
+        let metadata_cases = [
             (
                 SDVprotoV1::Metadata {
                     data_type: SDVprotoV1::DataType::Int32.into(),
@@ -1933,6 +1923,16 @@ mod tests {
         assert_eq!(result, expected_metadata_response);
     }
 
+    // impl ConvertToV1<Option<i64>> for Option<protoV2::Value> {}
+    #[test]
+    fn test_convert_to_v1_restriction_of_unexpected_type() {
+        let max = Some(protoV2::Value {
+            typed_value: Some(protoV2::value::TypedValue::String("100".to_string())),
+        });
+        let converted: Option<i64> = max.convert_to_v1();
+        assert_eq!(converted, None);
+    }
+
     // impl ConvertToV1<protoV1::Metadata> for protoV2::Metadata {}
     #[test]
     fn test_convert_to_v1_metadata_v2() {
@@ -2605,6 +2605,17 @@ mod tests {
         );
     }
 
+    // impl ConvertToV2<SignalsTypeV2> for PathTypeV1 {}
+    #[test]
+    fn test_convert_to_v2_signals_type_v1() {
+        let paths: PathTypeV1 = vec!["Vehicle.Engine.Speed".to_string()];
+        let result: SignalsTypeV2 = paths.convert_to_v2();
+        assert_eq!(
+            result,
+            vec![SignalRef::Path("Vehicle.Engine.Speed".to_string())]
+        );
+    }
+
     // impl ConvertToV2<MetadataTypeV2> for PathTypeV1 {}
     #[test]
     fn test_convert_to_v2_metadata_v1() {
@@ -2630,7 +2641,7 @@ mod tests {
         );
         let result: MultipleUpdateActuationTypeV2 = update_map.convert_to_v2();
         assert_eq!(
-            result.get("Vehicle.Engine.Speed"),
+            result.get(&SignalRef::from("Vehicle.Engine.Speed")),
             Some(&protoV2::Value {
                 typed_value: Some(protoV2::value::TypedValue::Int32(100))
             })
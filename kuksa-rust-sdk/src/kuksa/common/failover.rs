@@ -0,0 +1,179 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+//! Selection of the databroker endpoint if the [`Client`](super::Client) is
+//! configured with standby endpoints.
+//!
+//! The endpoint is chosen whenever the channel is (re-)established, an endpoint
+//! which cannot be connected to is skipped. Lazy channels connect with the first
+//! request, so failing over between them requires the health based policy.
+
+use http::Uri;
+use std::time::Duration;
+use tonic::transport::Channel;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FailoverPolicy {
+    /// Connect to the first reachable endpoint in the configured order, e.g. the
+    /// primary databroker and the standby only while the primary is down.
+    ///
+    /// The order is only applied when the channel is (re-)established: a client
+    /// which failed over to the standby stays connected to it while it is healthy
+    /// and returns to the primary once the connection to the standby is lost.
+    #[default]
+    Priority,
+    /// Like [`FailoverPolicy::Priority`], but read-only calls are distributed over
+    /// all endpoints in turn.
+    RoundRobin,
+    /// Connect to the endpoint with the fewest consecutive failures, ties are
+    /// resolved by the latency of the last successful connect.
+    HealthBased,
+}
+
+#[derive(Debug, Clone, Default)]
+struct EndpointHealth {
+    consecutive_failures: u32,
+    latency: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Endpoints {
+    uris: Vec<Uri>,
+    policy: FailoverPolicy,
+    active: usize,
+    health: Vec<EndpointHealth>,
+    read_channels: Vec<Option<Channel>>,
+    next_read: usize,
+}
+
+impl Endpoints {
+    /// `uris` must contain the active endpoint first.
+    pub fn new(uris: Vec<Uri>, policy: FailoverPolicy) -> Self {
+        Endpoints {
+            health: vec![EndpointHealth::default(); uris.len()],
+            read_channels: vec![None; uris.len()],
+            uris,
+            policy,
+            active: 0,
+            next_read: 0,
+        }
+    }
+
+    pub fn uris(&self) -> &[Uri] {
+        &self.uris
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    pub fn set_active(&mut self, index: usize) {
+        self.active = index;
+    }
+
+    /// Order in which the endpoints are tried when (re-)establishing the channel.
+    pub fn connect_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.uris.len()).collect();
+        if self.policy == FailoverPolicy::HealthBased {
+            // Stable sort, so endpoints with the same health keep their priority
+            order.sort_by_key(|index| {
+                let health = &self.health[*index];
+                (
+                    health.consecutive_failures,
+                    health.latency.unwrap_or(Duration::MAX),
+                )
+            });
+        }
+        order
+    }
+
+    pub fn record_success(&mut self, index: usize, latency: Duration) {
+        self.health[index] = EndpointHealth {
+            consecutive_failures: 0,
+            latency: Some(latency),
+        };
+    }
+
+    pub fn record_failure(&mut self, index: usize) {
+        let health = &mut self.health[index];
+        health.consecutive_failures = health.consecutive_failures.saturating_add(1);
+        self.read_channels[index] = None;
+    }
+
+    /// Returns the endpoint for the next read-only call if they are distributed
+    /// over all endpoints.
+    pub fn next_read_endpoint(&mut self) -> Option<usize> {
+        if self.policy != FailoverPolicy::RoundRobin || self.uris.len() < 2 {
+            return None;
+        }
+        let index = self.next_read % self.uris.len();
+        self.next_read = index + 1;
+        Some(index)
+    }
+
+    pub fn read_channel(&self, index: usize) -> Option<Channel> {
+        self.read_channels[index].clone()
+    }
+
+    pub fn set_read_channel(&mut self, index: usize, channel: Channel) {
+        self.read_channels[index] = Some(channel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoints(policy: FailoverPolicy) -> Endpoints {
+        Endpoints::new(
+            vec![
+                Uri::from_static("http://primary:55555"),
+                Uri::from_static("http://standby:55555"),
+                Uri::from_static("http://backup:55555"),
+            ],
+            policy,
+        )
+    }
+
+    #[test]
+    fn test_priority_keeps_configured_order() {
+        let mut endpoints = endpoints(FailoverPolicy::Priority);
+        endpoints.record_failure(0);
+        assert_eq!(endpoints.connect_order(), vec![0, 1, 2]);
+        assert_eq!(endpoints.next_read_endpoint(), None);
+    }
+
+    #[test]
+    fn test_health_based_prefers_healthy_endpoints() {
+        let mut endpoints = endpoints(FailoverPolicy::HealthBased);
+        assert_eq!(endpoints.connect_order(), vec![0, 1, 2]);
+
+        endpoints.record_failure(0);
+        endpoints.record_success(2, Duration::from_millis(5));
+        endpoints.record_success(1, Duration::from_millis(10));
+        assert_eq!(endpoints.connect_order(), vec![2, 1, 0]);
+
+        endpoints.record_success(0, Duration::from_millis(1));
+        assert_eq!(endpoints.connect_order(), vec![0, 2, 1]);
+    }
+
+    #[test]
+    fn test_round_robin_rotates_read_endpoints() {
+        let mut endpoints = endpoints(FailoverPolicy::RoundRobin);
+        assert_eq!(endpoints.connect_order(), vec![0, 1, 2]);
+        let reads: Vec<Option<usize>> = (0..4).map(|_| endpoints.next_read_endpoint()).collect();
+        assert_eq!(reads, vec![Some(0), Some(1), Some(2), Some(0)]);
+    }
+}
@@ -0,0 +1,288 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+//! Background monitoring of the connection to the databroker.
+//!
+//! The monitor probes the databroker with the gRPC health protocol
+//! (`grpc.health.v1.Health/Check`) and falls back to `kuksa.val.v2.VAL/GetServerInfo`
+//! if the databroker does not implement it. The result of every probe is reported
+//! as [`ConnectionState`] of the monitored [`Client`].
+
+use super::{is_channel_dead, AuthenticatedChannel, Client, ClientError, ConnectionState};
+use crate::proto::kuksa::val::v2::{val_client::ValClient, GetServerInfoRequest};
+use log::{debug, warn};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// Shortest time between two probes, shorter intervals are raised to it.
+const MIN_PROBE_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Settings of the [`HealthMonitor`].
+#[derive(Debug, Clone)]
+pub struct HealthMonitorConfig {
+    /// Time between two probes, at least 1 millisecond.
+    pub interval: Duration,
+    /// Probes not answered within this time are considered failed and the
+    /// channel is re-established.
+    pub timeout: Duration,
+    /// Round-trip latency above which the connection is reported as
+    /// [`ConnectionState::Degraded`].
+    pub degraded_latency: Duration,
+}
+
+impl Default for HealthMonitorConfig {
+    fn default() -> Self {
+        HealthMonitorConfig {
+            interval: Duration::from_secs(5),
+            timeout: Duration::from_secs(2),
+            degraded_latency: Duration::from_millis(500),
+        }
+    }
+}
+
+impl HealthMonitorConfig {
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval.max(MIN_PROBE_INTERVAL);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_degraded_latency(mut self, degraded_latency: Duration) -> Self {
+        self.degraded_latency = degraded_latency;
+        self
+    }
+}
+
+/// Handle of a running health monitor, the monitor is stopped when it is dropped.
+#[derive(Debug)]
+pub struct HealthMonitor {
+    latency: watch::Receiver<Option<Duration>>,
+    task: JoinHandle<()>,
+}
+
+impl HealthMonitor {
+    /// Round-trip latency of the last successful probe, `None` if there was none yet
+    /// or the last probe failed.
+    pub fn latency(&self) -> Option<Duration> {
+        *self.latency.borrow()
+    }
+}
+
+impl Drop for HealthMonitor {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+// Messages of grpc.health.v1, defined here to not depend on the proto files
+#[derive(Clone, PartialEq, prost::Message)]
+struct HealthCheckRequest {
+    #[prost(string, tag = "1")]
+    service: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct HealthCheckResponse {
+    #[prost(int32, tag = "1")]
+    status: i32,
+}
+
+const SERVING: i32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Probe {
+    Health,
+    ServerInfo,
+}
+
+#[derive(Debug, PartialEq)]
+enum ProbeResult {
+    Serving,
+    NotServing,
+    Unimplemented,
+}
+
+async fn health_check(channel: AuthenticatedChannel) -> Result<ProbeResult, tonic::Status> {
+    let mut grpc = tonic::client::Grpc::new(channel);
+    // Keeps the transport error as source to recognize the failed connection
+    grpc.ready()
+        .await
+        .map_err(|err| tonic::Status::from_error(err.into()))?;
+    let path = http::uri::PathAndQuery::from_static("/grpc.health.v1.Health/Check");
+    let result = grpc
+        .unary::<_, HealthCheckResponse, _>(
+            tonic::Request::new(HealthCheckRequest::default()),
+            path,
+            tonic::codec::ProstCodec::default(),
+        )
+        .await;
+    match result {
+        Ok(response) if response.get_ref().status == SERVING => Ok(ProbeResult::Serving),
+        Ok(_) => Ok(ProbeResult::NotServing),
+        Err(status) if status.code() == tonic::Code::Unimplemented => {
+            Ok(ProbeResult::Unimplemented)
+        }
+        Err(status) => Err(status),
+    }
+}
+
+async fn server_info(channel: AuthenticatedChannel) -> Result<ProbeResult, tonic::Status> {
+    ValClient::new(channel)
+        .get_server_info(GetServerInfoRequest {})
+        .await?;
+    Ok(ProbeResult::Serving)
+}
+
+/// Returns the state to report for a probe answered after `latency`.
+fn probed_state(
+    result: &Result<ProbeResult, ClientError>,
+    latency: Duration,
+    config: &HealthMonitorConfig,
+) -> Option<ConnectionState> {
+    match result {
+        Ok(ProbeResult::Serving) if latency <= config.degraded_latency => {
+            Some(ConnectionState::Connected)
+        }
+        Ok(ProbeResult::Serving) | Ok(ProbeResult::NotServing) => {
+            Some(ConnectionState::Degraded { latency })
+        }
+        // Any answer of the databroker proves that it is reachable, statuses with
+        // a source have been created by the transport instead
+        Err(ClientError::Status(status))
+            if !is_channel_dead(status) && std::error::Error::source(status).is_none() =>
+        {
+            Some(ConnectionState::Connected)
+        }
+        _ => None,
+    }
+}
+
+async fn run(
+    client: Client,
+    config: HealthMonitorConfig,
+    latency_sender: watch::Sender<Option<Duration>>,
+) {
+    let mut probe = Probe::Health;
+    // The field may have been set without with_interval, a zero interval panics
+    let mut interval = tokio::time::interval(config.interval.max(MIN_PROBE_INTERVAL));
+    loop {
+        interval.tick().await;
+        if let Err(err) = client.get_channel().await {
+            debug!("Health probe skipped: {err}");
+            let _ = latency_sender.send(None);
+            continue;
+        }
+
+        let started = Instant::now();
+        let call = client.call(|channel| async move {
+            match probe {
+                Probe::Health => health_check(channel).await,
+                Probe::ServerInfo => server_info(channel).await,
+            }
+        });
+        let result = match tokio::time::timeout(config.timeout, call).await {
+            Ok(Ok(ProbeResult::Unimplemented)) => {
+                debug!("gRPC health protocol not available, probing with GetServerInfo");
+                probe = Probe::ServerInfo;
+                interval.reset_immediately();
+                continue;
+            }
+            Ok(result) => result,
+            Err(_) => {
+                warn!(
+                    "Health probe of {} timed out after {:?}",
+                    client.get_uri(),
+                    config.timeout
+                );
+                client.disconnect();
+                let _ = latency_sender.send(None);
+                continue;
+            }
+        };
+        let latency = started.elapsed();
+
+        match probed_state(&result, latency, &config) {
+            Some(state) => {
+                debug!("Health probe answered after {latency:?}");
+                let _ = latency_sender.send(Some(latency));
+                client.set_connection_state(state);
+            }
+            None => {
+                // Dead channels have already been dropped by the client
+                let _ = latency_sender.send(None);
+            }
+        }
+    }
+}
+
+impl Client {
+    /// Starts a background task probing the databroker every [`HealthMonitorConfig::interval`].
+    ///
+    /// The monitor updates the connection state of this client and all its clones with
+    /// [`ConnectionState::Degraded`] if the databroker answers slowly and re-establishes
+    /// the channel if it does not answer at all.
+    pub fn start_health_monitor(&self, config: HealthMonitorConfig) -> HealthMonitor {
+        let (latency_sender, latency) = watch::channel(None);
+        let task = tokio::spawn(run(self.clone(), config, latency_sender));
+        HealthMonitor { latency, task }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(Ok(ProbeResult::Serving), 10, Some(ConnectionState::Connected); "fast answer")]
+    #[test_case(Ok(ProbeResult::Serving), 900, Some(ConnectionState::Degraded { latency: Duration::from_millis(900) }); "slow answer")]
+    #[test_case(Ok(ProbeResult::NotServing), 10, Some(ConnectionState::Degraded { latency: Duration::from_millis(10) }); "not serving")]
+    #[test_case(Err(ClientError::Status(tonic::Status::permission_denied("denied"))), 10, Some(ConnectionState::Connected); "status from databroker")]
+    #[test_case(Err(ClientError::Connection("refused".to_owned())), 10, None; "connection failed")]
+    #[test_case(Err(ClientError::Status(tonic::Status::from_error(Box::new(std::io::Error::other("connection reset"))))), 10, None; "transport error")]
+    fn test_probed_state(
+        result: Result<ProbeResult, ClientError>,
+        latency_ms: u64,
+        expected: Option<ConnectionState>,
+    ) {
+        let config = HealthMonitorConfig::default();
+        assert_eq!(
+            probed_state(&result, Duration::from_millis(latency_ms), &config),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_zero_interval_is_raised() {
+        let config = HealthMonitorConfig::default().with_interval(Duration::ZERO);
+        assert_eq!(config.interval, MIN_PROBE_INTERVAL);
+    }
+
+    #[tokio::test]
+    async fn test_monitor_survives_zero_interval() {
+        let client = Client::new(http::Uri::from_static("http://127.0.0.1:1"));
+        let config = HealthMonitorConfig {
+            interval: Duration::ZERO,
+            ..HealthMonitorConfig::default()
+        };
+        let monitor = client.start_health_monitor(config);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!monitor.task.is_finished());
+    }
+}
@@ -0,0 +1,305 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+//! Client-side inspection of the JWT used to access the databroker.
+//!
+//! The token is only decoded, its signature is NOT verified. The result is meant
+//! for diagnostics and to avoid calls which are going to be rejected anyway, the
+//! databroker stays responsible for enforcing the permissions.
+
+use super::TokenError;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    Read,
+    Provide,
+    Actuate,
+    Create,
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Permission::Read => f.pad("read"),
+            Permission::Provide => f.pad("provide"),
+            Permission::Actuate => f.pad("actuate"),
+            Permission::Create => f.pad("create"),
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = TokenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Permission::Read),
+            "provide" => Ok(Permission::Provide),
+            "actuate" => Ok(Permission::Actuate),
+            "create" => Ok(Permission::Create),
+            _ => Err(TokenError::MalformedTokenError(format!(
+                "Unknown permission in scope: {s}"
+            ))),
+        }
+    }
+}
+
+impl Permission {
+    /// Returns true if a grant of this permission includes `other`.
+    /// Providing and actuating include reading, creating includes everything.
+    pub fn includes(&self, other: Permission) -> bool {
+        *self == other
+            || *self == Permission::Create
+            || (other == Permission::Read && *self != Permission::Read)
+    }
+}
+
+/// Single entry of the KUKSA scope claim, e.g. `read` or `actuate:Vehicle.Body.*`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grant {
+    pub permission: Permission,
+    /// Path pattern the grant is restricted to, `None` grants access to all paths.
+    pub path: Option<String>,
+}
+
+impl Grant {
+    pub fn allows(&self, permission: Permission, path: &str) -> bool {
+        self.permission.includes(permission)
+            && match &self.path {
+                Some(pattern) => matches_path(pattern, path),
+                None => true,
+            }
+    }
+}
+
+/// Returns true if `path` matches the VSS path `pattern`, following the glob rules
+/// of the databroker.
+///
+/// A pattern without wildcards matches the path itself and everything below it, so
+/// `Vehicle.Cabin` matches `Vehicle.Cabin.Sunroof.Position`. A `*` segment matches
+/// exactly one segment, a `**` segment any number of segments. A trailing `*` segment
+/// also matches all signals below, so `Vehicle.Body.*` matches
+/// `Vehicle.Body.Lights.Beam.Low.IsOn`.
+pub fn matches_path(pattern: &str, path: &str) -> bool {
+    if !pattern.contains('*') {
+        return path
+            .strip_prefix(pattern)
+            .is_some_and(|below| below.is_empty() || below.starts_with('.'));
+    }
+    let pattern: Vec<&str> = pattern.split('.').collect();
+    let path: Vec<&str> = path.split('.').collect();
+    matches_segments(&pattern, &path)
+}
+
+fn matches_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match (pattern.first(), path.first()) {
+        (None, None) => true,
+        (Some(&"**"), _) => {
+            matches_segments(&pattern[1..], path)
+                || (!path.is_empty() && matches_segments(pattern, &path[1..]))
+        }
+        (Some(&"*"), Some(_)) if pattern.len() == 1 => true,
+        (Some(segment), Some(path_segment)) if *segment == "*" || segment == path_segment => {
+            matches_segments(&pattern[1..], &path[1..])
+        }
+        _ => false,
+    }
+}
+
+/// The parsed KUKSA `scope` claim.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Scopes {
+    pub grants: Vec<Grant>,
+}
+
+impl FromStr for Scopes {
+    type Err = TokenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let grants = s
+            .split_whitespace()
+            .map(|entry| match entry.split_once(':') {
+                Some((permission, path)) => Ok(Grant {
+                    permission: permission.parse()?,
+                    path: Some(path.to_owned()),
+                }),
+                None => Ok(Grant {
+                    permission: entry.parse()?,
+                    path: None,
+                }),
+            })
+            .collect::<Result<Vec<Grant>, TokenError>>()?;
+        Ok(Scopes { grants })
+    }
+}
+
+impl Scopes {
+    pub fn allows(&self, permission: Permission, path: &str) -> bool {
+        self.grants
+            .iter()
+            .any(|grant| grant.allows(permission, path))
+    }
+}
+
+/// Claims of a KUKSA access token.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Claims {
+    pub sub: Option<String>,
+    pub iss: Option<String>,
+    pub aud: Vec<String>,
+    pub iat: Option<u64>,
+    pub exp: Option<u64>,
+    pub scope: Scopes,
+}
+
+impl Claims {
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.exp
+            .and_then(|exp| UNIX_EPOCH.checked_add(Duration::from_secs(exp)))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        match self.expires_at() {
+            Some(expires_at) => SystemTime::now() >= expires_at,
+            None => false,
+        }
+    }
+}
+
+/// Decodes the claims of `token` without verifying its signature.
+/// A leading `Bearer ` is ignored.
+pub fn decode(token: &str) -> Result<Claims, TokenError> {
+    let malformed = |msg: String| TokenError::MalformedTokenError(msg);
+
+    let token = token.strip_prefix("Bearer ").unwrap_or(token).trim();
+    let payload = token
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| malformed("Token is not a JWT".to_owned()))?;
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .map_err(|err| malformed(format!("Failed to decode token payload: {err}")))?;
+    let claims: serde_json::Value = serde_json::from_slice(&payload)
+        .map_err(|err| malformed(format!("Failed to parse token payload: {err}")))?;
+
+    let string_claim = |name: &str| claims.get(name).and_then(|v| v.as_str()).map(str::to_owned);
+    let aud = match claims.get("aud") {
+        Some(serde_json::Value::String(aud)) => vec![aud.clone()],
+        Some(serde_json::Value::Array(auds)) => auds
+            .iter()
+            .filter_map(|aud| aud.as_str().map(str::to_owned))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let scope = match string_claim("scope") {
+        Some(scope) => scope.parse()?,
+        None => Scopes::default(),
+    };
+
+    Ok(Claims {
+        sub: string_claim("sub"),
+        iss: string_claim("iss"),
+        aud,
+        iat: claims.get("iat").and_then(|v| v.as_u64()),
+        exp: claims.get("exp").and_then(|v| v.as_u64()),
+        scope,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn encode(claims: &str) -> String {
+        format!(
+            "eyJhbGciOiJub25lIn0.{}.signature",
+            URL_SAFE_NO_PAD.encode(claims)
+        )
+    }
+
+    #[test]
+    fn test_decode() {
+        let token = encode(
+            r#"{"sub":"local dev","iss":"createToken.py","aud":["kuksa.val"],"iat":1516239022,"exp":1767225599,"scope":"read actuate:Vehicle.Body.*"}"#,
+        );
+
+        let claims = decode(&format!("Bearer {token}")).unwrap();
+        assert_eq!(claims.sub.as_deref(), Some("local dev"));
+        assert_eq!(claims.iss.as_deref(), Some("createToken.py"));
+        assert_eq!(claims.aud, vec!["kuksa.val".to_owned()]);
+        assert_eq!(claims.iat, Some(1516239022));
+        assert_eq!(claims.exp, Some(1767225599));
+        assert_eq!(
+            claims.scope.grants,
+            vec![
+                Grant {
+                    permission: Permission::Read,
+                    path: None
+                },
+                Grant {
+                    permission: Permission::Actuate,
+                    path: Some("Vehicle.Body.*".to_owned())
+                }
+            ]
+        );
+    }
+
+    #[test_case("not a jwt"; "not a jwt")]
+    #[test_case("header.!!!.signature"; "invalid base64")]
+    #[test_case("header.bm90IGpzb24.signature"; "invalid json")]
+    fn test_decode_fails(token: &str) {
+        assert!(decode(token).is_err());
+    }
+
+    #[test]
+    fn test_decode_fails_for_unknown_permission() {
+        assert!(decode(&encode(r#"{"scope":"fly:Vehicle"}"#)).is_err());
+    }
+
+    #[test_case("Vehicle.Speed", "Vehicle.Speed", true; "exact match")]
+    #[test_case("Vehicle.Speed", "Vehicle.AverageSpeed", false; "no match")]
+    #[test_case("Vehicle.Cabin", "Vehicle.Cabin.Sunroof.Position", true; "branch matches subtree")]
+    #[test_case("Vehicle.Cabin", "Vehicle.CabinTemperature", false; "branch matches whole segments")]
+    #[test_case("Vehicle.Cabin.Sunroof", "Vehicle.Cabin", false; "branch does not match parent")]
+    #[test_case("Vehicle.*", "Vehicle.Speed", true; "trailing wildcard")]
+    #[test_case("Vehicle.*", "Vehicle.Body.Raindetection.Intensity", true; "trailing wildcard matches branch")]
+    #[test_case("Vehicle.*.IsOpen", "Vehicle.Trunk.IsOpen", true; "single segment wildcard")]
+    #[test_case("Vehicle.*.IsOpen", "Vehicle.Cabin.Door.IsOpen", false; "single segment wildcard matches one segment")]
+    #[test_case("Vehicle.**.IsOpen", "Vehicle.Cabin.Door.IsOpen", true; "multi segment wildcard")]
+    #[test_case("Vehicle.Body.*", "Vehicle.Speed", false; "wildcard other branch")]
+    fn test_matches_path(pattern: &str, path: &str, expected: bool) {
+        assert_eq!(matches_path(pattern, path), expected);
+    }
+
+    #[test_case("read", Permission::Read, "Vehicle.Speed", true; "read all")]
+    #[test_case("read", Permission::Provide, "Vehicle.Speed", false; "read does not provide")]
+    #[test_case("actuate provide", Permission::Read, "Vehicle.Speed", true; "write includes read")]
+    #[test_case("actuate:Vehicle.Body.*", Permission::Actuate, "Vehicle.Body.Trunk.Rear.IsOpen", true; "actuate branch")]
+    #[test_case("actuate:Vehicle.Body.*", Permission::Actuate, "Vehicle.Cabin.Seat.Row1.Pos", false; "actuate other branch")]
+    #[test_case("actuate:Vehicle.Cabin", Permission::Actuate, "Vehicle.Cabin.Sunroof.Position", true; "actuate branch without wildcard")]
+    #[test_case("actuate:Vehicle.Cabin", Permission::Actuate, "Vehicle.Body.Trunk.Rear.IsOpen", false; "actuate other branch without wildcard")]
+    #[test_case("create", Permission::Provide, "Vehicle.Speed", true; "create includes all")]
+    #[test_case("", Permission::Read, "Vehicle.Speed", false; "no scope")]
+    fn test_scopes_allow(scope: &str, permission: Permission, path: &str, expected: bool) {
+        let scopes: Scopes = scope.parse().unwrap();
+        assert_eq!(scopes.allows(permission, path), expected);
+    }
+}
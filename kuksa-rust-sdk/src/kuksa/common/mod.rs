@@ -13,37 +13,184 @@
  * ******************************************************************************
  */
 
+pub mod builder;
 pub mod conversion;
+pub mod failover;
+pub mod health;
+pub mod jwt;
+pub mod options;
+pub mod reconnect;
+pub mod retry;
+pub mod tls;
+pub mod token;
 pub mod types;
+#[cfg(unix)]
+pub mod uds;
 
 use crate::proto::kuksa::val::v1::Error;
+use failover::{Endpoints, FailoverPolicy};
 use http::Uri;
-use log::info;
+use log::{debug, info, warn};
+use options::{CallOptions, RequestOptions, REQUEST_OPTIONS};
+use reconnect::ReconnectPolicy;
+use retry::RetryPolicy;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+use token::TokenProvider;
 use tokio_stream::wrappers::BroadcastStream;
+use tonic::service::interceptor::InterceptedService;
 use tonic::{async_trait, transport::Channel};
 
-#[derive(Debug)]
+/// Channel inserting the access token of the [`Client`] into every request.
+pub type AuthenticatedChannel = InterceptedService<Channel, AuthInterceptor>;
+
+const CONNECTION_STATE_CAPACITY: usize = 16;
+
+/// Connection to the databroker.
+///
+/// All clones share the channel, the access token and the connection state, so one
+/// client can be handed to several tasks which multiplex their calls over a single
+/// HTTP/2 connection.
+#[derive(Debug, Clone)]
 pub struct Client {
+    shared: Arc<Shared>,
+    // Not shared, every handle can use its own options
+    call_options: CallOptions,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    // Read by the interceptor with every request, so cached stubs use refreshed tokens
+    token: Arc<RwLock<Option<tonic::metadata::AsciiMetadataValue>>>,
+    // Concurrent calls wait for a single (re-)connect or token refresh
+    connecting: tokio::sync::Mutex<()>,
+    refreshing: tokio::sync::Mutex<()>,
+    connection_state: tokio::sync::watch::Sender<ConnectionState>,
+    connection_state_subs: tokio::sync::broadcast::Sender<ConnectionState>,
+}
+
+#[derive(Debug)]
+struct State {
     uri: Uri,
-    token: Option<tonic::metadata::AsciiMetadataValue>,
+    endpoints: Option<Endpoints>,
+    token_provider: Option<Arc<dyn TokenProvider>>,
+    token_refresh_at: Option<SystemTime>,
+    scope_precheck: bool,
     #[cfg(feature = "tls")]
     tls_config: Option<tonic::transport::ClientTlsConfig>,
-    channel: Option<tonic::transport::Channel>,
-    connection_state_subs: Option<tokio::sync::broadcast::Sender<ConnectionState>>,
+    channel: Option<Channel>,
+    // Number of channels established so far
+    connections: u64,
+    // Typed stubs on top of `channel`, dropped together with it
+    stubs: HashMap<TypeId, Box<dyn Any + Send>>,
+    reconnect_policy: Option<ReconnectPolicy>,
+    retry_policy: Option<RetryPolicy>,
+    channel_config: builder::ChannelConfig,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CallKind {
+    Unary,
+    ReadOnly,
+    Streaming,
+}
+
+/// Typed client of a gRPC service, which is created once per channel by the
+/// [`Client`] and reused for all calls made with [`Client::call_stub`].
+pub trait ServiceStub: Clone + Send + 'static {
+    fn from_channel(channel: AuthenticatedChannel) -> Self;
+}
+
+impl ServiceStub for AuthenticatedChannel {
+    fn from_channel(channel: AuthenticatedChannel) -> Self {
+        channel
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ConnectionState {
+    /// The channel is being established for the first time or after it was dropped.
+    Connecting,
     Connected,
+    /// The databroker is reachable but answers slowly, reported by the
+    /// [`HealthMonitor`](health::HealthMonitor).
+    Degraded {
+        latency: Duration,
+    },
+    /// Connecting failed and is retried, `attempt` counts all attempts so far.
+    Reconnecting {
+        attempt: u32,
+    },
     Disconnected,
+    /// The channel has been established to another endpoint than before, the uri of
+    /// the now active endpoint is passed. Followed by [`ConnectionState::Connected`].
+    EndpointChanged(String),
+}
+
+/// Request rejected by the databroker, carried by the variants of [`ClientError`]
+/// for the documented outcomes of an operation.
+#[derive(Debug, Clone)]
+pub struct RequestError {
+    /// Name of the client operation, e.g. `get_value`.
+    pub operation: &'static str,
+    /// Paths or ids of the signals addressed by the request, empty if the
+    /// operation does not address signals.
+    pub signals: Vec<String>,
+    pub status: tonic::Status,
+}
+
+impl std::fmt::Display for RequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut msg = format!("{} failed", self.operation);
+        if !self.signals.is_empty() {
+            msg.push_str(&format!(" for {}", self.signals.join(", ")));
+        }
+        f.pad(&format!(
+            "{msg}: {} ({:?})",
+            self.status.message(),
+            self.status.code()
+        ))
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum ClientError {
     Connection(String),
+    /// Status without a dedicated variant or of a call made with [`Client::call`].
     Status(tonic::Status),
     Function(Vec<Error>),
+    Token(TokenError),
+    NotFound(RequestError),
+    /// Denied by the databroker or by the scope pre-check of the client.
+    PermissionDenied(RequestError),
+    Unauthenticated(RequestError),
+    InvalidArgument(RequestError),
+    /// The databroker is shutting down or, for actuations, no provider is available.
+    Unavailable(RequestError),
+    AlreadyExists(RequestError),
+    DataLoss(RequestError),
+    Tls(tls::TlsError),
+    /// The call did not complete within the timeout of the [`CallOptions`].
+    DeadlineExceeded(Duration),
+    /// The call was cancelled with the cancellation token of the [`CallOptions`].
+    Cancelled,
+    /// The call has been repeated according to the [`RetryPolicy`] and failed
+    /// `attempts` times, `last_error` is the error of the last attempt.
+    RetriesExhausted {
+        attempts: u32,
+        last_error: Box<ClientError>,
+    },
+    /// The operation cannot be mapped onto the protocol of the client, e.g.
+    /// target values with `kuksa.val.v2`.
+    Unsupported {
+        operation: &'static str,
+        protocol: &'static str,
+    },
 }
 
 #[async_trait]
@@ -163,11 +310,12 @@ pub trait ClientTraitV2 {
     type SensorUpdateType;
     type UpdateActuationType;
     type MultipleUpdateActuationType;
-    type PathType;
-    type PathsType;
+    type SignalType;
+    type SignalsType;
     type IdsType;
     type SubscribeType;
     type SubscribeByIdType;
+    type SubscribeOptionsType;
     type PublishResponseType;
     type GetResponseType;
     type MultipleGetResponseType;
@@ -183,70 +331,170 @@ pub trait ClientTraitV2 {
     // from deeply embedded layer providing sensor values (to keep backwards compatibility the naming is different for the corresponding interfaces)
     // if we do not want to put in the effort just give an unimplemented error for the function
     async fn publish_value(
-        &mut self,
-        signal_path: Self::PathType,
+        &self,
+        signal: impl Into<Self::SignalType> + Send,
         value: Self::SensorUpdateType,
     ) -> Result<Self::PublishResponseType, ClientError>;
 
     // from application getting sensor values (to keep backwards compatibility the naming is different for the corresponding interfaces)
     // if we do not want to put in the effort just give an unimplemented error for the function
     async fn get_value(
-        &mut self,
-        path: Self::PathType,
+        &self,
+        signal: impl Into<Self::SignalType> + Send,
     ) -> Result<Self::GetResponseType, ClientError>;
     async fn get_values(
-        &mut self,
-        paths: Self::PathsType,
+        &self,
+        signals: Self::SignalsType,
     ) -> Result<Self::MultipleGetResponseType, ClientError>;
 
     // from povider side pick up actuation requests (to keep backwards compatibility the naming is different for the corresponding interfaces)
     // if we do not want to put in the effort just give an unimplemented error for the function
     async fn open_provider_stream(
-        &mut self,
+        &self,
         buffer_size: Option<usize>,
     ) -> Result<Self::OpenProviderStreamResponseType, ClientError>;
 
     async fn provide_actuation(
-        &mut self,
-        paths: Self::PathType,
+        &self,
+        signals: Self::SignalsType,
     ) -> Result<Self::ProvideResponseType, ClientError>;
 
     // from povider side pick up actuation requests (to keep backwards compatibility the naming is different for the corresponding interfaces)
     // if we do not want to put in the effort just give an unimplemented error for the function
     async fn subscribe(
-        &mut self,
+        &self,
         paths: Self::SubscribeType,
-        buffer_size: Option<u32>,
-        min_sample_interval: Option<u32>,
+        options: Self::SubscribeOptionsType,
     ) -> Result<Self::SubscribeResponseType, ClientError>;
     async fn subscribe_by_id(
-        &mut self,
+        &self,
         signal_ids: Self::SubscribeByIdType,
-        buffer_size: Option<u32>,
-        min_sample_interval: Option<u32>,
+        options: Self::SubscribeOptionsType,
     ) -> Result<Self::SubscribeByIdResponseType, ClientError>;
 
     // from application requesting an actuation (to keep backwards compatibility the naming is different for the corresponding interfaces)
     // if we do not want to put in the effort just give an unimplemented error for the function
     async fn actuate(
-        &mut self,
-        signal_path: Self::PathType,
+        &self,
+        signal: impl Into<Self::SignalType> + Send,
         value: Self::UpdateActuationType,
     ) -> Result<Self::ActuateResponseType, ClientError>;
     async fn batch_actuate(
-        &mut self,
+        &self,
         datapoints: Self::MultipleUpdateActuationType,
     ) -> Result<Self::ActuateResponseType, ClientError>;
 
     // general functions
     async fn list_metadata(
-        &mut self,
+        &self,
         tuple: Self::MetadataType,
     ) -> Result<Self::MetadataResponseType, ClientError>;
-    async fn get_server_info(&mut self) -> Result<Self::ServerInfoType, ClientError>;
+    async fn get_server_info(&self) -> Result<Self::ServerInfoType, ClientError>;
+}
+
+impl ClientError {
+    /// Converts a status returned for `operation` into the variant for its code.
+    pub fn from_status(
+        status: tonic::Status,
+        operation: &'static str,
+        signals: Vec<String>,
+    ) -> Self {
+        let variant = match status.code() {
+            tonic::Code::NotFound => ClientError::NotFound,
+            tonic::Code::PermissionDenied => ClientError::PermissionDenied,
+            tonic::Code::Unauthenticated => ClientError::Unauthenticated,
+            tonic::Code::InvalidArgument => ClientError::InvalidArgument,
+            tonic::Code::Unavailable => ClientError::Unavailable,
+            tonic::Code::AlreadyExists => ClientError::AlreadyExists,
+            tonic::Code::DataLoss => ClientError::DataLoss,
+            _ => return ClientError::Status(status),
+        };
+        variant(RequestError {
+            operation,
+            signals,
+            status,
+        })
+    }
+
+    /// Adds the operation and the addressed signals to the error, converting
+    /// statuses into their dedicated variant.
+    pub fn with_context(self, operation: &'static str, signals: Vec<String>) -> Self {
+        match self {
+            ClientError::Status(status) => ClientError::from_status(status, operation, signals),
+            ClientError::RetriesExhausted {
+                attempts,
+                last_error,
+            } => ClientError::RetriesExhausted {
+                attempts,
+                last_error: Box::new(last_error.with_context(operation, signals)),
+            },
+            mut err => {
+                if let Some(request_error) = err.request_error_mut() {
+                    request_error.operation = operation;
+                    if request_error.signals.is_empty() {
+                        request_error.signals = signals;
+                    }
+                }
+                err
+            }
+        }
+    }
+
+    pub fn request_error(&self) -> Option<&RequestError> {
+        match self {
+            ClientError::NotFound(err)
+            | ClientError::PermissionDenied(err)
+            | ClientError::Unauthenticated(err)
+            | ClientError::InvalidArgument(err)
+            | ClientError::Unavailable(err)
+            | ClientError::AlreadyExists(err)
+            | ClientError::DataLoss(err) => Some(err),
+            ClientError::RetriesExhausted { last_error, .. } => last_error.request_error(),
+            _ => None,
+        }
+    }
+
+    fn request_error_mut(&mut self) -> Option<&mut RequestError> {
+        match self {
+            ClientError::NotFound(err)
+            | ClientError::PermissionDenied(err)
+            | ClientError::Unauthenticated(err)
+            | ClientError::InvalidArgument(err)
+            | ClientError::Unavailable(err)
+            | ClientError::AlreadyExists(err)
+            | ClientError::DataLoss(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Returns the status the databroker answered with, if any.
+    pub fn status(&self) -> Option<&tonic::Status> {
+        match self {
+            ClientError::Status(status) => Some(status),
+            ClientError::RetriesExhausted { last_error, .. } => last_error.status(),
+            err => err.request_error().map(|err| &err.status),
+        }
+    }
+
+    /// Returns the gRPC code the databroker answered with, if any.
+    pub fn code(&self) -> Option<tonic::Code> {
+        self.status().map(tonic::Status::code)
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Token(err) => Some(err),
+            ClientError::Tls(err) => Some(err),
+            ClientError::RetriesExhausted { last_error, .. } => Some(last_error.as_ref()),
+            err => err
+                .request_error()
+                .map(|err| &err.status as &(dyn std::error::Error + 'static)),
+        }
+    }
 }
 
-impl std::error::Error for ClientError {}
 impl std::fmt::Display for ClientError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -266,13 +514,35 @@ impl std::fmt::Display for ClientError {
 
                 f.pad(&formatted_result)
             }
+            ClientError::Token(err) => f.pad(&format!("{err}")),
+            ClientError::NotFound(err)
+            | ClientError::PermissionDenied(err)
+            | ClientError::Unauthenticated(err)
+            | ClientError::InvalidArgument(err)
+            | ClientError::Unavailable(err)
+            | ClientError::AlreadyExists(err)
+            | ClientError::DataLoss(err) => f.pad(&format!("{err}")),
+            ClientError::Tls(err) => f.pad(&format!("{err}")),
+            ClientError::DeadlineExceeded(timeout) => {
+                f.pad(&format!("Deadline of {timeout:?} exceeded"))
+            }
+            ClientError::Cancelled => f.pad("Call cancelled"),
+            ClientError::RetriesExhausted {
+                attempts,
+                last_error,
+            } => f.pad(&format!("{last_error} (failed {attempts} attempts)")),
+            ClientError::Unsupported {
+                operation,
+                protocol,
+            } => f.pad(&format!("{operation} is not supported by {protocol}")),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum TokenError {
     MalformedTokenError(String),
+    ProviderError(String),
 }
 
 impl std::error::Error for TokenError {}
@@ -280,11 +550,32 @@ impl std::fmt::Display for TokenError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             TokenError::MalformedTokenError(msg) => f.pad(msg),
+            TokenError::ProviderError(msg) => f.pad(msg),
         }
     }
 }
 
+/// Returns true if the status was generated by the transport because the databroker could
+/// not be reached. The databroker itself also answers with `Unavailable` (e.g. if no provider
+/// is available for an actuator) but those statuses do not carry a transport error as source.
+fn is_channel_dead(status: &tonic::Status) -> bool {
+    status.code() == tonic::Code::Unavailable && std::error::Error::source(status).is_some()
+}
+
+/// Converts an address into the uri used by [`Client`].
+///
+/// Addresses without scheme default to `http`. On unix platforms addresses of
+/// Unix domain sockets can be passed as `unix:///path/to/databroker.sock`.
 pub fn to_uri(uri: impl AsRef<str>) -> Result<Uri, String> {
+    if let Some(address) = uri.as_ref().strip_prefix("unix:") {
+        #[cfg(unix)]
+        return uds::to_unix_socket_uri(address);
+        #[cfg(not(unix))]
+        return Err(format!(
+            "Unix domain sockets are not supported on this platform: unix:{address}"
+        ));
+    }
+
     let uri = uri
         .as_ref()
         .parse::<tonic::transport::Uri>()
@@ -318,121 +609,789 @@ pub fn to_uri(uri: impl AsRef<str>) -> Result<Uri, String> {
 impl Client {
     pub fn new(uri: Uri) -> Self {
         info!("Creating client with URI: {}", uri);
+        let (connection_state, _) = tokio::sync::watch::channel(ConnectionState::Disconnected);
+        let (connection_state_subs, _) = tokio::sync::broadcast::channel(CONNECTION_STATE_CAPACITY);
         Client {
-            uri,
-            token: None,
-            #[cfg(feature = "tls")]
-            tls_config: None,
-            channel: None,
-            connection_state_subs: None,
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    uri,
+                    endpoints: None,
+                    token_provider: None,
+                    token_refresh_at: None,
+                    scope_precheck: false,
+                    #[cfg(feature = "tls")]
+                    tls_config: None,
+                    channel: None,
+                    connections: 0,
+                    stubs: HashMap::new(),
+                    reconnect_policy: None,
+                    retry_policy: None,
+                    channel_config: builder::ChannelConfig::default(),
+                }),
+                token: Arc::new(RwLock::new(None)),
+                connecting: tokio::sync::Mutex::new(()),
+                refreshing: tokio::sync::Mutex::new(()),
+                connection_state,
+                connection_state_subs,
+            }),
+            call_options: CallOptions::default(),
         }
     }
 
+    /// Returns a handle sharing the connection with this client, which applies
+    /// `call_options` to all its calls.
+    pub fn with_call_options(&self, call_options: CallOptions) -> Self {
+        Client {
+            shared: self.shared.clone(),
+            call_options,
+        }
+    }
+
+    pub fn call_options(&self) -> &CallOptions {
+        &self.call_options
+    }
+
+    // The lock is never held across an await point. A panic while holding it
+    // cannot leave the state half updated, so poisoning is ignored.
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.shared
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn token(&self) -> Option<tonic::metadata::AsciiMetadataValue> {
+        self.shared
+            .token
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn store_token(&self, token: Option<tonic::metadata::AsciiMetadataValue>) {
+        *self
+            .shared
+            .token
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = token;
+    }
+
+    /// Returns the uri of the active endpoint.
     pub fn get_uri(&self) -> String {
-        self.uri.to_string()
+        self.state().uri.to_string()
+    }
+
+    /// Adds endpoints to fail over to if the current endpoint cannot be reached.
+    ///
+    /// The current endpoint is treated as the primary, `standby` is tried in the given
+    /// order. Which endpoint is chosen when (re-)establishing the channel, and whether
+    /// read-only calls are distributed over all endpoints, depends on `policy`.
+    pub fn set_failover_endpoints(&self, standby: Vec<Uri>, policy: FailoverPolicy) {
+        let mut state = self.state();
+        let mut uris = vec![state.uri.clone()];
+        uris.extend(standby);
+        state.endpoints = Some(Endpoints::new(uris, policy));
+    }
+
+    /// Returns the uris of all configured endpoints, starting with the primary.
+    pub fn get_endpoints(&self) -> Vec<String> {
+        let state = self.state();
+        match &state.endpoints {
+            Some(endpoints) => endpoints.uris().iter().map(Uri::to_string).collect(),
+            None => vec![state.uri.to_string()],
+        }
     }
 
     #[cfg(feature = "tls")]
-    pub fn set_tls_config(&mut self, tls_config: tonic::transport::ClientTlsConfig) {
-        self.tls_config = Some(tls_config);
+    pub fn set_tls_config(&self, tls_config: tonic::transport::ClientTlsConfig) {
+        self.state().tls_config = Some(tls_config);
+    }
+
+    /// Loads the certificates and keys of `tls_config` and uses it for all following connections.
+    #[cfg(feature = "tls")]
+    pub fn set_tls(&self, tls_config: tls::TlsConfig) -> Result<(), tls::TlsError> {
+        self.set_tls_config(tls_config.build()?);
+        Ok(())
     }
 
-    pub fn set_access_token(&mut self, token: impl AsRef<str>) -> Result<(), TokenError> {
+    pub fn set_access_token(&self, token: impl AsRef<str>) -> Result<(), TokenError> {
         match tonic::metadata::AsciiMetadataValue::try_from(&format!("Bearer {}", token.as_ref())) {
             Ok(token) => {
-                self.token = Some(token);
+                self.store_token(Some(token));
                 Ok(())
             }
             Err(err) => Err(TokenError::MalformedTokenError(format!("{err}"))),
         }
     }
 
+    /// Sets the provider consulted for access tokens, replacing any token set before.
+    ///
+    /// The token is fetched before the first call and cached until shortly before it
+    /// expires. Calls answered with `UNAUTHENTICATED` are retried once with a fresh token.
+    pub fn set_token_provider(&self, token_provider: impl TokenProvider + 'static) {
+        self.set_shared_token_provider(Arc::new(token_provider));
+    }
+
+    fn set_shared_token_provider(&self, token_provider: Arc<dyn TokenProvider>) {
+        let mut state = self.state();
+        state.token_provider = Some(token_provider);
+        state.token_refresh_at = None;
+        self.store_token(None);
+    }
+
+    /// Fetches a token from the token provider if there is no valid token cached or
+    /// if `force` is set. Does nothing if no token provider is set.
+    pub async fn refresh_access_token(&self, force: bool) -> Result<(), ClientError> {
+        let Some(provider) = self.state().token_provider.clone() else {
+            return Ok(());
+        };
+        let _refreshing = self.shared.refreshing.lock().await;
+        let expired = match self.state().token_refresh_at {
+            Some(refresh_at) => SystemTime::now() >= refresh_at,
+            None => false,
+        };
+        if self.token().is_none() || expired || force {
+            let token = provider.token().await.map_err(ClientError::Token)?;
+            self.set_access_token(&token).map_err(ClientError::Token)?;
+            self.state().token_refresh_at = token::refresh_at(&token);
+        }
+        Ok(())
+    }
+
+    /// Decodes the claims of the configured access token without verifying it.
+    /// Returns `None` if no access token is configured.
+    pub fn access_token_claims(&self) -> Result<Option<jwt::Claims>, TokenError> {
+        match self.token() {
+            Some(token) => {
+                let token = token
+                    .to_str()
+                    .map_err(|err| TokenError::MalformedTokenError(format!("{err}")))?;
+                jwt::decode(token).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// If enabled, [`Client::check_permission`] rejects paths the scope of the
+    /// access token does not grant access to. Signals referenced by id are left to
+    /// the databroker, their path is not known client-side.
+    pub fn set_scope_precheck(&self, enabled: bool) {
+        self.state().scope_precheck = enabled;
+    }
+
+    /// Checks the scope of the access token for `permission` on all `paths` if the
+    /// scope pre-check is enabled, so calls which would be denied fail without a
+    /// round-trip to the databroker.
+    ///
+    /// Tokens which cannot be decoded are not checked, the databroker stays
+    /// responsible for enforcing the permissions.
+    pub async fn check_permission<'a>(
+        &self,
+        permission: jwt::Permission,
+        paths: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), ClientError> {
+        if !self.state().scope_precheck {
+            return Ok(());
+        }
+        self.refresh_access_token(false).await?;
+        let claims = match self.access_token_claims() {
+            Ok(Some(claims)) => claims,
+            Ok(None) => return Ok(()),
+            Err(err) => {
+                debug!("Skipping scope pre-check: {err}");
+                return Ok(());
+            }
+        };
+        for path in paths {
+            if !claims.scope.allows(permission, path) {
+                return Err(ClientError::PermissionDenied(RequestError {
+                    operation: "check_permission",
+                    signals: vec![path.to_owned()],
+                    status: tonic::Status::permission_denied(format!(
+                        "Access token does not grant {permission} permission for {path}"
+                    )),
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes a call on an [`AuthenticatedChannel`].
+    ///
+    /// The channel is (re-)established if needed and the access token is refreshed
+    /// before the call. If the databroker answers with `UNAUTHENTICATED` and a token
+    /// provider is set, the call is repeated once with a freshly fetched token,
+    /// which is why `call` may be invoked more than once.
+    pub async fn call<T, F, Fut>(&self, call: F) -> Result<T, ClientError>
+    where
+        F: FnMut(AuthenticatedChannel) -> Fut,
+        Fut: Future<Output = Result<T, tonic::Status>>,
+    {
+        self.call_with(CallKind::Unary, call).await
+    }
+
+    /// Executes a call which does not modify the state of the databroker.
+    ///
+    /// Behaves like [`Client::call`], except that with [`FailoverPolicy::RoundRobin`]
+    /// the calls are distributed over all configured endpoints.
+    pub async fn call_read_only<T, F, Fut>(&self, call: F) -> Result<T, ClientError>
+    where
+        F: FnMut(AuthenticatedChannel) -> Fut,
+        Fut: Future<Output = Result<T, tonic::Status>>,
+    {
+        self.call_with(CallKind::ReadOnly, call).await
+    }
+
+    /// Opens a stream, behaves like [`Client::call`] except that the timeout of the
+    /// [`CallOptions`] is not applied.
+    pub async fn call_streaming<T, F, Fut>(&self, call: F) -> Result<T, ClientError>
+    where
+        F: FnMut(AuthenticatedChannel) -> Fut,
+        Fut: Future<Output = Result<T, tonic::Status>>,
+    {
+        self.call_with(CallKind::Streaming, call).await
+    }
+
+    /// Like [`Client::call`], but passes the stub of the service, which is shared by
+    /// all calls on the same channel.
+    pub async fn call_stub<S, T, F, Fut>(&self, call: F) -> Result<T, ClientError>
+    where
+        S: ServiceStub,
+        F: FnMut(S) -> Fut,
+        Fut: Future<Output = Result<T, tonic::Status>>,
+    {
+        self.call_with(CallKind::Unary, call).await
+    }
+
+    /// Like [`Client::call_read_only`], but passes the stub of the service.
+    pub async fn call_stub_read_only<S, T, F, Fut>(&self, call: F) -> Result<T, ClientError>
+    where
+        S: ServiceStub,
+        F: FnMut(S) -> Fut,
+        Fut: Future<Output = Result<T, tonic::Status>>,
+    {
+        self.call_with(CallKind::ReadOnly, call).await
+    }
+
+    /// Like [`Client::call_streaming`], but passes the stub of the service.
+    pub async fn call_stub_streaming<S, T, F, Fut>(&self, call: F) -> Result<T, ClientError>
+    where
+        S: ServiceStub,
+        F: FnMut(S) -> Fut,
+        Fut: Future<Output = Result<T, tonic::Status>>,
+    {
+        self.call_with(CallKind::Streaming, call).await
+    }
+
+    /// Applies the [`CallOptions`] of this handle to the call.
+    async fn call_with<S, T, F, Fut>(&self, kind: CallKind, call: F) -> Result<T, ClientError>
+    where
+        S: ServiceStub,
+        F: FnMut(S) -> Fut,
+        Fut: Future<Output = Result<T, tonic::Status>>,
+    {
+        let timeout = match kind {
+            CallKind::Streaming => None,
+            CallKind::Unary | CallKind::ReadOnly => self.call_options.timeout,
+        };
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let request_options = RequestOptions {
+            deadline,
+            metadata: self.call_options.metadata.clone(),
+        };
+
+        let call = async {
+            let result = self.call_retrying(kind, &request_options, call).await;
+            match (result, timeout, deadline) {
+                (Err(ClientError::Status(status)), Some(timeout), Some(deadline))
+                    if status.code() == tonic::Code::DeadlineExceeded
+                        || (status.code() == tonic::Code::Cancelled
+                            && Instant::now() >= deadline) =>
+                {
+                    Err(ClientError::DeadlineExceeded(timeout))
+                }
+                (result, _, _) => result,
+            }
+        };
+        let call = async {
+            match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, call)
+                    .await
+                    .unwrap_or(Err(ClientError::DeadlineExceeded(timeout))),
+                None => call.await,
+            }
+        };
+        match &self.call_options.cancellation {
+            Some(cancellation) => {
+                tokio::select! {
+                    biased;
+                    _ = cancellation.cancelled() => Err(ClientError::Cancelled),
+                    result = call => result,
+                }
+            }
+            None => call.await,
+        }
+    }
+
+    /// Repeats failed calls according to the [`RetryPolicy`], streams and calls
+    /// modifying the databroker are only repeated if the policy allows it.
+    async fn call_retrying<S, T, F, Fut>(
+        &self,
+        kind: CallKind,
+        request_options: &RequestOptions,
+        mut call: F,
+    ) -> Result<T, ClientError>
+    where
+        S: ServiceStub,
+        F: FnMut(S) -> Fut,
+        Fut: Future<Output = Result<T, tonic::Status>>,
+    {
+        let read_only = kind == CallKind::ReadOnly;
+        let retry_policy = match (kind, self.get_retry_policy()) {
+            (CallKind::ReadOnly, Some(policy)) => policy,
+            (CallKind::Unary, Some(policy)) if policy.retry_writes => policy,
+            _ => {
+                return self
+                    .call_authenticated(read_only, request_options, &mut call)
+                    .await
+            }
+        };
+
+        let started = Instant::now();
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let error = match self
+                .call_authenticated(read_only, request_options, &mut call)
+                .await
+            {
+                Ok(response) => return Ok(response),
+                Err(error) => error,
+            };
+            let backoff = retry_policy.backoff(attempt);
+            if !retry_policy.is_retryable_error(&error)
+                || !retry_policy.should_retry(attempt, started.elapsed(), backoff)
+            {
+                return Err(match attempt {
+                    1 => error,
+                    attempts => ClientError::RetriesExhausted {
+                        attempts,
+                        last_error: Box::new(error),
+                    },
+                });
+            }
+            debug!("Attempt {attempt} failed, retrying in {backoff:?}: {error}");
+            tokio::time::sleep(backoff).await;
+        }
+    }
+
+    async fn call_authenticated<S, T, F, Fut>(
+        &self,
+        read_only: bool,
+        request_options: &RequestOptions,
+        call: &mut F,
+    ) -> Result<T, ClientError>
+    where
+        S: ServiceStub,
+        F: FnMut(S) -> Fut,
+        Fut: Future<Output = Result<T, tonic::Status>>,
+    {
+        let mut force_refresh = false;
+        loop {
+            self.refresh_access_token(force_refresh).await?;
+            let (read_endpoint, stub) = self.select_stub::<S>(read_only).await?;
+            let result = REQUEST_OPTIONS
+                .scope(request_options.clone(), call(stub))
+                .await;
+            match result {
+                Ok(response) => return Ok(response),
+                Err(status)
+                    if status.code() == tonic::Code::Unauthenticated
+                        && self.has_token_provider()
+                        && !force_refresh =>
+                {
+                    info!("Access token rejected, retrying with a new token");
+                    force_refresh = true;
+                }
+                Err(status) => {
+                    return Err(match read_endpoint {
+                        Some(index) => self.handle_read_status(index, status),
+                        None => self.handle_status(status),
+                    })
+                }
+            }
+        }
+    }
+
+    fn has_token_provider(&self) -> bool {
+        self.state().token_provider.is_some()
+    }
+
+    fn authenticate(&self, channel: Channel) -> AuthenticatedChannel {
+        InterceptedService::new(
+            channel,
+            AuthInterceptor {
+                token: self.shared.token.clone(),
+            },
+        )
+    }
+
+    /// Returns the stub for the next call and, if it does not use the channel to the
+    /// active endpoint, the index of the endpoint it is connected to.
+    async fn select_stub<S: ServiceStub>(
+        &self,
+        read_only: bool,
+    ) -> Result<(Option<usize>, S), ClientError> {
+        let read_endpoint = match self.state().endpoints.as_mut() {
+            Some(endpoints) if read_only => endpoints
+                .next_read_endpoint()
+                .filter(|index| *index != endpoints.active()),
+            _ => None,
+        };
+        if let Some(index) = read_endpoint {
+            if let Some(channel) = self.read_channel(index).await {
+                return Ok((Some(index), S::from_channel(self.authenticate(channel))));
+            }
+        }
+
+        let channel = self.get_channel().await?;
+        let mut state = self.state();
+        if state.channel.is_none() {
+            // Dropped in the meantime, do not cache a stub for a dead channel
+            return Ok((None, S::from_channel(self.authenticate(channel))));
+        }
+        if let Some(stub) = state
+            .stubs
+            .get(&TypeId::of::<S>())
+            .and_then(|stub| stub.downcast_ref::<S>())
+        {
+            return Ok((None, stub.clone()));
+        }
+        let stub = S::from_channel(self.authenticate(channel));
+        state
+            .stubs
+            .insert(TypeId::of::<S>(), Box::new(stub.clone()));
+        Ok((None, stub))
+    }
+
+    /// Returns the channel to the endpoint `index` used for read-only calls,
+    /// `None` if it cannot be connected.
+    async fn read_channel(&self, index: usize) -> Option<Channel> {
+        let uri = {
+            let state = self.state();
+            let endpoints = state.endpoints.as_ref()?;
+            if let Some(channel) = endpoints.read_channel(index) {
+                return Some(channel);
+            }
+            endpoints.uris()[index].clone()
+        };
+        let started = Instant::now();
+        let result = self.connect_once(&uri).await;
+        let mut state = self.state();
+        let endpoints = state.endpoints.as_mut()?;
+        match result {
+            Ok(channel) => {
+                endpoints.record_success(index, started.elapsed());
+                endpoints.set_read_channel(index, channel.clone());
+                Some(channel)
+            }
+            Err(err) => {
+                debug!("Skipping endpoint for read-only call: {err}");
+                endpoints.record_failure(index);
+                None
+            }
+        }
+    }
+
+    fn handle_read_status(&self, index: usize, status: tonic::Status) -> ClientError {
+        if is_channel_dead(&status) {
+            if let Some(endpoints) = self.state().endpoints.as_mut() {
+                warn!(
+                    "Lost connection to {}: {}",
+                    endpoints.uris()[index],
+                    status.message()
+                );
+                endpoints.record_failure(index);
+            }
+        }
+        ClientError::Status(status)
+    }
+
+    /// Sets the policy used to (re-)establish the channel to the databroker.
+    /// Without a policy only a single connection attempt is made per call.
+    pub fn set_reconnect_policy(&self, reconnect_policy: Option<ReconnectPolicy>) {
+        self.state().reconnect_policy = reconnect_policy;
+    }
+
+    pub fn get_reconnect_policy(&self) -> Option<ReconnectPolicy> {
+        self.state().reconnect_policy.clone()
+    }
+
+    /// Sets the policy used to repeat failed calls. Without a policy calls are
+    /// not repeated, apart from a single retry with a refreshed access token.
+    pub fn set_retry_policy(&self, retry_policy: Option<RetryPolicy>) {
+        self.state().retry_policy = retry_policy;
+    }
+
+    pub fn get_retry_policy(&self) -> Option<RetryPolicy> {
+        self.state().retry_policy.clone()
+    }
+
     pub fn is_connected(&self) -> bool {
-        self.channel.is_some()
+        self.state().channel.is_some()
+    }
+
+    /// Number of channels established so far, which changes with every (re-)connect.
+    ///
+    /// Lets state bound to a connection, e.g. ids resolved from the databroker,
+    /// detect that it might be outdated.
+    pub fn connection_count(&self) -> u64 {
+        self.state().connections
+    }
+
+    pub fn get_connection_state(&self) -> ConnectionState {
+        self.shared.connection_state.borrow().clone()
+    }
+
+    /// Returns a receiver holding the current connection state, which can be read
+    /// synchronously with [`borrow`](tokio::sync::watch::Receiver::borrow) or awaited
+    /// for changes. Unlike [`Client::subscribe_to_connection_state`] intermediate
+    /// states may be skipped.
+    pub fn watch_connection_state(&self) -> tokio::sync::watch::Receiver<ConnectionState> {
+        self.shared.connection_state.subscribe()
+    }
+
+    /// Returns a stream of all changes of the connection state from now on.
+    pub fn subscribe_to_connection_state(&self) -> BroadcastStream<ConnectionState> {
+        BroadcastStream::new(self.shared.connection_state_subs.subscribe())
+    }
+
+    fn set_connection_state(&self, state: ConnectionState) {
+        if *self.shared.connection_state.borrow() != state {
+            self.shared.connection_state.send_replace(state.clone());
+            // An error only means that there is currently no subscriber listening
+            let _ = self.shared.connection_state_subs.send(state);
+        }
+    }
+
+    async fn try_create_channel(&self) -> Result<Channel, ClientError> {
+        let mut attempt: u32 = 0;
+        loop {
+            attempt += 1;
+            match self.create_channel(attempt).await {
+                Ok(channel) => return Ok(channel),
+                Err(err) => {
+                    let backoff = match self.get_reconnect_policy() {
+                        Some(policy) if policy.should_retry(attempt) => policy.backoff(attempt),
+                        _ => {
+                            self.set_connection_state(ConnectionState::Disconnected);
+                            return Err(err);
+                        }
+                    };
+                    warn!("{err}, retrying in {backoff:?} (attempt {attempt})");
+                    tokio::time::sleep(backoff).await;
+                }
+            }
+        }
+    }
+
+    /// Makes a single connection attempt, reported as `attempt` in the connection state.
+    async fn create_channel(&self, attempt: u32) -> Result<Channel, ClientError> {
+        self.set_connection_state(match attempt {
+            1 => ConnectionState::Connecting,
+            _ => ConnectionState::Reconnecting { attempt },
+        });
+        let channel = self.connect_any().await?;
+        {
+            let mut state = self.state();
+            state.channel = Some(channel.clone());
+            state.connections += 1;
+            state.stubs.clear();
+        }
+        self.set_connection_state(ConnectionState::Connected);
+        Ok(channel)
+    }
+
+    /// Establishes the channel with a single connection attempt if there is none,
+    /// ignoring the reconnect policy. For callers which repeat the attempts themselves
+    /// and would otherwise multiply them with the attempts of the policy.
+    pub(crate) async fn connect_attempt(&self, attempt: u32) -> Result<(), ClientError> {
+        let _connecting = self.shared.connecting.lock().await;
+        if self.state().channel.is_some() {
+            return Ok(());
+        }
+        self.create_channel(attempt).await?;
+        Ok(())
     }
 
-    pub fn subscribe_to_connection_state(&mut self) -> BroadcastStream<ConnectionState> {
-        match &self.connection_state_subs {
-            Some(stream) => BroadcastStream::new(stream.subscribe()),
-            None => {
-                let (tx, rx1) = tokio::sync::broadcast::channel(1);
-                self.connection_state_subs = Some(tx);
-                BroadcastStream::new(rx1)
+    /// Connects to the first endpoint reachable in the order of the failover policy.
+    async fn connect_any(&self) -> Result<Channel, ClientError> {
+        let (uri, order) = {
+            let state = self.state();
+            let order = state.endpoints.as_ref().map(|endpoints| {
+                endpoints
+                    .connect_order()
+                    .into_iter()
+                    .map(|index| (index, endpoints.uris()[index].clone()))
+                    .collect::<Vec<(usize, Uri)>>()
+            });
+            (state.uri.clone(), order)
+        };
+        let Some(order) = order else {
+            return self.connect_once(&uri).await;
+        };
+
+        let mut last_error = None;
+        for (index, uri) in order {
+            let started = Instant::now();
+            let result = self.connect_once(&uri).await;
+            let mut guard = self.state();
+            let state = &mut *guard;
+            let Some(endpoints) = state.endpoints.as_mut() else {
+                break;
+            };
+            match result {
+                Ok(channel) => {
+                    endpoints.record_success(index, started.elapsed());
+                    if endpoints.active() != index {
+                        endpoints.set_active(index);
+                        info!("Switching from {} to endpoint {}", state.uri, uri);
+                        state.uri = uri.clone();
+                        drop(guard);
+                        self.set_connection_state(ConnectionState::EndpointChanged(
+                            uri.to_string(),
+                        ));
+                    }
+                    return Ok(channel);
+                }
+                Err(err) => {
+                    warn!("{err}");
+                    endpoints.record_failure(index);
+                    last_error = Some(err);
+                }
             }
         }
+        Err(last_error
+            .unwrap_or_else(|| ClientError::Connection("No endpoint configured".to_owned())))
     }
 
-    async fn try_create_channel(&mut self) -> Result<&Channel, ClientError> {
+    async fn connect_once(&self, uri: &Uri) -> Result<Channel, ClientError> {
+        let connection_error = |err: tonic::transport::Error| {
+            ClientError::Connection(format!("Failed to connect to {uri}: {err}"))
+        };
+        let channel_config = self.state().channel_config.clone();
+
+        // TLS is not applied to Unix domain sockets, access is controlled by file permissions
+        #[cfg(unix)]
+        if uds::is_unix_socket_uri(uri) {
+            let endpoint = channel_config
+                .apply(uds::endpoint())
+                .map_err(ClientError::Connection)?;
+            let connector = uds::UnixConnector::new(uri);
+            return if channel_config.lazy {
+                Ok(endpoint.connect_with_connector_lazy(connector))
+            } else {
+                endpoint
+                    .connect_with_connector(connector)
+                    .await
+                    .map_err(connection_error)
+            };
+        }
+
         #[cfg(feature = "tls")]
-        let mut builder = tonic::transport::Channel::builder(self.uri.clone());
+        let mut builder = tonic::transport::Channel::builder(uri.clone());
         #[cfg(not(feature = "tls"))]
-        let builder = tonic::transport::Channel::builder(self.uri.clone());
+        let builder = tonic::transport::Channel::builder(uri.clone());
 
         #[cfg(feature = "tls")]
-        if let Some(tls_config) = &self.tls_config {
-            match builder.tls_config(tls_config.clone()) {
+        if let Some(tls_config) = self.state().tls_config.clone() {
+            match builder.tls_config(tls_config) {
                 Ok(new_builder) => {
                     builder = new_builder;
                 }
                 Err(err) => {
-                    return Err(ClientError::Connection(format!(
+                    return Err(ClientError::Tls(tls::TlsError::Parse(format!(
                         "Failed to configure TLS: {err}"
-                    )));
+                    ))));
                 }
             }
         }
 
-        match builder.connect().await {
-            Ok(channel) => {
-                if let Some(subs) = &self.connection_state_subs {
-                    subs.send(ConnectionState::Connected).map_err(|err| {
-                        ClientError::Connection(format!(
-                            "Failed to notify connection state change: {err}"
-                        ))
-                    })?;
-                }
-                self.channel = Some(channel);
-                Ok(self.channel.as_ref().expect("Channel should exist"))
-            }
-            Err(err) => {
-                if let Some(subs) = &self.connection_state_subs {
-                    subs.send(ConnectionState::Disconnected).unwrap_or_default();
+        let builder = channel_config
+            .apply(builder)
+            .map_err(ClientError::Connection)?;
+        if channel_config.lazy {
+            Ok(builder.connect_lazy())
+        } else {
+            builder.connect().await.map_err(connection_error)
+        }
+    }
+
+    /// Drops the current channel so that the next call re-establishes it.
+    pub fn disconnect(&self) {
+        {
+            let mut state = self.state();
+            state.channel = None;
+            state.stubs.clear();
+        }
+        self.set_connection_state(ConnectionState::Disconnected);
+    }
+
+    /// Converts a status returned by a call into a [`ClientError`].
+    ///
+    /// A status with code `Unavailable` which was produced by the transport (and not sent
+    /// by the databroker) means that the channel is dead, in that case the channel gets dropped
+    /// and is re-established with the next call according to the reconnect policy.
+    pub fn handle_status(&self, status: tonic::Status) -> ClientError {
+        if is_channel_dead(&status) {
+            {
+                let mut state = self.state();
+                warn!("Lost connection to {}: {}", state.uri, status.message());
+                if let Some(endpoints) = state.endpoints.as_mut() {
+                    endpoints.record_failure(endpoints.active());
                 }
-                Err(ClientError::Connection(format!(
-                    "Failed to connect to {}: {}",
-                    self.uri, err
-                )))
             }
+            self.disconnect();
         }
+        ClientError::Status(status)
     }
 
-    pub async fn try_connect(&mut self) -> Result<(), ClientError> {
+    pub async fn try_connect(&self) -> Result<(), ClientError> {
+        let _connecting = self.shared.connecting.lock().await;
         self.try_create_channel().await?;
         Ok(())
     }
 
-    pub async fn try_connect_to(&mut self, uri: tonic::transport::Uri) -> Result<(), ClientError> {
-        self.uri = uri;
-        self.try_create_channel().await?;
-        Ok(())
+    /// Connects to `uri`, replacing the failover endpoints if any were set.
+    pub async fn try_connect_to(&self, uri: tonic::transport::Uri) -> Result<(), ClientError> {
+        {
+            let mut state = self.state();
+            state.uri = uri;
+            state.endpoints = None;
+        }
+        self.try_connect().await
     }
 
-    pub async fn get_channel(&mut self) -> Result<&Channel, ClientError> {
-        if self.channel.is_none() {
-            self.try_create_channel().await
-        } else {
-            match &self.channel {
-                Some(channel) => Ok(channel),
-                None => unreachable!(),
-            }
+    /// Returns the channel to the databroker, establishing it if needed.
+    pub async fn get_channel(&self) -> Result<Channel, ClientError> {
+        if let Some(channel) = self.state().channel.clone() {
+            return Ok(channel);
+        }
+        let _connecting = self.shared.connecting.lock().await;
+        // Another task may have connected while waiting for the lock
+        if let Some(channel) = self.state().channel.clone() {
+            return Ok(channel);
         }
+        self.try_create_channel().await
     }
 
+    #[allow(clippy::result_large_err)]
     pub fn get_auth_interceptor(
-        &mut self,
-    ) -> impl FnMut(tonic::Request<()>) -> Result<tonic::Request<()>, tonic::Status> + '_ {
+        &self,
+    ) -> impl FnMut(tonic::Request<()>) -> Result<tonic::Request<()>, tonic::Status> {
+        let token = self.token();
         move |mut req: tonic::Request<()>| {
-            if let Some(token) = &self.token {
+            if let Some(token) = &token {
                 // debug!("Inserting auth token: {:?}", token);
                 req.metadata_mut().insert("authorization", token.clone());
             }
@@ -440,3 +1399,423 @@ impl Client {
         }
     }
 }
+
+/// Interceptor inserting the current access token of the [`Client`] into the request metadata.
+#[derive(Debug, Clone)]
+pub struct AuthInterceptor {
+    token: Arc<RwLock<Option<tonic::metadata::AsciiMetadataValue>>>,
+}
+
+impl tonic::service::Interceptor for AuthInterceptor {
+    fn call(&mut self, mut req: tonic::Request<()>) -> Result<tonic::Request<()>, tonic::Status> {
+        // Not set if the channel is used outside of Client::call
+        let _ = REQUEST_OPTIONS.try_with(|options| options.apply(&mut req));
+        let token = self
+            .token
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        if let Some(token) = token {
+            req.metadata_mut().insert("authorization", token);
+        }
+        Ok(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("localhost:55555", "http://localhost:55555/"; "without scheme")]
+    #[test_case("https://databroker:55555", "https://databroker:55555/"; "with scheme")]
+    #[cfg_attr(unix, test_case("unix:///run/kuksa/databroker.sock", "unix://localhost/run/kuksa/databroker.sock"; "unix socket"))]
+    #[cfg_attr(unix, test_case("unix://localhost/run/kuksa/databroker.sock", "unix://localhost/run/kuksa/databroker.sock"; "unix socket with localhost"))]
+    #[cfg_attr(unix, test_case("unix:/run/kuksa/databroker.sock", "unix://localhost/run/kuksa/databroker.sock"; "unix socket short form"))]
+    #[cfg_attr(unix, test_case("unix:///run/my kuksa/databroker.sock", "unix://localhost/run/my%20kuksa/databroker.sock"; "unix socket with space"))]
+    fn test_to_uri(address: &str, expected: &str) {
+        assert_eq!(to_uri(address).unwrap().to_string(), expected);
+    }
+
+    #[test_case("unix://"; "unix socket without path")]
+    #[test_case("unix:databroker.sock"; "unix socket with relative path")]
+    #[test_case("/run/kuksa/databroker.sock"; "path without scheme")]
+    fn test_to_uri_fails(address: &str) {
+        assert!(to_uri(address).is_err());
+    }
+
+    #[derive(Debug, Default)]
+    struct CountingTokenProvider {
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl TokenProvider for CountingTokenProvider {
+        async fn token(&self) -> Result<String, TokenError> {
+            let calls = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(format!("token-{calls}"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_token_is_cached_until_refresh_is_forced() {
+        let client = Client::new(to_uri("localhost:55555").unwrap());
+        client.set_token_provider(CountingTokenProvider::default());
+
+        client.refresh_access_token(false).await.unwrap();
+        client.refresh_access_token(false).await.unwrap();
+        assert_eq!(client.token().unwrap(), "Bearer token-0");
+
+        client.refresh_access_token(true).await.unwrap();
+        assert_eq!(client.token().unwrap(), "Bearer token-1");
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_refreshed() {
+        let client = Client::new(to_uri("localhost:55555").unwrap());
+        client.set_token_provider(CountingTokenProvider::default());
+
+        client.refresh_access_token(false).await.unwrap();
+        client.state().token_refresh_at =
+            Some(SystemTime::now() - std::time::Duration::from_secs(1));
+        client.refresh_access_token(false).await.unwrap();
+        assert_eq!(client.token().unwrap(), "Bearer token-1");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_connect_to_missing_unix_socket_fails() {
+        let client = Client::new(to_uri("unix:///nonexistent/databroker.sock").unwrap());
+        match client.try_connect().await {
+            Err(ClientError::Connection(_)) => {}
+            other => panic!("unexpected result {other:?}"),
+        }
+        assert!(!client.is_connected());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_connect_to_unix_socket_with_space_in_path() {
+        let dir = std::env::temp_dir().join(format!("kuksa test {}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("databroker.sock");
+        let _ = std::fs::remove_file(&socket);
+        let _listener = tokio::net::UnixListener::bind(&socket).unwrap();
+
+        let client = Client::new(to_uri(format!("unix://{}", socket.display())).unwrap());
+        client.try_connect().await.unwrap();
+        assert!(client.is_connected());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failover_to_standby_endpoint() {
+        let socket =
+            std::env::temp_dir().join(format!("kuksa-test-standby-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        let server = tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.push(stream);
+            }
+        });
+
+        let client = Client::new(to_uri("unix:///nonexistent/databroker.sock").unwrap());
+        let standby = to_uri(format!("unix://{}", socket.display())).unwrap();
+        client.set_failover_endpoints(vec![standby.clone()], FailoverPolicy::Priority);
+        let mut states = client.subscribe_to_connection_state();
+
+        client.try_connect().await.unwrap();
+        assert_eq!(client.get_uri(), standby.to_string());
+        assert_eq!(client.get_connection_state(), ConnectionState::Connected);
+
+        use tokio_stream::StreamExt;
+        assert_eq!(
+            states.next().await.unwrap().unwrap(),
+            ConnectionState::Connecting
+        );
+        assert_eq!(
+            states.next().await.unwrap().unwrap(),
+            ConnectionState::EndpointChanged(standby.to_string())
+        );
+        assert_eq!(
+            states.next().await.unwrap().unwrap(),
+            ConnectionState::Connected
+        );
+
+        server.abort();
+        std::fs::remove_file(&socket).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_late_subscriber_reads_current_state() {
+        let client = Client::new(to_uri("localhost:55555").unwrap());
+        let clone = client.clone();
+        clone.set_connection_state(ConnectionState::Degraded {
+            latency: Duration::from_millis(600),
+        });
+
+        let states = client.watch_connection_state();
+        assert_eq!(
+            *states.borrow(),
+            ConnectionState::Degraded {
+                latency: Duration::from_millis(600)
+            }
+        );
+        assert_eq!(client.get_connection_state(), clone.get_connection_state());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_reconnect_attempts_are_reported() {
+        let client = Client::new(to_uri("unix:///nonexistent/databroker.sock").unwrap());
+        client.set_reconnect_policy(Some(
+            ReconnectPolicy::default()
+                .with_initial_backoff(Duration::from_millis(1))
+                .with_max_attempts(Some(2)),
+        ));
+        let mut states = client.subscribe_to_connection_state();
+        assert!(client.try_connect().await.is_err());
+
+        use tokio_stream::StreamExt;
+        for expected in [
+            ConnectionState::Connecting,
+            ConnectionState::Reconnecting { attempt: 2 },
+        ] {
+            assert_eq!(states.next().await.unwrap().unwrap(), expected);
+        }
+        assert_eq!(client.get_connection_state(), ConnectionState::Disconnected);
+    }
+
+    #[derive(Debug, Clone)]
+    struct CountingStub;
+
+    static CREATED_STUBS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    impl ServiceStub for CountingStub {
+        fn from_channel(_channel: AuthenticatedChannel) -> Self {
+            CREATED_STUBS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            CountingStub
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stub_is_shared_by_clones_until_disconnect() {
+        fn assert_shareable<T: Clone + Send + Sync>(_: &T) {}
+
+        let client = lazy_client();
+        assert_shareable(&client);
+        let clone = client.clone();
+        for client in [&client, &clone, &client] {
+            client
+                .call_stub(|_stub: CountingStub| async { Ok::<_, tonic::Status>(()) })
+                .await
+                .unwrap();
+        }
+        assert_eq!(CREATED_STUBS.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        clone.disconnect();
+        assert!(!client.is_connected());
+        client
+            .call_stub(|_stub: CountingStub| async { Ok::<_, tonic::Status>(()) })
+            .await
+            .unwrap();
+        assert_eq!(CREATED_STUBS.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_connection_count_changes_with_reconnect() {
+        let client = lazy_client();
+        assert_eq!(client.connection_count(), 0);
+
+        client.get_channel().await.unwrap();
+        client.get_channel().await.unwrap();
+        assert_eq!(client.connection_count(), 1);
+
+        client.disconnect();
+        client.get_channel().await.unwrap();
+        assert_eq!(client.connection_count(), 2);
+    }
+
+    fn lazy_client() -> Client {
+        builder::ClientBuilder::new("localhost:1")
+            .lazy(true)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_call_exceeding_timeout_fails() {
+        let client = lazy_client()
+            .with_call_options(CallOptions::new().with_timeout(Duration::from_millis(10)));
+        let slow_call = |_channel| async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            Ok::<_, tonic::Status>(())
+        };
+
+        match client.call(slow_call).await {
+            Err(ClientError::DeadlineExceeded(timeout)) => {
+                assert_eq!(timeout, Duration::from_millis(10))
+            }
+            other => panic!("unexpected result {other:?}"),
+        }
+        // Streams are not limited by the timeout
+        assert!(client.call_streaming(slow_call).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_call_fails() {
+        let cancellation = options::CancellationToken::new();
+        let client = lazy_client()
+            .with_call_options(CallOptions::new().with_cancellation(cancellation.clone()));
+        cancellation.cancel();
+
+        let result = client
+            .call(|_channel| async { Ok::<_, tonic::Status>(()) })
+            .await;
+        assert!(matches!(result, Err(ClientError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_request_options_are_set_during_call() {
+        let mut metadata = tonic::metadata::MetadataMap::new();
+        metadata.insert("x-request-id", "42".parse().unwrap());
+        let client = lazy_client();
+        let with_metadata = client.with_call_options(CallOptions::new().with_metadata(metadata));
+
+        let request_id = |_channel| async {
+            Ok::<_, tonic::Status>(
+                REQUEST_OPTIONS
+                    .try_with(|options| options.metadata.get("x-request-id").cloned())
+                    .unwrap(),
+            )
+        };
+        assert_eq!(with_metadata.call(request_id).await.unwrap().unwrap(), "42");
+        assert!(client.call(request_id).await.unwrap().is_none());
+    }
+
+    async fn count_attempts(client: &Client, read_only: bool, status: tonic::Status) -> u32 {
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+        let call = |_channel| {
+            attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let status = status.clone();
+            async move { Err::<(), _>(status) }
+        };
+        let result = match read_only {
+            true => client.call_read_only(call).await,
+            false => client.call(call).await,
+        };
+        let attempts = attempts.into_inner();
+        match result {
+            Err(ClientError::RetriesExhausted {
+                attempts: reported, ..
+            }) => assert_eq!(reported, attempts),
+            Err(ClientError::Status(_)) => assert_eq!(attempts, 1),
+            other => panic!("unexpected result {other:?}"),
+        }
+        attempts
+    }
+
+    #[tokio::test]
+    async fn test_retry_policy_repeats_reads() {
+        let client = lazy_client();
+        let unavailable = tonic::Status::unavailable("restarting");
+        assert_eq!(count_attempts(&client, true, unavailable.clone()).await, 1);
+
+        client.set_retry_policy(Some(
+            RetryPolicy::default()
+                .with_max_attempts(3)
+                .with_initial_backoff(Duration::from_millis(1)),
+        ));
+        assert_eq!(count_attempts(&client, true, unavailable.clone()).await, 3);
+        let not_found = tonic::Status::not_found("Vehicle.Foo");
+        assert_eq!(count_attempts(&client, true, not_found).await, 1);
+        // Writes are not repeated unless enabled
+        assert_eq!(count_attempts(&client, false, unavailable.clone()).await, 1);
+
+        client.set_retry_policy(client.get_retry_policy().map(|p| p.with_retry_writes(true)));
+        assert_eq!(count_attempts(&client, false, unavailable).await, 3);
+    }
+
+    #[test_case(tonic::Code::NotFound, "NotFound")]
+    #[test_case(tonic::Code::PermissionDenied, "PermissionDenied")]
+    #[test_case(tonic::Code::Unauthenticated, "Unauthenticated")]
+    #[test_case(tonic::Code::InvalidArgument, "InvalidArgument")]
+    #[test_case(tonic::Code::Unavailable, "Unavailable")]
+    #[test_case(tonic::Code::AlreadyExists, "AlreadyExists")]
+    #[test_case(tonic::Code::DataLoss, "DataLoss")]
+    #[test_case(tonic::Code::Internal, "Status")]
+    fn test_status_is_converted_to_variant(code: tonic::Code, expected: &str) {
+        let status = tonic::Status::new(code, "rejected");
+        let err = ClientError::from_status(status, "get_value", vec!["Vehicle.Speed".to_owned()]);
+        assert!(format!("{err:?}").starts_with(expected), "{err:?}");
+        assert_eq!(err.code(), Some(code));
+    }
+
+    #[test]
+    fn test_context_is_added_to_error() {
+        let err = ClientError::Status(tonic::Status::not_found("Vehicle.Foo"))
+            .with_context("get_values", vec!["Vehicle.Foo".to_owned()]);
+        let ClientError::NotFound(request_error) = &err else {
+            panic!("unexpected error {err:?}");
+        };
+        assert_eq!(request_error.operation, "get_values");
+        assert_eq!(request_error.signals, vec!["Vehicle.Foo".to_owned()]);
+        assert_eq!(
+            format!("{err}"),
+            "get_values failed for Vehicle.Foo: Vehicle.Foo (NotFound)"
+        );
+
+        let source = std::error::Error::source(&err).unwrap();
+        let status = source.downcast_ref::<tonic::Status>().unwrap();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[test]
+    fn test_context_is_added_to_retried_error() {
+        let err = ClientError::RetriesExhausted {
+            attempts: 3,
+            last_error: Box::new(ClientError::Status(tonic::Status::unavailable("down"))),
+        }
+        .with_context("get_server_info", vec![]);
+        assert!(
+            matches!(err.request_error(), Some(request_error) if request_error.operation == "get_server_info")
+        );
+        assert_eq!(err.code(), Some(tonic::Code::Unavailable));
+    }
+
+    #[test]
+    fn test_status_from_databroker_keeps_channel() {
+        let status = tonic::Status::unavailable("no provider for actuator");
+        assert!(!is_channel_dead(&status));
+    }
+
+    #[tokio::test]
+    async fn test_status_from_transport_drops_channel() {
+        let mut status = tonic::Status::unavailable("error trying to connect");
+        status.set_source(std::sync::Arc::new(std::io::Error::from(
+            std::io::ErrorKind::ConnectionRefused,
+        )));
+        assert!(is_channel_dead(&status));
+
+        let client = Client::new(to_uri("localhost:55555").unwrap());
+        client.set_connection_state(ConnectionState::Connected);
+        let mut states = client.subscribe_to_connection_state();
+
+        match client.handle_status(status) {
+            ClientError::Status(status) => assert_eq!(status.code(), tonic::Code::Unavailable),
+            other => panic!("unexpected error {other:?}"),
+        }
+        assert!(!client.is_connected());
+        assert_eq!(client.get_connection_state(), ConnectionState::Disconnected);
+
+        use tokio_stream::StreamExt;
+        assert_eq!(
+            states.next().await.unwrap().unwrap(),
+            ConnectionState::Disconnected
+        );
+    }
+}
@@ -0,0 +1,132 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+use std::time::{Duration, Instant};
+pub use tokio_util::sync::CancellationToken;
+use tonic::metadata::{KeyAndValueRef, MetadataMap};
+
+/// Options applied to every call made through a client handle, see
+/// [`Client::with_call_options`](super::Client::with_call_options).
+///
+/// ```no_run
+/// # use kuksa_rust_sdk::kuksa::common::options::{CallOptions, CancellationToken};
+/// # use kuksa_rust_sdk::kuksa::common::ClientTraitV2;
+/// # use kuksa_rust_sdk::kuksa::val::v2::KuksaClientV2;
+/// # use std::time::Duration;
+/// # async fn example(client: KuksaClientV2) {
+/// let cancellation = CancellationToken::new();
+/// let client = client.with_call_options(
+///     CallOptions::new()
+///         .with_timeout(Duration::from_millis(50))
+///         .with_cancellation(cancellation.clone()),
+/// );
+/// let speed = client.get_value("Vehicle.Speed".to_owned()).await;
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CallOptions {
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) metadata: MetadataMap,
+    pub(crate) cancellation: Option<CancellationToken>,
+}
+
+impl CallOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deadline of unary calls, including (re-)connecting and fetching the access token.
+    /// It is sent to the databroker as `grpc-timeout`. Calls exceeding it fail with
+    /// [`ClientError::DeadlineExceeded`](super::ClientError::DeadlineExceeded).
+    ///
+    /// Streaming calls like subscriptions are not limited by the deadline.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Metadata added to every request.
+    pub fn with_metadata(mut self, metadata: MetadataMap) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Calls fail with [`ClientError::Cancelled`](super::ClientError::Cancelled) as soon
+    /// as `cancellation` is cancelled. Streams which have already been opened are not affected.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub fn metadata(&self) -> &MetadataMap {
+        &self.metadata
+    }
+}
+
+/// Part of the [`CallOptions`] applied by the interceptor to the requests of one call.
+#[derive(Debug, Clone)]
+pub(crate) struct RequestOptions {
+    pub deadline: Option<Instant>,
+    pub metadata: MetadataMap,
+}
+
+tokio::task_local! {
+    // Set while a call is polled, so the stubs cached per channel can be shared by
+    // client handles with different options
+    pub(crate) static REQUEST_OPTIONS: RequestOptions;
+}
+
+impl RequestOptions {
+    pub fn apply<T>(&self, request: &mut tonic::Request<T>) {
+        if let Some(deadline) = self.deadline {
+            request.set_timeout(deadline.saturating_duration_since(Instant::now()));
+        }
+        let metadata = request.metadata_mut();
+        for entry in self.metadata.iter() {
+            match entry {
+                KeyAndValueRef::Ascii(key, value) => {
+                    metadata.insert(key.clone(), value.clone());
+                }
+                KeyAndValueRef::Binary(key, value) => {
+                    metadata.insert_bin(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_options_are_applied() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert("x-request-id", "42".parse().unwrap());
+        let options = RequestOptions {
+            deadline: Some(Instant::now() + Duration::from_secs(10)),
+            metadata,
+        };
+
+        let mut request = tonic::Request::new(());
+        options.apply(&mut request);
+        assert_eq!(request.metadata().get("x-request-id").unwrap(), "42");
+        assert!(request.metadata().get("grpc-timeout").is_some());
+    }
+}
@@ -0,0 +1,184 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Describes how a [`Client`](super::Client) re-establishes its channel to the
+/// databroker after the connection has been lost or could not be created.
+///
+/// The delay between two attempts grows exponentially, starting at
+/// `initial_backoff` and being capped at `max_backoff`. A random jitter of up to
+/// `jitter` (as a fraction of the delay) is applied to every delay so that many
+/// clients do not hammer a restarting databroker at the same time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectPolicy {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    pub jitter: f64,
+    /// Maximum number of connection attempts, `None` retries forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    pub fn with_initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: Option<u32>) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Returns true if another connection attempt shall be made after
+    /// `attempt` attempts have already failed.
+    pub fn should_retry(&self, attempt: u32) -> bool {
+        match self.max_attempts {
+            Some(max_attempts) => attempt < max_attempts,
+            None => true,
+        }
+    }
+
+    /// Delay before the next connection attempt without jitter applied,
+    /// `attempt` being the number of attempts that already failed (starting at 1).
+    pub fn base_backoff(&self, attempt: u32) -> Duration {
+        exponential_backoff(
+            self.initial_backoff,
+            self.multiplier,
+            self.max_backoff,
+            0.0,
+            attempt,
+        )
+    }
+
+    /// Delay before the next connection attempt with a random jitter applied.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        exponential_backoff(
+            self.initial_backoff,
+            self.multiplier,
+            self.max_backoff,
+            self.jitter,
+            attempt,
+        )
+    }
+}
+
+/// Delay growing from `initial` by `multiplier` per failed `attempt` (starting at 1),
+/// capped at `max` and scaled by a random factor within [1 - jitter, 1 + jitter].
+pub(super) fn exponential_backoff(
+    initial: Duration,
+    multiplier: f64,
+    max: Duration,
+    jitter: f64,
+    attempt: u32,
+) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+    let backoff = initial.as_secs_f64() * multiplier.powi(exponent);
+    let base = if !backoff.is_finite() || backoff >= max.as_secs_f64() {
+        max
+    } else {
+        Duration::from_secs_f64(backoff)
+    };
+    if jitter <= 0.0 {
+        return base;
+    }
+    base.mul_f64(1.0 + jitter * (2.0 * random_fraction() - 1.0))
+}
+
+/// Returns a pseudo random number within [0, 1) which is good enough to spread reconnects.
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_backoff_grows_exponentially() {
+        let policy = ReconnectPolicy::default()
+            .with_initial_backoff(Duration::from_millis(100))
+            .with_multiplier(2.0)
+            .with_max_backoff(Duration::from_secs(1));
+
+        assert_eq!(policy.base_backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.base_backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.base_backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.base_backoff(4), Duration::from_millis(800));
+        assert_eq!(policy.base_backoff(5), Duration::from_secs(1));
+        assert_eq!(policy.base_backoff(u32::MAX), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_backoff_stays_within_jitter() {
+        let policy = ReconnectPolicy::default()
+            .with_initial_backoff(Duration::from_millis(1000))
+            .with_jitter(0.5);
+
+        for _ in 0..100 {
+            let backoff = policy.backoff(1);
+            assert!(backoff >= Duration::from_millis(500));
+            assert!(backoff <= Duration::from_millis(1500));
+        }
+    }
+
+    #[test]
+    fn test_should_retry() {
+        let policy = ReconnectPolicy::default().with_max_attempts(Some(3));
+        assert!(policy.should_retry(1));
+        assert!(policy.should_retry(2));
+        assert!(!policy.should_retry(3));
+
+        let policy = ReconnectPolicy::default().with_max_attempts(None);
+        assert!(policy.should_retry(u32::MAX));
+    }
+}
@@ -0,0 +1,179 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+use super::reconnect::exponential_backoff;
+use super::ClientError;
+use std::time::Duration;
+use tonic::Code;
+
+/// Describes how failed calls of a [`Client`](super::Client) are repeated.
+///
+/// Only calls which do not modify the databroker (e.g. `get_value`, `get_values`,
+/// `list_metadata` and `get_server_info`) are retried, calls like `publish_value`,
+/// `actuate` or `batch_actuate` only if [`RetryPolicy::with_retry_writes`] is enabled.
+/// Streams are never retried.
+///
+/// A call is retried if it failed with one of the `retryable_codes` or because the
+/// databroker could not be reached, all other errors are returned immediately. The
+/// delay between two attempts grows exponentially like the one of the
+/// [`ReconnectPolicy`](super::reconnect::ReconnectPolicy).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts including the first one.
+    pub max_attempts: u32,
+    /// No attempt is started after this time has passed since the first one.
+    pub max_elapsed: Option<Duration>,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    pub jitter: f64,
+    pub retryable_codes: Vec<Code>,
+    pub retry_writes: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            max_elapsed: Some(Duration::from_secs(10)),
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(2),
+            multiplier: 2.0,
+            jitter: 0.2,
+            retryable_codes: vec![Code::Unavailable, Code::ResourceExhausted, Code::Aborted],
+            retry_writes: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_max_elapsed(mut self, max_elapsed: Option<Duration>) -> Self {
+        self.max_elapsed = max_elapsed;
+        self
+    }
+
+    pub fn with_initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_retryable_codes(mut self, retryable_codes: impl IntoIterator<Item = Code>) -> Self {
+        self.retryable_codes = retryable_codes.into_iter().collect();
+        self
+    }
+
+    /// Also retry calls which modify the databroker. Only enable this if applying
+    /// a write twice does no harm, e.g. when publishing absolute values.
+    pub fn with_retry_writes(mut self, retry_writes: bool) -> Self {
+        self.retry_writes = retry_writes;
+        self
+    }
+
+    /// Returns true if a call failing with `code` may succeed when repeated.
+    pub fn is_retryable(&self, code: Code) -> bool {
+        self.retryable_codes.contains(&code)
+    }
+
+    /// Returns true if the call shall be repeated after failing with `error`.
+    pub fn is_retryable_error(&self, error: &ClientError) -> bool {
+        match error {
+            ClientError::Connection(_) => self.is_retryable(Code::Unavailable),
+            ClientError::RetriesExhausted { .. } => false,
+            err => err.code().is_some_and(|code| self.is_retryable(code)),
+        }
+    }
+
+    /// Returns true if another attempt shall be started after `attempt` attempts
+    /// have failed within `elapsed`, waiting `backoff` before.
+    pub fn should_retry(&self, attempt: u32, elapsed: Duration, backoff: Duration) -> bool {
+        attempt < self.max_attempts
+            && self
+                .max_elapsed
+                .is_none_or(|max_elapsed| elapsed + backoff < max_elapsed)
+    }
+
+    /// Delay before the next attempt, `attempt` being the number of attempts that
+    /// already failed (starting at 1).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        exponential_backoff(
+            self.initial_backoff,
+            self.multiplier,
+            self.max_backoff,
+            self.jitter,
+            attempt,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(ClientError::Status(tonic::Status::unavailable("restarting")), true; "unavailable")]
+    #[test_case(ClientError::Status(tonic::Status::resource_exhausted("busy")), true; "resource exhausted")]
+    #[test_case(ClientError::Connection("refused".to_owned()), true; "connection failed")]
+    #[test_case(ClientError::Status(tonic::Status::not_found("Vehicle.Foo")), false; "not found")]
+    #[test_case(ClientError::Status(tonic::Status::permission_denied("denied")), false; "permission denied")]
+    #[test_case(ClientError::DeadlineExceeded(Duration::from_secs(1)), false; "deadline exceeded")]
+    fn test_is_retryable_error(error: ClientError, expected: bool) {
+        assert_eq!(RetryPolicy::default().is_retryable_error(&error), expected);
+    }
+
+    #[test]
+    fn test_should_retry_is_capped() {
+        let policy = RetryPolicy::default()
+            .with_max_attempts(3)
+            .with_max_elapsed(Some(Duration::from_secs(1)));
+        let backoff = Duration::from_millis(100);
+
+        assert!(policy.should_retry(1, Duration::ZERO, backoff));
+        assert!(policy.should_retry(2, Duration::ZERO, backoff));
+        assert!(!policy.should_retry(3, Duration::ZERO, backoff));
+        assert!(!policy.should_retry(1, Duration::from_millis(950), backoff));
+    }
+
+    #[test]
+    fn test_backoff_grows_exponentially() {
+        let policy = RetryPolicy::default()
+            .with_initial_backoff(Duration::from_millis(100))
+            .with_max_backoff(Duration::from_millis(300))
+            .with_jitter(0.0);
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(300));
+    }
+}
@@ -0,0 +1,196 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+#[cfg(feature = "tls")]
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TlsError {
+    /// A PEM file could not be read.
+    Io { path: String, message: String },
+    /// A certificate or private key could not be parsed.
+    Parse(String),
+}
+
+impl std::error::Error for TlsError {}
+impl std::fmt::Display for TlsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TlsError::Io { path, message } => f.pad(&format!("Failed to read {path}: {message}")),
+            TlsError::Parse(msg) => f.pad(msg),
+        }
+    }
+}
+
+#[cfg(feature = "tls")]
+#[derive(Debug, Clone)]
+enum PemSource {
+    File(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+#[cfg(feature = "tls")]
+impl PemSource {
+    fn load(&self, kind: &str, marker: &str) -> Result<Vec<u8>, TlsError> {
+        let (pem, origin) = match self {
+            PemSource::File(path) => (
+                std::fs::read(path).map_err(|err| TlsError::Io {
+                    path: path.display().to_string(),
+                    message: format!("{err}"),
+                })?,
+                path.display().to_string(),
+            ),
+            PemSource::Bytes(bytes) => (bytes.clone(), "PEM bytes".to_owned()),
+        };
+        if !String::from_utf8_lossy(&pem).contains(marker) {
+            return Err(TlsError::Parse(format!("No {kind} found in {origin}")));
+        }
+        Ok(pem)
+    }
+}
+
+/// TLS settings for the connection to the databroker.
+///
+/// ```no_run
+/// # use kuksa_rust_sdk::kuksa::common::tls::TlsConfig;
+/// let tls_config = TlsConfig::new()
+///     .ca_certificate_file("certificates/CA.pem")
+///     .client_identity_files("certificates/Client.pem", "certificates/Client.key")
+///     .domain_name("Server");
+/// ```
+///
+/// Certificates and keys are read and validated by [`TlsConfig::build`], which is done
+/// when the config is passed to [`Client::set_tls`](super::Client::set_tls) or the
+/// [`ClientBuilder`](super::builder::ClientBuilder).
+#[cfg(feature = "tls")]
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    ca_certificate: Option<PemSource>,
+    client_identity: Option<(PemSource, PemSource)>,
+    domain_name: Option<String>,
+}
+
+#[cfg(feature = "tls")]
+impl TlsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// CA bundle used to verify the certificate of the databroker.
+    pub fn ca_certificate_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.ca_certificate = Some(PemSource::File(path.as_ref().to_path_buf()));
+        self
+    }
+
+    pub fn ca_certificate_pem(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.ca_certificate = Some(PemSource::Bytes(pem.into()));
+        self
+    }
+
+    /// Certificate and private key used to authenticate the client (mutual TLS).
+    pub fn client_identity_files(
+        mut self,
+        certificate: impl AsRef<Path>,
+        private_key: impl AsRef<Path>,
+    ) -> Self {
+        self.client_identity = Some((
+            PemSource::File(certificate.as_ref().to_path_buf()),
+            PemSource::File(private_key.as_ref().to_path_buf()),
+        ));
+        self
+    }
+
+    pub fn client_identity_pem(
+        mut self,
+        certificate: impl Into<Vec<u8>>,
+        private_key: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.client_identity = Some((
+            PemSource::Bytes(certificate.into()),
+            PemSource::Bytes(private_key.into()),
+        ));
+        self
+    }
+
+    /// Name expected in the certificate of the databroker if it differs from the
+    /// host used to connect, e.g. `Server` for the KUKSA test certificates.
+    pub fn domain_name(mut self, domain_name: impl Into<String>) -> Self {
+        self.domain_name = Some(domain_name.into());
+        self
+    }
+
+    /// Loads and validates the certificates and key.
+    pub fn build(&self) -> Result<tonic::transport::ClientTlsConfig, TlsError> {
+        use tonic::transport::{Certificate, ClientTlsConfig, Identity};
+
+        let mut tls_config = ClientTlsConfig::new();
+        if let Some(ca_certificate) = &self.ca_certificate {
+            let pem = ca_certificate.load("CA certificate", "-----BEGIN CERTIFICATE-----")?;
+            tls_config = tls_config.ca_certificate(Certificate::from_pem(pem));
+        }
+        if let Some((certificate, private_key)) = &self.client_identity {
+            let certificate =
+                certificate.load("client certificate", "-----BEGIN CERTIFICATE-----")?;
+            let private_key = private_key.load("private key", "PRIVATE KEY-----")?;
+            tls_config = tls_config.identity(Identity::from_pem(certificate, private_key));
+        }
+        if let Some(domain_name) = &self.domain_name {
+            tls_config = tls_config.domain_name(domain_name);
+        }
+
+        // tonic parses certificates and keys when applying the config to an endpoint
+        tonic::transport::Endpoint::from_static("https://localhost")
+            .tls_config(tls_config.clone())
+            .map_err(|err| TlsError::Parse(format!("Invalid TLS configuration: {err}")))?;
+        Ok(tls_config)
+    }
+}
+
+#[cfg(all(test, feature = "tls"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_without_certificates() {
+        assert!(TlsConfig::new().domain_name("Server").build().is_ok());
+    }
+
+    #[test]
+    fn test_missing_file_is_io_error() {
+        let result = TlsConfig::new()
+            .ca_certificate_file("/nonexistent/CA.pem")
+            .build();
+        match result {
+            Err(TlsError::Io { path, .. }) => assert_eq!(path, "/nonexistent/CA.pem"),
+            other => panic!("Expected io error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_pem_is_parse_error() {
+        let result = TlsConfig::new()
+            .ca_certificate_pem("not a certificate")
+            .build();
+        assert!(matches!(result, Err(TlsError::Parse(_))));
+
+        let result = TlsConfig::new()
+            .client_identity_pem(
+                "-----BEGIN CERTIFICATE-----\n-----END CERTIFICATE-----\n",
+                "not a key",
+            )
+            .build();
+        assert!(matches!(result, Err(TlsError::Parse(_))));
+    }
+}
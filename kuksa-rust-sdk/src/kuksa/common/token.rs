@@ -0,0 +1,217 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+use super::{jwt, TokenError};
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+use tonic::async_trait;
+
+/// Tokens are refreshed this long before they expire.
+pub const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(30);
+
+/// Source of the access token used to authorize calls to the databroker.
+///
+/// The [`Client`](super::Client) caches the returned token until shortly before
+/// its `exp` claim and asks the provider for a new one when the databroker
+/// answers with `UNAUTHENTICATED`.
+#[async_trait]
+pub trait TokenProvider: Debug + Send + Sync {
+    async fn token(&self) -> Result<String, TokenError>;
+}
+
+/// Provides the same token forever.
+#[derive(Debug, Clone)]
+pub struct StaticTokenProvider {
+    token: String,
+}
+
+impl StaticTokenProvider {
+    pub fn new(token: impl Into<String>) -> Self {
+        StaticTokenProvider {
+            token: token.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl TokenProvider for StaticTokenProvider {
+    async fn token(&self) -> Result<String, TokenError> {
+        Ok(self.token.clone())
+    }
+}
+
+/// Reads the token from a file, the file is only read again if it has been modified.
+#[derive(Debug)]
+pub struct FileTokenProvider {
+    path: PathBuf,
+    cache: Mutex<Option<(SystemTime, String)>>,
+}
+
+impl FileTokenProvider {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileTokenProvider {
+            path: path.into(),
+            cache: Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl TokenProvider for FileTokenProvider {
+    async fn token(&self) -> Result<String, TokenError> {
+        let read_error = |err: std::io::Error| {
+            TokenError::ProviderError(format!(
+                "Failed to read token from {}: {err}",
+                self.path.display()
+            ))
+        };
+        let modified = tokio::fs::metadata(&self.path)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .map_err(read_error)?;
+
+        let mut cache = self.cache.lock().await;
+        match cache.as_ref() {
+            Some((cached_modified, token)) if *cached_modified == modified => Ok(token.clone()),
+            _ => {
+                let token = tokio::fs::read_to_string(&self.path)
+                    .await
+                    .map_err(read_error)?
+                    .trim()
+                    .to_owned();
+                *cache = Some((modified, token.clone()));
+                Ok(token)
+            }
+        }
+    }
+}
+
+/// Looks up the value of an environment variable, [`std::env::var`] by default.
+pub type EnvLookup = fn(&str) -> Result<String, std::env::VarError>;
+
+/// Reads the token from an environment variable.
+#[derive(Debug, Clone)]
+pub struct EnvTokenProvider {
+    variable: String,
+    lookup: EnvLookup,
+}
+
+impl EnvTokenProvider {
+    pub fn new(variable: impl Into<String>) -> Self {
+        Self::with_lookup(variable, |variable| std::env::var(variable))
+    }
+
+    /// Reads the variable through `lookup` instead of the process environment.
+    pub fn with_lookup(variable: impl Into<String>, lookup: EnvLookup) -> Self {
+        EnvTokenProvider {
+            variable: variable.into(),
+            lookup,
+        }
+    }
+}
+
+#[async_trait]
+impl TokenProvider for EnvTokenProvider {
+    async fn token(&self) -> Result<String, TokenError> {
+        (self.lookup)(&self.variable)
+            .map(|token| token.trim().to_owned())
+            .map_err(|err| {
+                TokenError::ProviderError(format!(
+                    "Failed to read token from environment variable {}: {err}",
+                    self.variable
+                ))
+            })
+    }
+}
+
+/// Returns the point in time after which a token shall no longer be used,
+/// `None` if the token does not expire or is no JWT.
+pub(crate) fn refresh_at(token: &str) -> Option<SystemTime> {
+    let expires_at = jwt::decode(token).ok()?.expires_at()?;
+    Some(
+        expires_at
+            .checked_sub(TOKEN_REFRESH_MARGIN)
+            .unwrap_or(UNIX_EPOCH),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // {"alg":"none"}.{"sub":"test","exp":1767225599}
+    const TOKEN_WITH_EXP: &str = "eyJhbGciOiJub25lIn0.eyJzdWIiOiJ0ZXN0IiwiZXhwIjoxNzY3MjI1NTk5fQ.";
+    // {"alg":"none"}.{"sub":"test"}
+    const TOKEN_WITHOUT_EXP: &str = "eyJhbGciOiJub25lIn0.eyJzdWIiOiJ0ZXN0In0.";
+
+    #[test]
+    fn test_refresh_at() {
+        assert_eq!(
+            refresh_at(TOKEN_WITH_EXP),
+            Some(UNIX_EPOCH + Duration::from_secs(1767225599) - TOKEN_REFRESH_MARGIN)
+        );
+        assert_eq!(refresh_at(TOKEN_WITHOUT_EXP), None);
+        assert_eq!(refresh_at("not a jwt"), None);
+    }
+
+    #[tokio::test]
+    async fn test_static_token_provider() {
+        let provider = StaticTokenProvider::new("token");
+        assert_eq!(provider.token().await.unwrap(), "token");
+    }
+
+    fn lookup(variable: &str) -> Result<String, std::env::VarError> {
+        match variable {
+            "KUKSA_TOKEN" => Ok("token\n".to_owned()),
+            _ => Err(std::env::VarError::NotPresent),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_env_token_provider() {
+        let provider = EnvTokenProvider::with_lookup("KUKSA_TOKEN", lookup);
+        assert_eq!(provider.token().await.unwrap(), "token");
+
+        let provider = EnvTokenProvider::with_lookup("KUKSA_TOKEN_MISSING", lookup);
+        assert!(provider.token().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_env_token_provider_reads_process_environment() {
+        // Only read, a variable which is not set in any sane environment
+        let provider = EnvTokenProvider::new("KUKSA_TEST_ENV_TOKEN_PROVIDER_UNSET");
+        assert!(provider.token().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_file_token_provider_rereads_modified_file() {
+        let path =
+            std::env::temp_dir().join(format!("kuksa-test-token-{}.token", std::process::id()));
+        std::fs::write(&path, "first\n").unwrap();
+        let provider = FileTokenProvider::new(&path);
+        assert_eq!(provider.token().await.unwrap(), "first");
+
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        std::fs::write(&path, "second\n").unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(provider.token().await.unwrap(), "second");
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(provider.token().await.is_err());
+    }
+}
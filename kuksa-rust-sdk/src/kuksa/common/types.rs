@@ -14,6 +14,7 @@
  */
 
 use std::collections::HashMap;
+use std::fmt;
 
 use crate::proto::kuksa::val::v1 as protoV1;
 use crate::proto::kuksa::val::v2 as protoV2;
@@ -48,9 +49,11 @@ pub type MetadataResponseTypeV1 = GetResponseTypeV1;
 // Type aliases V2
 pub type SensorUpdateTypeV2 = protoV2::Value;
 pub type UpdateActuationTypeV2 = SensorUpdateTypeV2;
-pub type MultipleUpdateActuationTypeV2 = HashMap<PathTypeV2, UpdateActuationTypeV2>;
+pub type MultipleUpdateActuationTypeV2 = HashMap<SignalTypeV2, UpdateActuationTypeV2>;
 pub type PathTypeV2 = String;
 pub type PathsTypeV2 = Vec<PathTypeV2>;
+pub type SignalTypeV2 = SignalRef;
+pub type SignalsTypeV2 = Vec<SignalTypeV2>;
 pub type IdsTypeV2 = Vec<i32>;
 pub type SubscribeTypeV2 = PathsTypeV2;
 pub type SubscribeByIdTypeV2 = IdsTypeV2;
@@ -59,13 +62,83 @@ pub type GetResponseTypeV2 = Option<protoV2::Datapoint>;
 pub type MultipleGetResponseTypeV2 = Vec<protoV2::Datapoint>;
 pub type SubscribeResponseTypeV2 = tonic::Streaming<protoV2::SubscribeResponse>;
 pub type SubscribeByIdResponseTypeV2 = tonic::Streaming<protoV2::SubscribeByIdResponse>;
-pub type ProvideResponseTypeV2 = ();
 pub type ActuateResponseTypeV2 = ();
 pub type OpenProviderStreamResponseTypeV2 = OpenProviderStream;
 pub type MetadataTypeV2 = (PathTypeV2, String);
 pub type MetadataResponseTypeV2 = Vec<protoV2::Metadata>;
 pub type ServerInfoTypeV2 = ServerInfo;
 
+/// Reference to a signal of the databroker, either by its path or by its id.
+///
+/// Ids avoid sending and looking up the path with every request, see
+/// [`KuksaClientV2::resolve_ids_for_paths`](crate::kuksa::val::v2::KuksaClientV2::resolve_ids_for_paths).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SignalRef {
+    Path(String),
+    Id(i32),
+}
+
+impl SignalRef {
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            SignalRef::Path(path) => Some(path),
+            SignalRef::Id(_) => None,
+        }
+    }
+
+    pub fn id(&self) -> Option<i32> {
+        match self {
+            SignalRef::Path(_) => None,
+            SignalRef::Id(id) => Some(*id),
+        }
+    }
+}
+
+impl From<&str> for SignalRef {
+    fn from(path: &str) -> Self {
+        SignalRef::Path(path.to_owned())
+    }
+}
+
+impl From<String> for SignalRef {
+    fn from(path: String) -> Self {
+        SignalRef::Path(path)
+    }
+}
+
+impl From<&String> for SignalRef {
+    fn from(path: &String) -> Self {
+        SignalRef::Path(path.clone())
+    }
+}
+
+impl From<i32> for SignalRef {
+    fn from(id: i32) -> Self {
+        SignalRef::Id(id)
+    }
+}
+
+impl From<SignalRef> for protoV2::SignalId {
+    fn from(signal: SignalRef) -> Self {
+        let signal = match signal {
+            SignalRef::Path(path) => protoV2::signal_id::Signal::Path(path),
+            SignalRef::Id(id) => protoV2::signal_id::Signal::Id(id),
+        };
+        protoV2::SignalId {
+            signal: Some(signal),
+        }
+    }
+}
+
+impl fmt::Display for SignalRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignalRef::Path(path) => f.write_str(path),
+            SignalRef::Id(id) => write!(f, "{id}"),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ServerInfo {
     pub name: String,
@@ -89,3 +162,29 @@ impl OpenProviderStream {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(SignalRef::from("Vehicle.Speed"), "Vehicle.Speed"; "path")]
+    #[test_case(SignalRef::from(42), "42"; "id")]
+    fn test_signal_ref_display(signal: SignalRef, expected: &str) {
+        assert_eq!(signal.to_string(), expected);
+    }
+
+    #[test]
+    fn test_signal_ref_into_signal_id() {
+        let by_path: protoV2::SignalId = SignalRef::from("Vehicle.Speed".to_string()).into();
+        assert_eq!(
+            by_path.signal,
+            Some(protoV2::signal_id::Signal::Path(
+                "Vehicle.Speed".to_string()
+            ))
+        );
+
+        let by_id: protoV2::SignalId = SignalRef::from(42).into();
+        assert_eq!(by_id.signal, Some(protoV2::signal_id::Signal::Id(42)));
+    }
+}
@@ -0,0 +1,148 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+//! Unix domain socket transport for the connection to the databroker.
+//!
+//! Unix domain sockets are addressed with `unix:///path/to/socket` URIs, which
+//! [`to_uri`](super::to_uri) normalizes to `unix://localhost/path/to/socket`.
+//! Characters of the path which are not allowed in uris, e.g. spaces, are
+//! percent-encoded in the uri and decoded again when connecting.
+
+use http::Uri;
+use std::ffi::OsString;
+use std::future::Future;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::net::UnixStream;
+use tonic::codegen::Service;
+
+pub const UNIX_SCHEME: &str = "unix";
+
+// tonic requires a valid http uri for the endpoint even if it is not used for
+// connecting. The authority ends up in the :authority pseudo header.
+const UNIX_ENDPOINT_URI: &str = "http://localhost";
+
+pub fn is_unix_socket_uri(uri: &Uri) -> bool {
+    uri.scheme_str() == Some(UNIX_SCHEME)
+}
+
+/// Parses the part of an `unix:` address following the scheme, e.g.
+/// `///run/kuksa/databroker.sock`, `//localhost/run/kuksa/databroker.sock`
+/// or `/run/kuksa/databroker.sock`.
+pub(crate) fn to_unix_socket_uri(address: &str) -> Result<Uri, String> {
+    let path = match address.strip_prefix("//") {
+        Some(rest) => rest.strip_prefix("localhost").unwrap_or(rest),
+        None => address,
+    };
+    if !path.starts_with('/') || path.len() == 1 {
+        return Err(format!(
+            "Unix socket address must contain an absolute path: unix:{address}"
+        ));
+    }
+    let path = encode_path(Path::new(path));
+    format!("{UNIX_SCHEME}://localhost{path}")
+        .parse::<Uri>()
+        .map_err(|err| format!("{err}"))
+}
+
+// Keeps the characters allowed in uri paths, including '/', all other bytes
+// (and '%' itself) are percent-encoded.
+fn encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for &byte in path.as_os_str().as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => encoded.push(byte as char),
+            b'-' | b'.' | b'_' | b'~' | b'/' | b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*'
+            | b'+' | b',' | b';' | b'=' | b':' | b'@' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+fn decode_path(path: &str) -> PathBuf {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    PathBuf::from(OsString::from_vec(decoded))
+}
+
+pub(crate) fn endpoint() -> tonic::transport::Endpoint {
+    tonic::transport::Endpoint::from_static(UNIX_ENDPOINT_URI)
+}
+
+/// Connector handing out connections to the unix socket at `path`
+/// regardless of the uri requested by the channel.
+#[derive(Debug, Clone)]
+pub(crate) struct UnixConnector {
+    path: PathBuf,
+}
+
+impl UnixConnector {
+    pub fn new(uri: &Uri) -> Self {
+        UnixConnector {
+            path: decode_path(uri.path()),
+        }
+    }
+}
+
+impl Service<Uri> for UnixConnector {
+    type Response = UnixStream;
+    type Error = std::io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _uri: Uri) -> Self::Future {
+        let path = self.path.clone();
+        Box::pin(async move { UnixStream::connect(path).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("/run/kuksa/databroker.sock"; "plain path")]
+    #[test_case("/run/my kuksa/databroker.sock"; "path with space")]
+    #[test_case("/run/kuksa/data%20broker#1.sock"; "path with escape characters")]
+    #[test_case("/run/kuksa/dätäbroker.sock"; "path with non ascii characters")]
+    fn test_connector_path_matches_address(path: &str) {
+        let uri = to_unix_socket_uri(path).unwrap();
+        assert_eq!(UnixConnector::new(&uri).path, PathBuf::from(path));
+    }
+}
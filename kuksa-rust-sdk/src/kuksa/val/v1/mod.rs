@@ -14,7 +14,9 @@
  */
 
 use crate::kuksa::common;
+use crate::kuksa::common::builder::ClientBuilder;
 use crate::kuksa::common::conversion::{ConvertToSDV, ConvertToV1};
+use crate::kuksa::common::options::CallOptions;
 use crate::kuksa::common::types;
 use crate::kuksa::common::ClientTraitV1;
 use http::Uri;
@@ -25,11 +27,19 @@ pub use crate::proto::kuksa::val::v1::{self as proto, DataEntry};
 pub use crate::kuksa::common::{Client, ClientError};
 use crate::proto::kuksa::val::v1;
 
+const PROTOCOL: &str = "kuksa.val.v1";
+
 #[derive(Debug)]
 pub struct KuksaClient {
     pub basic_client: Client,
 }
 
+impl From<Client> for KuksaClient {
+    fn from(basic_client: Client) -> Self {
+        KuksaClient { basic_client }
+    }
+}
+
 impl KuksaClient {
     pub fn new(uri: Uri) -> Self {
         KuksaClient {
@@ -38,40 +48,62 @@ impl KuksaClient {
     }
 
     pub fn from_host(host: &'static str) -> Self {
-        let uri = Uri::from_static(host);
-        Self::new(uri)
+        ClientBuilder::new(host)
+            .build()
+            .expect("host should be a valid uri")
+    }
+
+    pub fn builder(address: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(address)
+    }
+
+    /// Returns a client sharing the connection with this one, which applies
+    /// `call_options` (timeout, metadata, cancellation) to all its calls.
+    pub fn with_call_options(&self, call_options: CallOptions) -> Self {
+        KuksaClient {
+            basic_client: self.basic_client.with_call_options(call_options),
+        }
     }
 
     async fn set(&mut self, entry: DataEntry, _fields: Vec<i32>) -> Result<(), ClientError> {
-        let mut client = v1::val_client::ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
         let set_request = v1::SetRequest {
             updates: vec![v1::EntryUpdate {
                 entry: Some(entry),
                 fields: _fields,
             }],
         };
-        match client.set(set_request).await {
-            Ok(response) => {
-                let message = response.into_inner();
-                let mut errors: Vec<v1::Error> = Vec::new();
-                if let Some(err) = message.error {
-                    errors.push(err);
-                }
-                for error in message.errors {
-                    if let Some(err) = error.error {
-                        errors.push(err);
-                    }
-                }
-                if errors.is_empty() {
-                    Ok(())
-                } else {
-                    Err(ClientError::Function(errors))
+        let response = self
+            .basic_client
+            .call(|channel| {
+                let set_request = set_request.clone();
+                async move {
+                    v1::val_client::ValClient::new(channel)
+                        .set(set_request)
+                        .await
                 }
+            })
+            .await
+            .map_err(|err| {
+                let paths = set_request
+                    .updates
+                    .iter()
+                    .filter_map(|update| update.entry.as_ref());
+                err.with_context("set", paths.map(|entry| entry.path.clone()).collect())
+            })?;
+        let message = response.into_inner();
+        let mut errors: Vec<v1::Error> = Vec::new();
+        if let Some(err) = message.error {
+            errors.push(err);
+        }
+        for error in message.errors {
+            if let Some(err) = error.error {
+                errors.push(err);
             }
-            Err(err) => Err(ClientError::Status(err)),
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ClientError::Function(errors))
         }
     }
 
@@ -81,11 +113,6 @@ impl KuksaClient {
         view: v1::View,
         _fields: Vec<i32>,
     ) -> Result<Vec<DataEntry>, ClientError> {
-        let mut client = v1::val_client::ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
-
         let get_request = v1::GetRequest {
             entries: vec![v1::EntryRequest {
                 path: path.to_string(),
@@ -94,26 +121,33 @@ impl KuksaClient {
             }],
         };
 
-        match client.get(get_request).await {
-            Ok(response) => {
-                let message = response.into_inner();
-                let mut errors = Vec::new();
-                if let Some(err) = message.error {
-                    errors.push(err);
-                }
-                for error in message.errors {
-                    if let Some(err) = error.error {
-                        errors.push(err);
-                    }
-                }
-                if !errors.is_empty() {
-                    Err(ClientError::Function(errors))
-                } else {
-                    // since there is only one DataEntry in the vector return only the according DataEntry
-                    Ok(message.entries.clone())
+        let response = self
+            .basic_client
+            .call_read_only(|channel| {
+                let get_request = get_request.clone();
+                async move {
+                    v1::val_client::ValClient::new(channel)
+                        .get(get_request)
+                        .await
                 }
+            })
+            .await
+            .map_err(|err| err.with_context("get", vec![path.to_owned()]))?;
+        let message = response.into_inner();
+        let mut errors = Vec::new();
+        if let Some(err) = message.error {
+            errors.push(err);
+        }
+        for error in message.errors {
+            if let Some(err) = error.error {
+                errors.push(err);
             }
-            Err(err) => Err(ClientError::Status(err)),
+        }
+        if !errors.is_empty() {
+            Err(ClientError::Function(errors))
+        } else {
+            // since there is only one DataEntry in the vector return only the according DataEntry
+            Ok(message.entries.clone())
         }
     }
 }
@@ -135,10 +169,7 @@ impl common::SDVClientTraitV1 for KuksaClient {
         &mut self,
         datapoints: Self::SensorUpdateType,
     ) -> Result<Self::PublishResponseType, ClientError> {
-        let result = self
-            .set_current_values(datapoints.convert_to_v1())
-            .await
-            .unwrap();
+        let result = self.set_current_values(datapoints.convert_to_v1()).await?;
         let converted_result = result.convert_to_sdv();
         Ok(converted_result)
     }
@@ -149,8 +180,7 @@ impl common::SDVClientTraitV1 for KuksaClient {
     ) -> Result<Self::GetResponseType, ClientError> {
         Ok(self
             .get_current_values(paths.convert_to_v1())
-            .await
-            .unwrap()
+            .await?
             .convert_to_sdv())
     }
 
@@ -158,31 +188,31 @@ impl common::SDVClientTraitV1 for KuksaClient {
         &mut self,
         _paths: Self::SubscribeType,
     ) -> Result<Self::SubscribeResponseType, ClientError> {
-        unimplemented!("Subscribe mechanism has changed. SQL queries not supported anymore")
+        // Subscribe mechanism has changed, SQL queries are not supported anymore
+        Err(ClientError::Unsupported {
+            operation: "subscribe",
+            protocol: PROTOCOL,
+        })
     }
 
     async fn set_datapoints(
         &mut self,
         datapoints: Self::UpdateActuationType,
     ) -> Result<Self::ActuateResponseType, ClientError> {
-        let result = self
-            .set_target_values(datapoints.convert_to_v1())
-            .await
-            .unwrap();
+        let result = self.set_target_values(datapoints.convert_to_v1()).await?;
         let converted_result = result.convert_to_sdv();
         Ok(converted_result)
     }
 
     async fn get_metadata(
         &mut self,
-        paths: Self::PathType,
+        _paths: Self::PathType,
     ) -> Result<Self::MetadataResponseType, ClientError> {
-        Ok(
-            common::ClientTraitV1::get_metadata(self, paths.convert_to_v1())
-                .await
-                .unwrap()
-                .convert_to_sdv(),
-        )
+        // The metadata of kuksa.val.v1 lacks the ids required by sdv.databroker.v1
+        Err(ClientError::Unsupported {
+            operation: "get_metadata",
+            protocol: PROTOCOL,
+        })
     }
 }
 
@@ -253,10 +283,6 @@ impl common::ClientTraitV1 for KuksaClient {
         &mut self,
         paths: Self::PathType,
     ) -> Result<Self::ProvideResponseType, ClientError> {
-        let mut client = v1::val_client::ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
         let mut entries = Vec::new();
         for path in paths {
             entries.push(v1::SubscribeEntry {
@@ -268,10 +294,18 @@ impl common::ClientTraitV1 for KuksaClient {
 
         let req = v1::SubscribeRequest { entries };
 
-        match client.subscribe(req).await {
-            Ok(response) => Ok(response.into_inner()),
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        let response = self
+            .basic_client
+            .call_streaming(|channel| {
+                let req = req.clone();
+                async move { v1::val_client::ValClient::new(channel).subscribe(req).await }
+            })
+            .await
+            .map_err(|err| {
+                let paths = req.entries.iter().map(|entry| entry.path.clone());
+                err.with_context("subscribe", paths.collect())
+            })?;
+        Ok(response.into_inner())
     }
 
     async fn get_target_values(
@@ -301,11 +335,6 @@ impl common::ClientTraitV1 for KuksaClient {
         &mut self,
         paths: Self::SubscribeType,
     ) -> Result<Self::SubscribeResponseType, ClientError> {
-        let mut client = v1::val_client::ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
-
         let mut entries = Vec::new();
         for path in paths {
             entries.push(v1::SubscribeEntry {
@@ -317,10 +346,18 @@ impl common::ClientTraitV1 for KuksaClient {
 
         let req = v1::SubscribeRequest { entries };
 
-        match client.subscribe(req).await {
-            Ok(response) => Ok(response.into_inner()),
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        let response = self
+            .basic_client
+            .call_streaming(|channel| {
+                let req = req.clone();
+                async move { v1::val_client::ValClient::new(channel).subscribe(req).await }
+            })
+            .await
+            .map_err(|err| {
+                let paths = req.entries.iter().map(|entry| entry.path.clone());
+                err.with_context("subscribe", paths.collect())
+            })?;
+        Ok(response.into_inner())
     }
 
     async fn subscribe(
@@ -0,0 +1,539 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+//! Runtime dispatching the actuation requests of the databroker to registered handlers.
+//!
+//! ```no_run
+//! # use kuksa_rust_sdk::kuksa::common::options::CancellationToken;
+//! # use kuksa_rust_sdk::kuksa::val::v2::actuator::ActuatorRuntime;
+//! # use kuksa_rust_sdk::kuksa::val::v2::KuksaClientV2;
+//! # async fn example(client: KuksaClientV2) {
+//! let shutdown = CancellationToken::new();
+//! let result = ActuatorRuntime::new()
+//!     .handler_fn("Vehicle.ADAS.ABS.IsEnabled", |enabled: bool| async move {
+//!         println!("ABS enabled: {enabled}");
+//!         Ok(())
+//!     })
+//!     .run(&client, shutdown)
+//!     .await;
+//! # }
+//! ```
+
+use super::provider::{keep_claimed, ActuationCommand};
+use super::KuksaClientV2;
+use crate::kuksa::common::options::CancellationToken;
+use crate::kuksa::common::reconnect::ReconnectPolicy;
+use crate::kuksa::common::types::SignalRef;
+use crate::kuksa::common::{ClientError, ClientTraitV2};
+use crate::proto::kuksa::val::v2::{
+    signal_id::Signal, value::TypedValue, Error, ErrorCode, SignalId, Value,
+};
+use futures_core::Stream;
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use tokio::task::JoinSet;
+use tokio_stream::StreamExt;
+use tonic::async_trait;
+
+/// Handler of the actuation requests for one actuator.
+///
+/// The requested value is converted to [`ActuatorHandler::Value`] before the handler
+/// is called. Values of another type are rejected with `INVALID_ARGUMENT`.
+#[async_trait]
+pub trait ActuatorHandler: Send + Sync + 'static {
+    type Value: TryFrom<TypedValue> + Send;
+
+    /// Actuates the actuator, the error is reported to the databroker.
+    async fn actuate(&self, value: Self::Value) -> Result<(), Error>;
+}
+
+/// [`ActuatorHandler`] calling an async closure.
+struct FnHandler<T, F> {
+    f: F,
+    value: PhantomData<fn(T)>,
+}
+
+#[async_trait]
+impl<T, F, Fut> ActuatorHandler for FnHandler<T, F>
+where
+    T: TryFrom<TypedValue> + Send + 'static,
+    F: Fn(T) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), Error>> + Send,
+{
+    type Value = T;
+
+    async fn actuate(&self, value: T) -> Result<(), Error> {
+        (self.f)(value).await
+    }
+}
+
+// Object safe form of ActuatorHandler, decoding the value first
+#[async_trait]
+trait ValueHandler: Send + Sync {
+    async fn handle(&self, value: Value) -> Result<(), Error>;
+}
+
+#[async_trait]
+impl<H: ActuatorHandler> ValueHandler for H {
+    async fn handle(&self, value: Value) -> Result<(), Error> {
+        let Some(typed_value) = value.typed_value else {
+            return Err(error(ErrorCode::InvalidArgument, "No value provided"));
+        };
+        let Ok(value) = H::Value::try_from(typed_value) else {
+            return Err(error(
+                ErrorCode::InvalidArgument,
+                "Value has an incompatible type",
+            ));
+        };
+        self.actuate(value).await
+    }
+}
+
+fn error(code: ErrorCode, message: &str) -> Error {
+    Error {
+        code: code as i32,
+        message: message.to_owned(),
+    }
+}
+
+/// Provider of actuators calling the handler registered for each actuator.
+///
+/// The actuation requests of a batch are handled concurrently, every request is
+/// answered with the result of its handler.
+#[derive(Default)]
+pub struct ActuatorRuntime {
+    handlers: HashMap<String, Arc<dyn ValueHandler>>,
+}
+
+impl ActuatorRuntime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the handler of the actuator `path`, replacing a previous one.
+    pub fn handler(mut self, path: impl Into<String>, handler: impl ActuatorHandler) -> Self {
+        self.handlers.insert(path.into(), Arc::new(handler));
+        self
+    }
+
+    /// Registers an async closure as handler of the actuator `path`.
+    pub fn handler_fn<T, F, Fut>(self, path: impl Into<String>, f: F) -> Self
+    where
+        T: TryFrom<TypedValue> + Send + 'static,
+        F: Fn(T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send,
+    {
+        self.handler(
+            path,
+            FnHandler {
+                f,
+                value: PhantomData,
+            },
+        )
+    }
+
+    /// Claims the actuators of all handlers and handles their actuation requests
+    /// until `shutdown` is cancelled.
+    ///
+    /// The provider stream is re-opened and the actuators claimed again after the
+    /// connection has been lost. If the reconnect policy of the client gives up or
+    /// the ids of the actuators differ after reconnecting, the ids are resolved again
+    /// and the actuators are claimed on a new provider stream after the backoff of the
+    /// policy. Returns the error if the databroker rejects the claim. Actuations in
+    /// progress when shutting down are completed before returning.
+    pub async fn run(
+        self,
+        client: &KuksaClientV2,
+        shutdown: CancellationToken,
+    ) -> Result<(), ClientError> {
+        let paths: Vec<String> = self.handlers.keys().cloned().collect();
+        let policy = client
+            .basic_client
+            .get_reconnect_policy()
+            .unwrap_or_default();
+        let claim = || async {
+            // The databroker identifies the actuators by id in its requests
+            let ids = client.resolve_ids_for_paths(paths.clone()).await?;
+            let signals = ids.values().copied().map(SignalRef::Id).collect();
+            let provider = client.provide_actuation(signals).await?;
+            let paths = ids.into_iter().map(|(path, id)| (id, path)).collect();
+            Ok::<_, ClientError>((paths, provider))
+        };
+        self.serve_claimed(claim, policy, shutdown).await
+    }
+
+    // Claims the actuators with `claim` until it is rejected, or `shutdown` is cancelled
+    async fn serve_claimed<S, F, Fut>(
+        &self,
+        claim: F,
+        policy: ReconnectPolicy,
+        shutdown: CancellationToken,
+    ) -> Result<(), ClientError>
+    where
+        S: Stream<Item = Result<ActuationCommand, ClientError>> + Unpin,
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<(HashMap<i32, String>, S), ClientError>>,
+    {
+        let shutdown = &shutdown;
+        let serve = |(paths, commands): (HashMap<i32, String>, S)| async move {
+            self.serve(&paths, commands, shutdown).await
+        };
+        keep_claimed(claim, serve, policy, shutdown).await
+    }
+
+    // Handles the commands until `shutdown` is cancelled or the stream fails
+    async fn serve<S>(
+        &self,
+        paths: &HashMap<i32, String>,
+        mut commands: S,
+        shutdown: &CancellationToken,
+    ) -> Result<(), ClientError>
+    where
+        S: Stream<Item = Result<ActuationCommand, ClientError>> + Unpin,
+    {
+        let mut actuations = JoinSet::new();
+        let result = loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break Ok(()),
+                Some(done) = actuations.join_next(), if !actuations.is_empty() => {
+                    report(done);
+                }
+                command = commands.next() => match command {
+                    Some(Ok(command)) => {
+                        let handler = self.find_handler(paths, &command.signal_id);
+                        actuations.spawn(actuate(handler, command));
+                    }
+                    Some(Err(err)) => break Err(err),
+                    None => break Err(ClientError::Connection("Provider stream closed".to_owned())),
+                },
+            }
+        };
+        while let Some(done) = actuations.join_next().await {
+            report(done);
+        }
+        result
+    }
+
+    fn find_handler(
+        &self,
+        paths: &HashMap<i32, String>,
+        signal_id: &SignalId,
+    ) -> Option<Arc<dyn ValueHandler>> {
+        let path = match &signal_id.signal {
+            Some(Signal::Id(id)) => paths.get(id)?,
+            Some(Signal::Path(path)) => path,
+            None => return None,
+        };
+        self.handlers.get(path).cloned()
+    }
+}
+
+async fn actuate(
+    handler: Option<Arc<dyn ValueHandler>>,
+    mut command: ActuationCommand,
+) -> Result<(), ClientError> {
+    let result = match handler {
+        Some(handler) => {
+            let value = std::mem::take(&mut command.value);
+            // The handler runs as a task of its own to answer the actuation even if it
+            // panics, the set aborts it if the actuation is dropped
+            let mut handling = JoinSet::new();
+            handling.spawn(async move { handler.handle(value).await });
+            match handling
+                .join_next()
+                .await
+                .expect("handler has been spawned")
+            {
+                Ok(result) => result,
+                Err(err) => {
+                    warn!("Handler of actuator {:?} failed: {err}", command.signal_id);
+                    Err(error(ErrorCode::Unspecified, "Actuator handler failed"))
+                }
+            }
+        }
+        None => {
+            warn!("No handler for actuator {:?}", command.signal_id);
+            Err(error(ErrorCode::NotFound, "Actuator is not provided"))
+        }
+    };
+    command.reply(result).await
+}
+
+fn report(done: Result<Result<(), ClientError>, tokio::task::JoinError>) {
+    match done {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => debug!("Could not reply to actuation: {err}"),
+        Err(err) => warn!("Actuation handler failed: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kuksa::val::v2::provider::{ActuationCommands, ProviderStream};
+    use crate::proto::kuksa::val::v2::{
+        open_provider_stream_request, open_provider_stream_response, ActuateRequest,
+        BatchActuateStreamRequest, BatchActuateStreamResponse, OpenProviderStreamRequest,
+        OpenProviderStreamResponse,
+    };
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    struct Recorder {
+        values: Arc<Mutex<Vec<f32>>>,
+    }
+
+    #[async_trait]
+    impl ActuatorHandler for Recorder {
+        type Value = f32;
+
+        async fn actuate(&self, value: f32) -> Result<(), Error> {
+            self.values.lock().unwrap().push(value);
+            Ok(())
+        }
+    }
+
+    fn actuate_request(id: i32, typed_value: TypedValue) -> ActuateRequest {
+        ActuateRequest {
+            signal_id: Some(SignalId {
+                signal: Some(Signal::Id(id)),
+            }),
+            value: Some(Value {
+                typed_value: Some(typed_value),
+            }),
+        }
+    }
+
+    async fn replies(
+        requests: &mut mpsc::Receiver<OpenProviderStreamRequest>,
+        count: usize,
+    ) -> Vec<(i32, Option<Error>)> {
+        let mut replies = Vec::new();
+        for _ in 0..count {
+            match requests.recv().await.unwrap().action {
+                Some(open_provider_stream_request::Action::BatchActuateStreamResponse(
+                    BatchActuateStreamResponse { signal_id, error },
+                )) => {
+                    let Some(Signal::Id(id)) = signal_id.unwrap().signal else {
+                        panic!("reply without id");
+                    };
+                    replies.push((id, error));
+                }
+                other => panic!("unexpected request {other:?}"),
+            }
+        }
+        replies
+    }
+
+    #[tokio::test]
+    async fn test_batch_is_dispatched_to_handlers() {
+        let (sender, mut requests) = mpsc::channel(8);
+        let (responses, receiver) = mpsc::channel(8);
+        let stream = ProviderStream::start(
+            sender,
+            Box::pin(tokio_stream::wrappers::ReceiverStream::new(receiver)),
+        );
+        let commands = stream.actuation_commands();
+
+        let values = Arc::new(Mutex::new(Vec::new()));
+        let runtime = ActuatorRuntime::new()
+            .handler(
+                "Vehicle.Body.Mirrors.DriverSide.Pan",
+                Recorder {
+                    values: values.clone(),
+                },
+            )
+            .handler_fn("Vehicle.ADAS.ABS.IsEnabled", |enabled: bool| async move {
+                if enabled {
+                    Ok(())
+                } else {
+                    Err(error(ErrorCode::PermissionDenied, "ABS must stay enabled"))
+                }
+            });
+        let ids = HashMap::from([
+            ("Vehicle.Body.Mirrors.DriverSide.Pan".to_owned(), 1),
+            ("Vehicle.ADAS.ABS.IsEnabled".to_owned(), 2),
+        ]);
+        let paths = ids.into_iter().map(|(path, id)| (id, path)).collect();
+        let shutdown = CancellationToken::new();
+        let serving = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { runtime.serve(&paths, commands, &shutdown).await }
+        });
+
+        let batch = BatchActuateStreamRequest {
+            actuate_requests: vec![
+                actuate_request(1, TypedValue::Float(0.5)),
+                actuate_request(2, TypedValue::Bool(false)),
+                actuate_request(1, TypedValue::Bool(true)),
+                actuate_request(3, TypedValue::Bool(true)),
+            ],
+        };
+        responses
+            .send(Ok(OpenProviderStreamResponse {
+                action: Some(
+                    open_provider_stream_response::Action::BatchActuateStreamRequest(batch),
+                ),
+            }))
+            .await
+            .unwrap();
+
+        let mut errors: Vec<i32> = replies(&mut requests, 4)
+            .await
+            .into_iter()
+            .filter_map(|(_, error)| error.map(|err| err.code))
+            .collect();
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                ErrorCode::InvalidArgument as i32,
+                ErrorCode::NotFound as i32,
+                ErrorCode::PermissionDenied as i32,
+            ]
+        );
+        assert_eq!(*values.lock().unwrap(), vec![0.5]);
+
+        shutdown.cancel();
+        serving.await.unwrap().unwrap();
+    }
+
+    fn batch(actuate_requests: Vec<ActuateRequest>) -> OpenProviderStreamResponse {
+        OpenProviderStreamResponse {
+            action: Some(
+                open_provider_stream_response::Action::BatchActuateStreamRequest(
+                    BatchActuateStreamRequest { actuate_requests },
+                ),
+            ),
+        }
+    }
+
+    fn fast_policy() -> ReconnectPolicy {
+        ReconnectPolicy::default().with_initial_backoff(Duration::from_millis(1))
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_provider_is_claimed_again() {
+        let (first_sender, _first_requests) = mpsc::channel(8);
+        let (first_responses, receiver) = mpsc::channel(8);
+        let first = ProviderStream::start(
+            first_sender,
+            Box::pin(tokio_stream::wrappers::ReceiverStream::new(receiver)),
+        );
+        let (second_sender, mut second_requests) = mpsc::channel(8);
+        let (second_responses, receiver) = mpsc::channel(8);
+        let second = ProviderStream::start(
+            second_sender,
+            Box::pin(tokio_stream::wrappers::ReceiverStream::new(receiver)),
+        );
+        // The actuator has another id after reconnecting
+        let abs = "Vehicle.ADAS.ABS.IsEnabled".to_owned();
+        let claims = Mutex::new(vec![
+            (
+                HashMap::from([(5, abs.clone())]),
+                second.actuation_commands(),
+            ),
+            (HashMap::from([(2, abs)]), first.actuation_commands()),
+        ]);
+        first_responses
+            .send(Err(tonic::Status::unavailable("shutting down")))
+            .await
+            .unwrap();
+
+        let shutdown = CancellationToken::new();
+        let serving = {
+            let shutdown = shutdown.clone();
+            async move {
+                ActuatorRuntime::new()
+                    .handler_fn("Vehicle.ADAS.ABS.IsEnabled", |_: bool| async { Ok(()) })
+                    .serve_claimed(
+                        || async { Ok(claims.lock().unwrap().pop().unwrap()) },
+                        fast_policy(),
+                        shutdown,
+                    )
+                    .await
+            }
+        };
+        let databroker = async {
+            // Only the re-claimed provider gets the actuation
+            second_responses
+                .send(Ok(batch(vec![actuate_request(5, TypedValue::Bool(true))])))
+                .await
+                .unwrap();
+            let replies = replies(&mut second_requests, 1).await;
+            shutdown.cancel();
+            replies
+        };
+        let (result, replies) = tokio::join!(serving, databroker);
+        result.unwrap();
+        // Handled with the id of the new claim, an unknown id would be NOT_FOUND
+        assert_eq!(replies, vec![(5, None)]);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_claim_stops_runtime() {
+        let result = ActuatorRuntime::new()
+            .serve_claimed(
+                || async {
+                    Err::<(HashMap<i32, String>, ActuationCommands), _>(ClientError::Status(
+                        tonic::Status::already_exists("Provider already claimed"),
+                    ))
+                },
+                fast_policy(),
+                CancellationToken::new(),
+            )
+            .await;
+        assert!(matches!(result, Err(ClientError::Status(_))), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn test_panicking_handler_is_answered() {
+        let (sender, mut requests) = mpsc::channel(8);
+        let (responses, receiver) = mpsc::channel(8);
+        let stream = ProviderStream::start(
+            sender,
+            Box::pin(tokio_stream::wrappers::ReceiverStream::new(receiver)),
+        );
+        let commands = stream.actuation_commands();
+        let runtime = ActuatorRuntime::new()
+            .handler_fn("Vehicle.ADAS.ABS.IsEnabled", |_: bool| async {
+                panic!("actuator broken")
+            });
+        let paths = HashMap::from([(2, "Vehicle.ADAS.ABS.IsEnabled".to_owned())]);
+        let shutdown = CancellationToken::new();
+        let serving = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { runtime.serve(&paths, commands, &shutdown).await }
+        });
+
+        responses
+            .send(Ok(batch(vec![actuate_request(2, TypedValue::Bool(true))])))
+            .await
+            .unwrap();
+        let replies = replies(&mut requests, 1).await;
+        assert_eq!(replies[0].0, 2);
+        assert_eq!(
+            replies[0].1.as_ref().map(|error| error.code),
+            Some(ErrorCode::Unspecified as i32)
+        );
+
+        shutdown.cancel();
+        serving.await.unwrap().unwrap();
+    }
+}
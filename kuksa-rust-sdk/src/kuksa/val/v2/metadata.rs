@@ -0,0 +1,412 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+//! Cache of the signal metadata of the databroker.
+//!
+//! [`KuksaClientV2::resolve_metadata`] fetches the metadata of many signals with as
+//! few `ListMetadata` requests as possible and keeps it in the [`MetadataRegistry`]
+//! of the client, so later lookups of the same paths or ids do not need a round-trip.
+
+use super::KuksaClientV2;
+use crate::kuksa::common::{ClientError, ClientTraitV2, RequestError};
+use crate::proto::kuksa::val::v2::Metadata;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Metadata of the signals resolved by a [`KuksaClientV2`], shared by all its clones.
+///
+/// The entries are dropped when the client reconnects, as the databroker may have
+/// been restarted with another VSS tree in the meantime, and when
+/// [`KuksaClientV2::check_metadata_version`] detects another databroker version.
+#[derive(Debug, Default)]
+pub struct MetadataRegistry {
+    cache: Mutex<Cache>,
+}
+
+#[derive(Debug, Default)]
+struct Cache {
+    // Connection count of the client the entries have been fetched with
+    connection: u64,
+    // Version and commit hash of the databroker, once checked
+    server_version: Option<(String, String)>,
+    by_path: HashMap<String, Metadata>,
+    paths_by_id: HashMap<i32, String>,
+    // Paths matched by the patterns expanded so far
+    patterns: HashMap<String, Vec<String>>,
+}
+
+impl Cache {
+    fn clear(&mut self) {
+        self.by_path.clear();
+        self.paths_by_id.clear();
+        self.patterns.clear();
+    }
+
+    fn insert(&mut self, metadata: &[Metadata]) {
+        for metadata in metadata {
+            self.paths_by_id.insert(metadata.id, metadata.path.clone());
+            self.by_path.insert(metadata.path.clone(), metadata.clone());
+        }
+    }
+}
+
+impl MetadataRegistry {
+    /// Returns the cached metadata of the signal at `path`.
+    pub fn get(&self, path: &str) -> Option<Metadata> {
+        self.lock().by_path.get(path).cloned()
+    }
+
+    /// Returns the cached metadata of the signal with the databroker id `id`.
+    pub fn get_by_id(&self, id: i32) -> Option<Metadata> {
+        let cache = self.lock();
+        let path = cache.paths_by_id.get(&id)?;
+        cache.by_path.get(path).cloned()
+    }
+
+    /// Returns the path of the signal with the databroker id `id`, if cached.
+    pub fn path_of(&self, id: i32) -> Option<String> {
+        self.lock().paths_by_id.get(&id).cloned()
+    }
+
+    /// Number of signals cached.
+    pub fn len(&self) -> usize {
+        self.lock().by_path.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().by_path.is_empty()
+    }
+
+    /// Drops all entries, the next lookups fetch the metadata again.
+    pub fn invalidate(&self) {
+        self.lock().clear();
+    }
+
+    /// Drops the entries fetched with another connection than `connection`.
+    fn sync(&self, connection: u64) {
+        let mut cache = self.lock();
+        if cache.connection != connection {
+            cache.clear();
+            cache.server_version = None;
+            cache.connection = connection;
+        }
+    }
+
+    /// Looks up `paths` (or patterns), returns the ones which are not cached.
+    fn lookup(&self, paths: Vec<String>, resolved: &mut HashMap<String, Metadata>) -> Vec<String> {
+        let cache = self.lock();
+        let mut missing = Vec::new();
+        for path in paths {
+            match cache.patterns.get(&path) {
+                Some(matches) => resolved.extend(
+                    matches
+                        .iter()
+                        .filter_map(|path| cache.by_path.get_key_value(path))
+                        .map(|(path, metadata)| (path.clone(), metadata.clone())),
+                ),
+                None => match cache.by_path.get(&path) {
+                    Some(metadata) => {
+                        resolved.insert(path, metadata.clone());
+                    }
+                    None => missing.push(path),
+                },
+            }
+        }
+        missing
+    }
+
+    /// Caches the `metadata` fetched with the connection `connection`, `pattern` is
+    /// the pattern expanded to it, if any.
+    fn insert(&self, connection: u64, pattern: Option<&str>, metadata: &[Metadata]) {
+        let mut cache = self.lock();
+        // Fetched before a reconnect, may already be outdated
+        if cache.connection != connection {
+            return;
+        }
+        cache.insert(metadata);
+        if let Some(pattern) = pattern {
+            let paths = metadata.iter().map(|metadata| metadata.path.clone());
+            cache.patterns.insert(pattern.to_owned(), paths.collect());
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Cache> {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Number of paths sharing a top-level branch (e.g. `Vehicle`) from which on the whole
+/// branch is fetched instead of each path, as it returns the entire VSS tree.
+const MIN_PATHS_PER_TOP_LEVEL_BRANCH: usize = 32;
+
+fn is_pattern(path: &str) -> bool {
+    path.contains('*')
+}
+
+/// Groups `paths` into the roots of the `ListMetadata` requests needed to resolve them.
+///
+/// Paths sharing their parent branch are fetched with one request for the branch,
+/// which returns all signals below it. Top-level branches are only fetched for at
+/// least [`MIN_PATHS_PER_TOP_LEVEL_BRANCH`] paths. Other paths and patterns are
+/// requested as they are, so resolving a few signals does not fetch a whole tree.
+fn group_by_root(paths: Vec<String>) -> BTreeMap<String, Vec<String>> {
+    let mut by_parent: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut groups = BTreeMap::new();
+    for path in paths {
+        match path.rsplit_once('.') {
+            Some((parent, _)) if !is_pattern(&path) => {
+                by_parent.entry(parent.to_owned()).or_default().push(path)
+            }
+            _ => {
+                groups.insert(path.clone(), vec![path]);
+            }
+        }
+    }
+    for (parent, mut paths) in by_parent {
+        paths.sort();
+        paths.dedup();
+        let top_level = !parent.contains('.');
+        if paths.len() == 1 || (top_level && paths.len() < MIN_PATHS_PER_TOP_LEVEL_BRANCH) {
+            for path in paths {
+                groups.insert(path.clone(), vec![path]);
+            }
+        } else {
+            groups.entry(parent).or_default().extend(paths);
+        }
+    }
+    groups
+}
+
+impl KuksaClientV2 {
+    /// Returns the metadata cached by this client and its clones.
+    pub fn metadata_registry(&self) -> &MetadataRegistry {
+        self.metadata.sync(self.basic_client.connection_count());
+        &self.metadata
+    }
+
+    /// Resolves the metadata of the signals at `paths`, served from the
+    /// [`MetadataRegistry`] where possible.
+    ///
+    /// Paths may contain wildcards (e.g. `Vehicle.Cabin.Door.*.IsOpen`), which are
+    /// expanded to all signals matching them. Paths sharing their parent branch are
+    /// fetched with a single request, except for top-level branches like `Vehicle`
+    /// which would return the whole tree.
+    ///
+    /// Returns (GRPC error code):
+    ///   NOT_FOUND listing all paths which do not exist or patterns matching no signal.
+    ///   UNAUTHENTICATED if no credentials provided or credentials has expired
+    ///
+    pub async fn resolve_metadata(
+        &self,
+        paths: Vec<String>,
+    ) -> Result<HashMap<String, Metadata>, ClientError> {
+        let mut resolved = HashMap::with_capacity(paths.len());
+        let missing = self.metadata_registry().lookup(paths, &mut resolved);
+
+        let mut unknown = Vec::new();
+        for (root, group) in group_by_root(missing) {
+            let metadata = match self.list_metadata((root.clone(), String::new())).await {
+                Ok(metadata) => metadata,
+                Err(ClientError::NotFound(_)) => Vec::new(),
+                Err(err) => return Err(err.with_context("resolve_metadata", group)),
+            };
+            let pattern = Some(root.as_str()).filter(|root| is_pattern(root));
+            self.metadata_registry().insert(
+                self.basic_client.connection_count(),
+                pattern,
+                &metadata,
+            );
+
+            if pattern.is_some() {
+                if metadata.is_empty() {
+                    unknown.push(root);
+                }
+                resolved.extend(
+                    metadata
+                        .into_iter()
+                        .map(|metadata| (metadata.path.clone(), metadata)),
+                );
+                continue;
+            }
+            let mut by_path: HashMap<String, Metadata> = metadata
+                .into_iter()
+                .map(|metadata| (metadata.path.clone(), metadata))
+                .collect();
+            for path in group {
+                match by_path.remove(&path) {
+                    Some(metadata) => {
+                        resolved.insert(path, metadata);
+                    }
+                    None => unknown.push(path),
+                }
+            }
+        }
+
+        if !unknown.is_empty() {
+            return Err(ClientError::NotFound(RequestError {
+                operation: "resolve_metadata",
+                status: tonic::Status::not_found(format!(
+                    "No metadata found for {}",
+                    unknown.join(", ")
+                )),
+                signals: unknown,
+            }));
+        }
+        Ok(resolved)
+    }
+
+    /// Returns the paths of all signals matching `pattern`, e.g. `Vehicle.Cabin.**`.
+    ///
+    /// Returns (GRPC error code):
+    ///   NOT_FOUND if no signal matches the pattern.
+    ///   UNAUTHENTICATED if no credentials provided or credentials has expired
+    ///
+    pub async fn expand_paths(
+        &self,
+        pattern: impl Into<String>,
+    ) -> Result<Vec<String>, ClientError> {
+        let resolved = self.resolve_metadata(vec![pattern.into()]).await?;
+        let mut paths: Vec<String> = resolved.into_keys().collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Fetches the version of the databroker and drops the [`MetadataRegistry`] if it
+    /// differs from the version seen before, e.g. after an update of the databroker
+    /// which was not noticed as reconnect.
+    ///
+    /// Returns whether the registry has been dropped.
+    pub async fn check_metadata_version(&self) -> Result<bool, ClientError> {
+        let server_info = self.get_server_info().await?;
+        let version = (server_info.version, server_info.commit_hash);
+        let mut cache = self.metadata_registry().lock();
+        let changed = matches!(&cache.server_version, Some(known) if *known != version);
+        if changed {
+            cache.clear();
+        }
+        cache.server_version = Some(version);
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn metadata(id: i32, path: &str) -> Metadata {
+        Metadata {
+            id,
+            path: path.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test_case(&["Vehicle.Speed"], &[("Vehicle.Speed", &["Vehicle.Speed"])]; "single path")]
+    #[test_case(
+        &["Vehicle.Cabin.Door.Row1.Left.IsOpen", "Vehicle.Cabin.Door.Row1.Left.IsLocked", "Vehicle.Speed"],
+        &[
+            ("Vehicle.Cabin.Door.Row1.Left", &["Vehicle.Cabin.Door.Row1.Left.IsLocked", "Vehicle.Cabin.Door.Row1.Left.IsOpen"]),
+            ("Vehicle.Speed", &["Vehicle.Speed"]),
+        ];
+        "common parent"
+    )]
+    #[test_case(
+        &["Vehicle.Cabin.Door.*.IsOpen", "Vehicle.Cabin.Door.Row1.Left.IsOpen"],
+        &[
+            ("Vehicle.Cabin.Door.*.IsOpen", &["Vehicle.Cabin.Door.*.IsOpen"]),
+            ("Vehicle.Cabin.Door.Row1.Left.IsOpen", &["Vehicle.Cabin.Door.Row1.Left.IsOpen"]),
+        ];
+        "pattern"
+    )]
+    #[test_case(&["Vehicle.Speed", "Vehicle.Speed"], &[("Vehicle.Speed", &["Vehicle.Speed"])]; "duplicate")]
+    #[test_case(
+        &["Vehicle.Speed", "Vehicle.AverageSpeed"],
+        &[
+            ("Vehicle.AverageSpeed", &["Vehicle.AverageSpeed"]),
+            ("Vehicle.Speed", &["Vehicle.Speed"]),
+        ];
+        "top-level siblings"
+    )]
+    fn test_group_by_root(paths: &[&str], expected: &[(&str, &[&str])]) {
+        let groups = group_by_root(paths.iter().map(|path| path.to_string()).collect());
+        let expected: BTreeMap<String, Vec<String>> = expected
+            .iter()
+            .map(|(root, paths)| {
+                let paths = paths.iter().map(|path| path.to_string()).collect();
+                (root.to_string(), paths)
+            })
+            .collect();
+        assert_eq!(groups, expected);
+    }
+
+    #[test]
+    fn test_many_top_level_siblings_are_fetched_with_branch() {
+        let paths: Vec<String> = (0..MIN_PATHS_PER_TOP_LEVEL_BRANCH)
+            .map(|index| format!("Vehicle.Signal{index}"))
+            .collect();
+        let groups = group_by_root(paths.clone());
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups["Vehicle"].len(), paths.len());
+
+        let groups = group_by_root(paths[1..].to_vec());
+        assert_eq!(groups.len(), paths.len() - 1);
+    }
+
+    #[test]
+    fn test_registry_serves_cached_paths_and_patterns() {
+        let registry = MetadataRegistry::default();
+        registry.insert(0, None, &[metadata(1, "Vehicle.Speed")]);
+        registry.insert(
+            0,
+            Some("Vehicle.Cabin.Door.*.IsOpen"),
+            &[
+                metadata(2, "Vehicle.Cabin.Door.Row1.Left.IsOpen"),
+                metadata(3, "Vehicle.Cabin.Door.Row1.Right.IsOpen"),
+            ],
+        );
+
+        let mut resolved = HashMap::new();
+        let missing = registry.lookup(
+            vec![
+                "Vehicle.Speed".to_owned(),
+                "Vehicle.Cabin.Door.*.IsOpen".to_owned(),
+                "Vehicle.AverageSpeed".to_owned(),
+            ],
+            &mut resolved,
+        );
+        assert_eq!(missing, vec!["Vehicle.AverageSpeed".to_owned()]);
+        assert_eq!(resolved.len(), 3);
+        assert_eq!(
+            registry.path_of(3).unwrap(),
+            "Vehicle.Cabin.Door.Row1.Right.IsOpen"
+        );
+        assert_eq!(registry.get_by_id(1).unwrap().path, "Vehicle.Speed");
+    }
+
+    #[test]
+    fn test_registry_is_dropped_on_reconnect() {
+        let registry = MetadataRegistry::default();
+        registry.insert(0, None, &[metadata(1, "Vehicle.Speed")]);
+        registry.sync(0);
+        assert_eq!(registry.len(), 1);
+
+        registry.sync(1);
+        assert!(registry.is_empty());
+        // Fetched before the reconnect
+        registry.insert(0, None, &[metadata(1, "Vehicle.Speed")]);
+        assert!(registry.get("Vehicle.Speed").is_none());
+    }
+}
@@ -12,72 +12,125 @@
  *  SPDX-License-Identifier: Apache-2.0
  * ******************************************************************************
  */
+pub mod actuator;
+pub mod metadata;
+pub mod options;
+pub mod provider;
+pub mod sensor;
+pub mod signal;
+pub mod subscription;
+pub mod updates;
+
 use crate::kuksa::common;
+use crate::kuksa::common::builder::ClientBuilder;
+use crate::kuksa::common::jwt::Permission;
+use crate::kuksa::common::options::CallOptions;
 use crate::kuksa::common::types;
+use crate::kuksa::common::{AuthenticatedChannel, RequestError, ServiceStub};
 pub use crate::kuksa::common::{Client, ClientError, ClientTraitV2};
 use crate::proto::kuksa::val::v2::{
-    signal_id::Signal::Path, val_client::ValClient, ActuateRequest, BatchActuateRequest, Datapoint,
-    GetServerInfoRequest, GetValueRequest, GetValuesRequest, ListMetadataRequest,
-    PublishValueRequest, SignalId, SubscribeByIdRequest, SubscribeRequest, Value,
+    val_client::ValClient, ActuateRequest, BatchActuateRequest, Datapoint, GetServerInfoRequest,
+    GetValueRequest, GetValuesRequest, ListMetadataRequest, PublishValueRequest, SignalId,
+    SubscribeByIdRequest, SubscribeRequest, Value,
 };
-use crate::v2_proto::Filter;
 use http::Uri;
+pub use options::SubscribeOptions;
 use prost_types::Timestamp;
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::sync::Arc;
 use std::time::SystemTime;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::async_trait;
 
 use crate::kuksa::common::conversion::{ConvertToV1, ConvertToV2};
-use crate::kuksa::common::types::{OpenProviderStream, ServerInfo};
+use crate::kuksa::common::types::{OpenProviderStream, ServerInfo, SignalRef};
+
+// Type aliases V2 of types defined by this module
+pub type SubscribeOptionsTypeV2 = SubscribeOptions;
+pub type ProvideResponseTypeV2 = provider::ActuationProvider;
+
+const PROTOCOL: &str = "kuksa.val.v2";
+
+/// Typed client of the `kuksa.val.v2.VAL` service cached by the [`Client`].
+pub type ValStub = ValClient<AuthenticatedChannel>;
 
-#[derive(Debug)]
+impl ServiceStub for ValStub {
+    fn from_channel(channel: AuthenticatedChannel) -> Self {
+        ValClient::new(channel)
+    }
+}
+
+/// Client of the `kuksa.val.v2` API.
+///
+/// Clones share the connection, so a client can be cloned and used from many tasks
+/// concurrently while all calls are multiplexed over one HTTP/2 channel.
+#[derive(Debug, Clone)]
 pub struct KuksaClientV2 {
     pub basic_client: Client,
+    metadata: Arc<metadata::MetadataRegistry>,
 }
 
-impl KuksaClientV2 {
-    pub fn new(uri: Uri) -> Self {
+impl From<Client> for KuksaClientV2 {
+    fn from(basic_client: Client) -> Self {
         KuksaClientV2 {
-            basic_client: Client::new(uri.clone()),
+            basic_client,
+            metadata: Arc::default(),
         }
     }
+}
+
+impl KuksaClientV2 {
+    pub fn new(uri: Uri) -> Self {
+        KuksaClientV2::from(Client::new(uri))
+    }
 
     pub fn from_host(host: &'static str) -> Self {
-        let uri = Uri::from_static(host);
-        Self::new(uri)
+        ClientBuilder::new(host)
+            .build()
+            .expect("host should be a valid uri")
+    }
+
+    pub fn builder(address: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(address)
+    }
+
+    /// Returns a client sharing the connection with this one, which applies
+    /// `call_options` (timeout, metadata, cancellation) to all its calls.
+    pub fn with_call_options(&self, call_options: CallOptions) -> Self {
+        KuksaClientV2 {
+            basic_client: self.basic_client.with_call_options(call_options),
+            metadata: self.metadata.clone(),
+        }
     }
 
     /// Resolves the databroker ids for the specified list of paths and returns them in a HashMap<String, i32>
     ///
+    /// The ids are cached, see [`KuksaClientV2::resolve_metadata`].
+    ///
     /// Returns (GRPC error code):
-    ///   NOT_FOUND if the specified root branch does not exist.
+    ///   NOT_FOUND listing all paths which do not exist.
     ///   UNAUTHENTICATED if no credentials provided or credentials has expired
     ///
     pub async fn resolve_ids_for_paths(
-        &mut self,
+        &self,
         vss_paths: Vec<String>,
     ) -> Result<HashMap<String, i32>, ClientError> {
-        let mut hash_map = HashMap::new();
-
-        for path in vss_paths {
-            let vec = self.list_metadata((path, "*".to_string())).await?;
-            let metadata = vec.first().unwrap();
-
-            hash_map.insert(metadata.path.clone(), metadata.id);
-        }
-
-        Ok(hash_map)
+        let metadata = self
+            .resolve_metadata(vss_paths)
+            .await
+            .map_err(|err| err.with_context("resolve_ids_for_paths", vec![]))?;
+        Ok(metadata
+            .into_iter()
+            .map(|(path, metadata)| (path, metadata.id))
+            .collect())
     }
 
-    fn convert_to_actuate_requests(values: HashMap<String, Value>) -> Vec<ActuateRequest> {
+    fn convert_to_actuate_requests(values: HashMap<SignalRef, Value>) -> Vec<ActuateRequest> {
         let mut actuate_requests = Vec::with_capacity(values.len());
-        for (signal_path, value) in values {
+        for (signal, value) in values {
             let actuate_request = ActuateRequest {
-                signal_id: Some(SignalId {
-                    signal: Some(Path(signal_path)),
-                }),
+                signal_id: Some(signal.into()),
                 value: Some(value),
             };
 
@@ -87,6 +140,14 @@ impl KuksaClientV2 {
     }
 }
 
+fn invalid_argument(operation: &'static str, signals: Vec<String>, message: String) -> ClientError {
+    ClientError::InvalidArgument(RequestError {
+        operation,
+        signals,
+        status: tonic::Status::invalid_argument(message),
+    })
+}
+
 #[async_trait]
 impl common::ClientTraitV1 for KuksaClientV2 {
     type SensorUpdateType = types::SensorUpdateTypeV1;
@@ -117,8 +178,7 @@ impl common::ClientTraitV1 for KuksaClientV2 {
     ) -> Result<Self::GetResponseType, ClientError> {
         Ok(self
             .get_values(paths.convert_to_v2())
-            .await
-            .unwrap()
+            .await?
             .convert_to_v1())
     }
 
@@ -126,54 +186,53 @@ impl common::ClientTraitV1 for KuksaClientV2 {
         &mut self,
         _paths: Self::PathType,
     ) -> Result<Self::ProvideResponseType, ClientError> {
-        unimplemented!("The concept behind target and current value has changed! Target values will not get stored anymore.")
+        // The concept behind target and current value has changed! Target values will not get stored anymore.
         // here we could default to call a kuksa.val.v1 function as well but I would not recommend.
         // This would suggerate that it still works which it won't
         // Other option would be to open a provider stream here and return stuff but this would change the return type aka the dev has to adapt anyways.
+        Err(ClientError::Unsupported {
+            operation: "subscribe_target_values",
+            protocol: PROTOCOL,
+        })
     }
 
     async fn get_target_values(
         &mut self,
         _paths: Self::PathType,
     ) -> Result<Self::GetResponseType, ClientError> {
-        unimplemented!("The concept behind target and current value has changed! Target values will not get stored anymore.")
-        // here we could default to call a kuksa.val.v1 function as well but I would not recommend.
-        // This would suggerate that it still works which it won't
-        // Other option would be to open a provider stream here and return stuff but this would change the return type aka the dev has to adapt anyways.
+        // The concept behind target and current value has changed! Target values will not get stored anymore.
+        Err(ClientError::Unsupported {
+            operation: "get_target_values",
+            protocol: PROTOCOL,
+        })
     }
 
     async fn subscribe_current_values(
         &mut self,
-        paths: Self::SubscribeType,
+        _paths: Self::SubscribeType,
     ) -> Result<Self::SubscribeResponseType, ClientError> {
-        Ok(
-            ClientTraitV2::subscribe(self, paths.convert_to_v2(), None, None)
-                .await
-                .unwrap()
-                .convert_to_v1(),
-        )
+        // Streams of kuksa.val.v2 cannot be converted into kuksa.val.v1 streams
+        Err(ClientError::Unsupported {
+            operation: "subscribe_current_values",
+            protocol: PROTOCOL,
+        })
     }
 
     async fn subscribe(
         &mut self,
-        paths: Self::SubscribeType,
+        _paths: Self::SubscribeType,
     ) -> Result<Self::SubscribeResponseType, ClientError> {
-        Ok(
-            ClientTraitV2::subscribe(self, paths.convert_to_v2(), None, None)
-                .await
-                .unwrap()
-                .convert_to_v1(),
-        )
+        Err(ClientError::Unsupported {
+            operation: "subscribe",
+            protocol: PROTOCOL,
+        })
     }
 
     async fn set_target_values(
         &mut self,
         datapoints: Self::UpdateActuationType,
     ) -> Result<Self::ActuateResponseType, ClientError> {
-        let result = self
-            .batch_actuate(datapoints.convert_to_v2())
-            .await
-            .unwrap();
+        let result = self.batch_actuate(datapoints.convert_to_v2()).await?;
         let converted_result = result.convert_to_v1();
         Ok(converted_result)
     }
@@ -182,7 +241,7 @@ impl common::ClientTraitV1 for KuksaClientV2 {
         &mut self,
         paths: Self::PathType,
     ) -> Result<Self::MetadataResponseType, ClientError> {
-        let result = self.list_metadata(paths.convert_to_v2()).await.unwrap();
+        let result = self.list_metadata(paths.convert_to_v2()).await?;
         let converted_result = result.convert_to_v1();
         Ok(converted_result)
     }
@@ -193,17 +252,18 @@ impl ClientTraitV2 for KuksaClientV2 {
     type SensorUpdateType = types::SensorUpdateTypeV2;
     type UpdateActuationType = types::UpdateActuationTypeV2;
     type MultipleUpdateActuationType = types::MultipleUpdateActuationTypeV2;
-    type PathType = types::PathTypeV2;
-    type PathsType = types::PathsTypeV2;
+    type SignalType = types::SignalTypeV2;
+    type SignalsType = types::SignalsTypeV2;
     type IdsType = types::IdsTypeV2;
     type SubscribeType = types::SubscribeTypeV2;
     type SubscribeByIdType = types::SubscribeByIdTypeV2;
+    type SubscribeOptionsType = SubscribeOptionsTypeV2;
     type PublishResponseType = types::PublishResponseTypeV2;
     type GetResponseType = types::GetResponseTypeV2;
     type MultipleGetResponseType = types::MultipleGetResponseTypeV2;
     type SubscribeResponseType = types::SubscribeResponseTypeV2;
     type SubscribeByIdResponseType = types::SubscribeByIdResponseTypeV2;
-    type ProvideResponseType = types::ProvideResponseTypeV2;
+    type ProvideResponseType = ProvideResponseTypeV2;
     type ActuateResponseType = types::ActuateResponseTypeV2;
     type OpenProviderStreamResponseType = types::OpenProviderStreamResponseTypeV2;
     type MetadataType = types::MetadataTypeV2;
@@ -222,27 +282,24 @@ impl ClientTraitV2 for KuksaClientV2 {
     ///       - MAX_REQUEST_PATH_LENGTH: usize = 1000;
     ///
     async fn get_value(
-        &mut self,
-        path: Self::PathType,
+        &self,
+        signal: impl Into<Self::SignalType> + Send,
     ) -> Result<Self::GetResponseType, ClientError> {
-        let mut client = ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
-
+        let signal = signal.into();
         let get_value_request = GetValueRequest {
-            signal_id: Some(SignalId {
-                signal: Some(Path(path)),
-            }),
+            signal_id: Some(signal.clone().into()),
         };
 
-        match client.get_value(get_value_request).await {
-            Ok(response) => {
-                let message = response.into_inner();
-                Ok(message.data_point)
-            }
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        let response = self
+            .basic_client
+            .call_stub_read_only(|mut stub: ValStub| {
+                let get_value_request = get_value_request.clone();
+                async move { stub.get_value(get_value_request).await }
+            })
+            .await
+            .map_err(|err| err.with_context("get_value", vec![signal.to_string()]))?;
+        let message = response.into_inner();
+        Ok(message.data_point)
     }
 
     /// Get the latest values of a set of signals.
@@ -257,30 +314,24 @@ impl ClientTraitV2 for KuksaClientV2 {
     ///       - MAX_REQUEST_PATH_LENGTH: usize = 1000;
     ///
     async fn get_values(
-        &mut self,
-        signal_paths: Self::PathsType,
+        &self,
+        signals: Self::SignalsType,
     ) -> Result<Self::MultipleGetResponseType, ClientError> {
-        let mut client = ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
-
-        let signal_ids: Vec<SignalId> = signal_paths
-            .iter()
-            .map(move |signal_path| SignalId {
-                signal: Some(Path(signal_path.to_string())),
-            })
-            .collect();
+        let signal_names = signals.iter().map(SignalRef::to_string).collect();
+        let signal_ids: Vec<SignalId> = signals.into_iter().map(SignalId::from).collect();
 
         let get_values_request = GetValuesRequest { signal_ids };
 
-        match client.get_values(get_values_request).await {
-            Ok(response) => {
-                let message = response.into_inner();
-                Ok(message.data_points)
-            }
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        let response = self
+            .basic_client
+            .call_stub_read_only(|mut stub: ValStub| {
+                let get_values_request = get_values_request.clone();
+                async move { stub.get_values(get_values_request).await }
+            })
+            .await
+            .map_err(|err| err.with_context("get_values", signal_names))?;
+        let message = response.into_inner();
+        Ok(message.data_points)
     }
 
     /// Publish a signal value. Used for low frequency signals (e.g. attributes).
@@ -298,36 +349,39 @@ impl ClientTraitV2 for KuksaClientV2 {
     ///       - if the published value is out of the min/max range specified
     ///
     async fn publish_value(
-        &mut self,
-        signal_path: Self::PathType,
+        &self,
+        signal: impl Into<Self::SignalType> + Send,
         value: Self::SensorUpdateType,
     ) -> Result<Self::PublishResponseType, ClientError> {
-        let mut client = ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
+        let signal = signal.into();
+        self.basic_client
+            .check_permission(Permission::Provide, signal.path())
+            .await
+            .map_err(|err| err.with_context("publish_value", vec![]))?;
 
         let now = SystemTime::now();
         let duration_since_epoch = now
             .duration_since(SystemTime::UNIX_EPOCH)
-            .expect("Clock may have gone backwards");
+            .unwrap_or_default();
         let seconds = duration_since_epoch.as_secs() as i64;
         let nanos = duration_since_epoch.subsec_nanos() as i32;
 
         let publish_value_request = PublishValueRequest {
-            signal_id: Some(SignalId {
-                signal: Some(Path(signal_path)),
-            }),
+            signal_id: Some(signal.clone().into()),
             data_point: Some(Datapoint {
                 timestamp: Some(Timestamp { seconds, nanos }),
                 value: Some(value),
             }),
         };
 
-        match client.publish_value(publish_value_request).await {
-            Ok(_response) => Ok(()),
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        self.basic_client
+            .call_stub(|mut stub: ValStub| {
+                let publish_value_request = publish_value_request.clone();
+                async move { stub.publish_value(publish_value_request).await }
+            })
+            .await
+            .map_err(|err| err.with_context("publish_value", vec![signal.to_string()]))?;
+        Ok(())
     }
 
     /// Actuate a single actuator
@@ -347,26 +401,29 @@ impl ClientTraitV2 for KuksaClientV2 {
     ///       - if the provided value is out of the min/max range specified
     ///
     async fn actuate(
-        &mut self,
-        signal_path: Self::PathType,
+        &self,
+        signal: impl Into<Self::SignalType> + Send,
         value: Self::UpdateActuationType,
     ) -> Result<Self::ActuateResponseType, ClientError> {
-        let mut client = ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
+        let signal = signal.into();
+        self.basic_client
+            .check_permission(Permission::Actuate, signal.path())
+            .await
+            .map_err(|err| err.with_context("actuate", vec![]))?;
 
         let actuate_request = ActuateRequest {
-            signal_id: Some(SignalId {
-                signal: Some(Path(signal_path)),
-            }),
+            signal_id: Some(signal.clone().into()),
             value: Some(value),
         };
 
-        match client.actuate(actuate_request).await {
-            Ok(_response) => Ok(()),
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        self.basic_client
+            .call_stub(|mut stub: ValStub| {
+                let actuate_request = actuate_request.clone();
+                async move { stub.actuate(actuate_request).await }
+            })
+            .await
+            .map_err(|err| err.with_context("actuate", vec![signal.to_string()]))?;
+        Ok(())
     }
 
     /// Actuate simultaneously multiple actuators.
@@ -388,22 +445,30 @@ impl ClientTraitV2 for KuksaClientV2 {
     ///       - if any of the provided actuators values are out of the min/max range specified
     ///
     async fn batch_actuate(
-        &mut self,
+        &self,
         values: Self::MultipleUpdateActuationType,
     ) -> Result<Self::ActuateResponseType, ClientError> {
-        let mut client = ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
+        self.basic_client
+            .check_permission(
+                Permission::Actuate,
+                values.keys().filter_map(SignalRef::path),
+            )
+            .await
+            .map_err(|err| err.with_context("batch_actuate", vec![]))?;
 
+        let signal_names: Vec<String> = values.keys().map(SignalRef::to_string).collect();
         let actuate_requests = Self::convert_to_actuate_requests(values);
 
         let batch_actuate_request = BatchActuateRequest { actuate_requests };
 
-        match client.batch_actuate(batch_actuate_request).await {
-            Ok(_response) => Ok(()),
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        self.basic_client
+            .call_stub(|mut stub: ValStub| {
+                let batch_actuate_request = batch_actuate_request.clone();
+                async move { stub.batch_actuate(batch_actuate_request).await }
+            })
+            .await
+            .map_err(|err| err.with_context("batch_actuate", signal_names))?;
+        Ok(())
     }
 
     /// Subscribe to a set of signals using string path parameters
@@ -416,6 +481,7 @@ impl ClientTraitV2 for KuksaClientV2 {
     ///             MAX_REQUEST_PATH_LENGTH: usize = 1000;
     ///       - if buffer_size exceeds the maximum permitted
     ///             MAX_BUFFER_SIZE: usize = 1000;
+    ///     These limits are checked before subscribing, see [`SubscribeOptions`].
     ///
     /// When subscribing, Databroker shall immediately return the value for all
     /// subscribed entries.
@@ -425,30 +491,28 @@ impl ClientTraitV2 for KuksaClientV2 {
     /// to the specified buffer_size before the oldest messages are dropped.
     ///
     async fn subscribe(
-        &mut self,
+        &self,
         signal_paths: Self::SubscribeType,
-        buffer_size: Option<u32>,
-        min_sample_interval_ms: Option<u32>,
+        options: Self::SubscribeOptionsType,
     ) -> Result<Self::SubscribeResponseType, ClientError> {
-        let mut client = ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
-
+        options
+            .validate_paths(&signal_paths)
+            .map_err(|message| invalid_argument("subscribe", signal_paths.clone(), message))?;
         let subscribe_request = SubscribeRequest {
             signal_paths,
-            buffer_size: buffer_size.unwrap_or(0),
-            filter: min_sample_interval_ms.map(|interval_ms| Filter {
-                min_sample_interval: Some(crate::v2_proto::SampleInterval { interval_ms }),
-                // Default behavior might change - No clear definition on provider side yet
-                duration_ms: 0,
-            }),
+            buffer_size: options.buffer_size(),
+            filter: options.filter().cloned(),
         };
 
-        match client.subscribe(subscribe_request).await {
-            Ok(response) => Ok(response.into_inner()),
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        let response = self
+            .basic_client
+            .call_stub_streaming(|mut stub: ValStub| {
+                let subscribe_request = subscribe_request.clone();
+                async move { stub.subscribe(subscribe_request).await }
+            })
+            .await
+            .map_err(|err| err.with_context("subscribe", subscribe_request.signal_paths.clone()))?;
+        Ok(response.into_inner())
     }
 
     /// Subscribe to a set of signals using i32 id parameters.
@@ -462,6 +526,7 @@ impl ClientTraitV2 for KuksaClientV2 {
     ///             MAX_REQUEST_PATH_LENGTH: usize = 1000;
     ///       - if buffer_size exceeds the maximum permitted
     ///             MAX_BUFFER_SIZE: usize = 1000;
+    ///     These limits are checked before subscribing, see [`SubscribeOptions`].
     ///
     /// When subscribing, Databroker shall immediately return the value for all
     /// subscribed entries.
@@ -471,30 +536,32 @@ impl ClientTraitV2 for KuksaClientV2 {
     /// to the specified buffer_size before the oldest messages are dropped.
     ///
     async fn subscribe_by_id(
-        &mut self,
+        &self,
         signal_ids: Self::SubscribeByIdType,
-        buffer_size: Option<u32>,
-        min_sample_interval_ms: Option<u32>,
+        options: Self::SubscribeOptionsType,
     ) -> Result<Self::SubscribeByIdResponseType, ClientError> {
-        let mut client = ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
-
+        options.validate_ids(&signal_ids).map_err(|message| {
+            let signals = signal_ids.iter().map(i32::to_string).collect();
+            invalid_argument("subscribe_by_id", signals, message)
+        })?;
         let subscribe_by_id_request = SubscribeByIdRequest {
             signal_ids,
-            buffer_size: buffer_size.unwrap_or(0),
-            filter: min_sample_interval_ms.map(|interval_ms| Filter {
-                min_sample_interval: Some(crate::v2_proto::SampleInterval { interval_ms }),
-                // Default behavior might change - No clear definition on provider side yet
-                duration_ms: 0,
-            }),
+            buffer_size: options.buffer_size(),
+            filter: options.filter().cloned(),
         };
 
-        match client.subscribe_by_id(subscribe_by_id_request).await {
-            Ok(response) => Ok(response.into_inner()),
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        let response = self
+            .basic_client
+            .call_stub_streaming(|mut stub: ValStub| {
+                let subscribe_by_id_request = subscribe_by_id_request.clone();
+                async move { stub.subscribe_by_id(subscribe_by_id_request).await }
+            })
+            .await
+            .map_err(|err| {
+                let signal_ids = subscribe_by_id_request.signal_ids.iter();
+                err.with_context("subscribe_by_id", signal_ids.map(i32::to_string).collect())
+            })?;
+        Ok(response.into_inner())
     }
 
     /// Open a stream used to provide actuation and/or publishing values using
@@ -529,24 +596,21 @@ impl ClientTraitV2 for KuksaClientV2 {
     ///        No error definition, a BatchActuateStreamResponse is expected from provider.
     ///
     async fn open_provider_stream(
-        &mut self,
+        &self,
         buffer_size: Option<usize>,
     ) -> Result<Self::OpenProviderStreamResponseType, ClientError> {
-        let mut client = ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
+        self.basic_client
+            .call_stub_streaming(|mut stub: ValStub| async move {
+                // The request stream is consumed by the call, so a new one is needed per attempt
+                let (sender, receiver) = tokio::sync::mpsc::channel(buffer_size.unwrap_or(1));
+                let receiver_stream = ReceiverStream::new(receiver);
 
-        let (sender, receiver) = tokio::sync::mpsc::channel(buffer_size.unwrap_or(1));
-        let receiver_stream = ReceiverStream::new(receiver);
-
-        match client.open_provider_stream(receiver_stream).await {
-            Ok(response) => {
+                let response = stub.open_provider_stream(receiver_stream).await?;
                 let message = response.into_inner();
                 Ok(OpenProviderStream::new(sender, message))
-            }
-            Err(err) => Err(ClientError::Status(err)),
-        }
+            })
+            .await
+            .map_err(|err| err.with_context("open_provider_stream", vec![]))
     }
 
     /// List metadata of signals matching the request.
@@ -557,56 +621,70 @@ impl ClientTraitV2 for KuksaClientV2 {
     ///   INVALID_ARGUMENT if the provided path or wildcard is wrong.
     ///
     async fn list_metadata(
-        &mut self,
+        &self,
         tuple: Self::MetadataType,
     ) -> Result<Self::MetadataResponseType, ClientError> {
-        let mut client = ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
-
         let list_metadata_request = ListMetadataRequest {
             root: tuple.0,
             filter: tuple.1,
         };
 
-        match client.list_metadata(list_metadata_request).await {
-            Ok(response) => {
-                let metadata_response = response.into_inner();
-                Ok(metadata_response.metadata)
-            }
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        let response = self
+            .basic_client
+            .call_stub_read_only(|mut stub: ValStub| {
+                let list_metadata_request = list_metadata_request.clone();
+                async move { stub.list_metadata(list_metadata_request).await }
+            })
+            .await
+            .map_err(|err| {
+                err.with_context("list_metadata", vec![list_metadata_request.root.clone()])
+            })?;
+        let metadata_response = response.into_inner();
+        Ok(metadata_response.metadata)
     }
 
     /// Get server information
-    async fn get_server_info(&mut self) -> Result<Self::ServerInfoType, ClientError> {
-        let mut client = ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
-
+    async fn get_server_info(&self) -> Result<Self::ServerInfoType, ClientError> {
         let get_server_info_request = GetServerInfoRequest {};
 
-        match client.get_server_info(get_server_info_request).await {
-            Ok(response) => {
-                let get_server_info_response = response.into_inner();
-                let server_info = ServerInfo {
-                    name: get_server_info_response.name,
-                    commit_hash: get_server_info_response.commit_hash,
-                    version: get_server_info_response.version,
-                };
-                Ok(server_info)
-            }
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        let response = self
+            .basic_client
+            .call_stub_read_only(|mut stub: ValStub| {
+                let get_server_info_request = get_server_info_request.clone();
+                async move { stub.get_server_info(get_server_info_request).await }
+            })
+            .await
+            .map_err(|err| err.with_context("get_server_info", vec![]))?;
+        let get_server_info_response = response.into_inner();
+        let server_info = ServerInfo {
+            name: get_server_info_response.name,
+            commit_hash: get_server_info_response.commit_hash,
+            version: get_server_info_response.version,
+        };
+        Ok(server_info)
     }
 
+    /// Claims the actuators `signals` on a new provider stream.
+    ///
+    /// Returns a provider yielding the actuation requests of the databroker as
+    /// [`ActuationCommand`](provider::ActuationCommand)s once the databroker confirmed
+    /// the claim. Each command must be answered with
+    /// [`ActuationCommand::reply`](provider::ActuationCommand::reply). The stream is
+    /// re-opened and the actuators claimed again after the connection has been lost,
+    /// see [`ProviderStream::open`](provider::ProviderStream::open).
+    ///
+    /// Returns (GRPC error code):
+    ///   ALREADY_EXISTS if another provider claimed one of the actuators
+    ///   NOT_FOUND if any of the signals are non-existant.
+    ///   PERMISSION_DENIED if access is denied for any of the signals.
+    ///
     async fn provide_actuation(
-        &mut self,
-        _path: Self::PathType,
+        &self,
+        signals: Self::SignalsType,
     ) -> Result<Self::ProvideResponseType, ClientError> {
-        todo!()
+        let stream = provider::ProviderStream::open(self).await?;
+        let actuators = signals.into_iter().map(SignalId::from).collect();
+        provider::ActuationProvider::start(stream, actuators).await
     }
 }
 
@@ -615,12 +693,12 @@ mod tests {
     use super::*;
     use crate::kuksa::val::v2::tests::TokenType::{Read, ReadWrite};
     use crate::proto::kuksa::val::v2::open_provider_stream_request::Action;
+    use crate::proto::kuksa::val::v2::signal_id::Signal::Path;
     use crate::proto::kuksa::val::v2::value::TypedValue;
     use crate::proto::kuksa::val::v2::ProvideActuationRequest;
     use std::fs;
     use test_tag::tag;
     use tokio::test;
-    use tonic::Code::{InvalidArgument, NotFound, PermissionDenied, Unauthenticated, Unavailable};
 
     impl KuksaClientV2 {
         fn new_test_client(token_type: Option<TokenType>) -> Self {
@@ -630,10 +708,10 @@ mod tests {
                 "http://localhost:55555"
             };
 
-            let mut client = Self::new(Uri::from_static(host));
+            let client = Self::new(Uri::from_static(host));
 
-            if token_type.is_some() {
-                let jwt = read_jwt(token_type.unwrap());
+            if let Some(token_type) = token_type {
+                let jwt = read_jwt(token_type);
                 client
                     .basic_client
                     .set_access_token(jwt)
@@ -647,27 +725,40 @@ mod tests {
     #[tag(integration, insecure)]
     #[test]
     async fn test_get_value() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let response = client.get_value("Vehicle.Speed".to_string()).await;
         assert!(response.is_ok());
     }
 
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_get_value_by_id() {
+        let client = KuksaClientV2::new_test_client(Some(Read));
+
+        let ids = client
+            .resolve_ids_for_paths(vec!["Vehicle.Speed".to_string()])
+            .await
+            .unwrap();
+        let response = client.get_value(ids["Vehicle.Speed"]).await;
+        assert!(response.is_ok());
+    }
+
     #[tag(integration, insecure)]
     #[test]
     async fn test_get_value_with_empty_path_will_return_not_found() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let response = client.get_value("".to_string()).await;
 
         let err = response.unwrap_err();
-        expect_status_code(err, NotFound);
+        assert!(matches!(err, ClientError::NotFound(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_get_value_with_invalid_path_will_return_not_found() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let response = client
             .get_value("Vehicle.Some.Invalid.Path".to_string())
@@ -675,43 +766,40 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, NotFound);
+        assert!(matches!(err, ClientError::NotFound(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_get_value_with_long_path_will_return_invalid_argument() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let long_path = "Vehicle.".repeat(200) + "Speed";
         let response = client.get_value(long_path).await;
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, InvalidArgument);
+        assert!(matches!(err, ClientError::InvalidArgument(_)), "{err:?}");
     }
 
     #[tag(integration, insecure, authentication)]
     #[test]
     async fn test_get_value_without_auth_token_will_return_unauthenticated() {
-        let mut client = KuksaClientV2::new_test_client(None);
+        let client = KuksaClientV2::new_test_client(None);
 
         let response = client.get_value("Vehicle.Speed".to_string()).await;
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, Unauthenticated);
+        assert!(matches!(err, ClientError::Unauthenticated(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_get_values_will_return_ok() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
-        let signal_paths = vec![
-            "Vehicle.Speed".to_string(),
-            "Vehicle.AverageSpeed".to_string(),
-        ];
+        let signal_paths = vec!["Vehicle.Speed".into(), "Vehicle.AverageSpeed".into()];
         let response = client.get_values(signal_paths).await;
         assert!(response.is_ok());
     }
@@ -719,52 +807,46 @@ mod tests {
     #[tag(integration, insecure)]
     #[test]
     async fn test_get_values_with_empty_path_will_return_not_found() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
-        let signal_paths = vec!["Vehicle.Speed".to_string(), "".to_string()];
+        let signal_paths = vec!["Vehicle.Speed".into(), "".into()];
         let response = client.get_values(signal_paths).await;
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, NotFound);
+        assert!(matches!(err, ClientError::NotFound(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_get_values_with_invalid_path_will_return_not_found() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
-        let signal_paths = vec![
-            "Vehicle.Speed".to_string(),
-            "Vehicle.Some.Invalid.Path".to_string(),
-        ];
+        let signal_paths = vec!["Vehicle.Speed".into(), "Vehicle.Some.Invalid.Path".into()];
         let response = client.get_values(signal_paths).await;
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, NotFound);
+        assert!(matches!(err, ClientError::NotFound(_)), "{err:?}");
     }
 
     #[tag(integration, insecure, authentication)]
     #[test]
     async fn test_get_values_without_auth_token_will_return_unauthenticated() {
-        let mut client = KuksaClientV2::new_test_client(None);
+        let client = KuksaClientV2::new_test_client(None);
 
-        let signal_paths = vec![
-            "Vehicle.Speed".to_string(),
-            "Vehicle.AverageSpeed".to_string(),
-        ];
+        let signal_paths = vec!["Vehicle.Speed".into(), "Vehicle.AverageSpeed".into()];
         let response = client.get_values(signal_paths).await;
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, Unauthenticated);
+        assert!(matches!(err, ClientError::Unauthenticated(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_publish_value_will_return_ok() {
-        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+        let client = KuksaClientV2::new_test_client(Some(ReadWrite));
 
         let signal_path = "Vehicle.Speed".to_string();
         let value = Value {
@@ -785,7 +867,7 @@ mod tests {
     #[tag(integration, insecure)]
     #[test]
     async fn test_publish_value_with_invalid_data_type_will_return_invalid_argument() {
-        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+        let client = KuksaClientV2::new_test_client(Some(ReadWrite));
 
         let signal_path = "Vehicle.Speed".to_string();
         let value = Value {
@@ -796,13 +878,13 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, InvalidArgument);
+        assert!(matches!(err, ClientError::InvalidArgument(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_publish_value_with_invalid_value_will_return_invalid_argument() {
-        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+        let client = KuksaClientV2::new_test_client(Some(ReadWrite));
 
         let signal_path = "Vehicle.Powertrain.Type".to_string();
         let value = Value {
@@ -813,13 +895,13 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, InvalidArgument);
+        assert!(matches!(err, ClientError::InvalidArgument(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_publish_value_with_invalid_min_max_value_will_return_invalid_argument() {
-        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+        let client = KuksaClientV2::new_test_client(Some(ReadWrite));
 
         let signal_path = "Vehicle.ADAS.PowerOptimizeLevel".to_string();
         let value = Value {
@@ -830,13 +912,13 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, InvalidArgument);
+        assert!(matches!(err, ClientError::InvalidArgument(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_publish_value_with_empty_path_will_return_not_found() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let signal_path = "".to_string();
         let value = Value {
@@ -847,13 +929,13 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, NotFound);
+        assert!(matches!(err, ClientError::NotFound(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_publish_value_with_invalid_path_will_return_not_found() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let signal_path = "Vehicle.Some.Invalid.Path".to_string();
         let value = Value {
@@ -863,13 +945,13 @@ mod tests {
         let response = client.publish_value(signal_path, value).await;
 
         let err = response.unwrap_err();
-        expect_status_code(err, NotFound);
+        assert!(matches!(err, ClientError::NotFound(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_publish_value_to_an_actuator_will_return_ok() {
-        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+        let client = KuksaClientV2::new_test_client(Some(ReadWrite));
 
         let signal_path = "Vehicle.ADAS.ABS.IsEnabled".to_string(); // is an actuator
         let value = Value {
@@ -883,7 +965,7 @@ mod tests {
     #[tag(integration, insecure, authentication)]
     #[test]
     async fn test_publish_value_without_auth_token_will_return_unauthenticated() {
-        let mut client = KuksaClientV2::new_test_client(None);
+        let client = KuksaClientV2::new_test_client(None);
 
         let signal_path = "Vehicle.Driver.HeartRate".to_string();
         let value = Value {
@@ -894,13 +976,13 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, Unauthenticated);
+        assert!(matches!(err, ClientError::Unauthenticated(_)), "{err:?}");
     }
 
     #[tag(integration, insecure, authentication)]
     #[test]
     async fn test_publish_value_with_read_auth_token_will_return_permission_denied() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let signal_path = "Vehicle.Driver.HeartRate".to_string();
         let value = Value {
@@ -911,13 +993,59 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, PermissionDenied);
+        assert!(matches!(err, ClientError::PermissionDenied(_)), "{err:?}");
+    }
+
+    #[test]
+    async fn test_target_values_are_unsupported() {
+        let mut client = KuksaClientV2::new(Uri::from_static("http://localhost:1"));
+        let paths = vec!["Vehicle.ADAS.ABS.IsEnabled".to_owned()];
+
+        let response = common::ClientTraitV1::get_target_values(&mut client, paths.clone()).await;
+        assert!(
+            matches!(
+                response,
+                Err(ClientError::Unsupported {
+                    operation: "get_target_values",
+                    protocol: "kuksa.val.v2"
+                })
+            ),
+            "{response:?}"
+        );
+        let response = common::ClientTraitV1::subscribe_target_values(&mut client, paths).await;
+        assert!(matches!(response, Err(ClientError::Unsupported { .. })));
+    }
+
+    #[test]
+    async fn test_publish_value_with_read_scope_and_precheck_will_return_permission_denied() {
+        // Not connectable, the pre-check has to fail before connecting
+        let client = KuksaClientV2::new(Uri::from_static("http://localhost:1"));
+        client
+            .basic_client
+            .set_access_token("eyJhbGciOiJub25lIn0.eyJzY29wZSI6InJlYWQifQ.signature")
+            .unwrap();
+        client.basic_client.set_scope_precheck(true);
+
+        let value = Value {
+            typed_value: Some(TypedValue::Uint32(80)),
+        };
+        let response = client
+            .publish_value("Vehicle.Driver.HeartRate".to_string(), value)
+            .await;
+
+        match response {
+            Err(ClientError::PermissionDenied(err)) => {
+                assert_eq!(err.operation, "publish_value");
+                assert_eq!(err.signals, vec!["Vehicle.Driver.HeartRate".to_owned()]);
+            }
+            other => panic!("Expected PermissionDenied, got {other:?}"),
+        }
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_actuate() {
-        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+        let client = KuksaClientV2::new_test_client(Some(ReadWrite));
 
         let signal_path = "Vehicle.ADAS.ABS.IsEnabled".to_string(); // is an actuator
 
@@ -945,7 +1073,7 @@ mod tests {
     #[tag(integration, insecure)]
     #[test]
     async fn test_actuate_with_no_actuation_provider_will_return_unavailable() {
-        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+        let client = KuksaClientV2::new_test_client(Some(ReadWrite));
 
         let signal_path = "Vehicle.ADAS.CruiseControl.IsActive".to_string(); // is an actuator
         let value = Value {
@@ -956,13 +1084,13 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, Unavailable);
+        assert!(matches!(err, ClientError::Unavailable(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_actuate_a_sensor_will_return_invalid_argument() {
-        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+        let client = KuksaClientV2::new_test_client(Some(ReadWrite));
 
         let signal_path = "Vehicle.Speed".to_string();
         let value = Value {
@@ -973,13 +1101,13 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, InvalidArgument);
+        assert!(matches!(err, ClientError::InvalidArgument(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_actuate_with_invalid_signal_path_will_return_not_found() {
-        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+        let client = KuksaClientV2::new_test_client(Some(ReadWrite));
 
         let signal_path = "Vehicle.Some.Invalid.Path".to_string();
         let value = Value {
@@ -990,13 +1118,13 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, NotFound);
+        assert!(matches!(err, ClientError::NotFound(_)), "{err:?}");
     }
 
     #[tag(integration, insecure, authentication)]
     #[test]
     async fn test_actuate_without_auth_token_will_return_unauthenticated() {
-        let mut client = KuksaClientV2::new_test_client(None);
+        let client = KuksaClientV2::new_test_client(None);
 
         let signal_path = "Vehicle.ADAS.ESC.IsEnabled".to_string(); // is an actuator
 
@@ -1008,13 +1136,13 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, Unauthenticated);
+        assert!(matches!(err, ClientError::Unauthenticated(_)), "{err:?}");
     }
 
     #[tag(integration, insecure, authentication)]
     #[test]
     async fn test_actuate_with_read_auth_token_will_return_permission_denied() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let signal_path = "Vehicle.ADAS.ESC.IsEnabled".to_string(); // is an actuator
 
@@ -1026,26 +1154,26 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, PermissionDenied);
+        assert!(matches!(err, ClientError::PermissionDenied(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_batch_actuate() {
-        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+        let client = KuksaClientV2::new_test_client(Some(ReadWrite));
 
         let eba_is_enabled = "Vehicle.ADAS.EBA.IsEnabled".to_string();
         let ebd_is_enabled = "Vehicle.ADAS.EBD.IsEnabled".to_string();
 
         let mut values = HashMap::new();
         values.insert(
-            ebd_is_enabled.to_string(),
+            ebd_is_enabled.as_str().into(),
             Value {
                 typed_value: Some(TypedValue::Bool(true)),
             },
         );
         values.insert(
-            eba_is_enabled.to_string(),
+            eba_is_enabled.as_str().into(),
             Value {
                 typed_value: Some(TypedValue::Bool(false)),
             },
@@ -1076,11 +1204,11 @@ mod tests {
     #[tag(integration, insecure)]
     #[test]
     async fn test_batch_actuate_with_empty_path_will_return_not_found() {
-        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+        let client = KuksaClientV2::new_test_client(Some(ReadWrite));
 
         let mut values = HashMap::new();
         values.insert(
-            "".to_string(),
+            "".into(),
             Value {
                 typed_value: Some(TypedValue::Bool(true)),
             },
@@ -1090,17 +1218,17 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, NotFound);
+        assert!(matches!(err, ClientError::NotFound(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_batch_actuate_with_invalid_signal_will_return_not_found() {
-        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+        let client = KuksaClientV2::new_test_client(Some(ReadWrite));
 
         let mut values = HashMap::new();
         values.insert(
-            "Vehicle.Some.Invalid.Path".to_string(),
+            "Vehicle.Some.Invalid.Path".into(),
             Value {
                 typed_value: Some(TypedValue::Bool(true)),
             },
@@ -1110,26 +1238,26 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, NotFound);
+        assert!(matches!(err, ClientError::NotFound(_)), "{err:?}");
     }
 
     #[tag(integration, insecure, authentication)]
     #[test]
     async fn test_batch_actuate_without_auth_token_will_return_unauthenticated() {
-        let mut client = KuksaClientV2::new_test_client(None);
+        let client = KuksaClientV2::new_test_client(None);
 
         let eba_is_enabled = "Vehicle.ADAS.EBA.IsEnabled".to_string();
         let ebd_is_enabled = "Vehicle.ADAS.EBD.IsEnabled".to_string();
 
         let mut values = HashMap::new();
         values.insert(
-            ebd_is_enabled.to_string(),
+            ebd_is_enabled.as_str().into(),
             Value {
                 typed_value: Some(TypedValue::Bool(true)),
             },
         );
         values.insert(
-            eba_is_enabled.to_string(),
+            eba_is_enabled.as_str().into(),
             Value {
                 typed_value: Some(TypedValue::Bool(false)),
             },
@@ -1139,26 +1267,26 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, Unauthenticated);
+        assert!(matches!(err, ClientError::Unauthenticated(_)), "{err:?}");
     }
 
     #[tag(integration, insecure, authentication)]
     #[test]
     async fn test_batch_actuate_with_read_auth_token_will_return_permission_denied() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let eba_is_enabled = "Vehicle.ADAS.EBA.IsEnabled".to_string();
         let ebd_is_enabled = "Vehicle.ADAS.EBD.IsEnabled".to_string();
 
         let mut values = HashMap::new();
         values.insert(
-            ebd_is_enabled.to_string(),
+            ebd_is_enabled.as_str().into(),
             Value {
                 typed_value: Some(TypedValue::Bool(true)),
             },
         );
         values.insert(
-            eba_is_enabled.to_string(),
+            eba_is_enabled.as_str().into(),
             Value {
                 typed_value: Some(TypedValue::Bool(false)),
             },
@@ -1168,13 +1296,13 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, PermissionDenied);
+        assert!(matches!(err, ClientError::PermissionDenied(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_subscribe_sends_out_an_initial_update() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let mut stream = client
             .subscribe(
@@ -1182,8 +1310,7 @@ mod tests {
                     "Vehicle.AverageSpeed".to_string(),
                     "Vehicle.Body.Raindetection.Intensity".to_string(),
                 ],
-                None,
-                None,
+                SubscribeOptions::default(),
             )
             .await
             .unwrap();
@@ -1196,10 +1323,48 @@ mod tests {
         assert_eq!(subscribe_response.entries.len(), 2);
     }
 
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_subscribe_resilient_sends_out_an_initial_update() {
+        use crate::kuksa::val::v2::subscription::SubscriptionEvent;
+        use tokio_stream::StreamExt;
+
+        let client = KuksaClientV2::new_test_client(Some(Read));
+
+        let mut stream = client
+            .subscribe_resilient(
+                vec!["Vehicle.AverageSpeed".to_string()],
+                SubscribeOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        match stream.next().await {
+            Some(Ok(SubscriptionEvent::Update(response))) => assert_eq!(response.entries.len(), 1),
+            _ => panic!("Expected initial update"),
+        }
+    }
+
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_subscribe_resilient_with_invalid_path_will_return_not_found() {
+        let client = KuksaClientV2::new_test_client(Some(Read));
+
+        let response = client
+            .subscribe_resilient(
+                vec!["Vehicle.Some.Invalid.Path".to_string()],
+                SubscribeOptions::default(),
+            )
+            .await;
+
+        let err = response.err().unwrap();
+        assert!(matches!(err, ClientError::NotFound(_)), "{err:?}");
+    }
+
     #[tag(integration, insecure)]
     #[test]
     async fn test_subscribe() {
-        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+        let client = KuksaClientV2::new_test_client(Some(ReadWrite));
 
         let mut stream = client
             .subscribe(
@@ -1207,8 +1372,7 @@ mod tests {
                     "Vehicle.AverageSpeed".to_string(),
                     "Vehicle.Body.Raindetection.Intensity".to_string(),
                 ],
-                None,
-                None,
+                SubscribeOptions::default(),
             )
             .await
             .unwrap();
@@ -1242,50 +1406,88 @@ mod tests {
         assert_eq!(typed_value, TypedValue::Float(100.0));
     }
 
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_subscribe_updates_decodes_values() {
+        use tokio_stream::StreamExt;
+
+        let client = KuksaClientV2::new_test_client(Some(ReadWrite));
+        client
+            .publish_value(
+                "Vehicle.Speed",
+                Value {
+                    typed_value: Some(TypedValue::Float(30.0)),
+                },
+            )
+            .await
+            .unwrap();
+
+        let mut updates = client
+            .subscribe_updates(
+                vec!["Vehicle.Speed".to_string()],
+                SubscribeOptions::default(),
+            )
+            .await
+            .unwrap()
+            .typed::<f32>();
+
+        let update = updates.next().await.unwrap().unwrap();
+        assert_eq!(update.path, "Vehicle.Speed");
+        assert_eq!(update.value, Some(30.0));
+    }
+
     #[tag(integration, insecure)]
     #[test]
     async fn test_subscribe_to_empty_path_will_return_not_found() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
-        let response = client.subscribe(vec!["".to_string()], None, None).await;
+        let response = client
+            .subscribe(vec!["".to_string()], SubscribeOptions::default())
+            .await;
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, NotFound);
+        assert!(matches!(err, ClientError::NotFound(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_subscribe_to_invalid_path_will_return_not_found() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let response = client
-            .subscribe(vec!["Vehicle.Some.Invalid.Path".to_string()], None, None)
+            .subscribe(
+                vec!["Vehicle.Some.Invalid.Path".to_string()],
+                SubscribeOptions::default(),
+            )
             .await;
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, NotFound);
+        assert!(matches!(err, ClientError::NotFound(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_subscribe_with_invalid_buffer_size_will_return_invalid_argument() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let response = client
-            .subscribe(vec!["Vehicle.AverageSpeed".to_string()], Some(2048), None)
+            .subscribe(
+                vec!["Vehicle.AverageSpeed".to_string()],
+                SubscribeOptions::new().with_buffer_size(2048),
+            )
             .await;
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, InvalidArgument);
+        assert!(matches!(err, ClientError::InvalidArgument(_)), "{err:?}");
     }
 
     #[tag(integration, insecure, authentication)]
     #[test]
     async fn test_subscribe_without_auth_token_will_return_unauthenticated() {
-        let mut client = KuksaClientV2::new_test_client(None);
+        let client = KuksaClientV2::new_test_client(None);
 
         let response = client
             .subscribe(
@@ -1293,20 +1495,19 @@ mod tests {
                     "Vehicle.AverageSpeed".to_string(),
                     "Vehicle.Body.Raindetection.Intensity".to_string(),
                 ],
-                None,
-                None,
+                SubscribeOptions::default(),
             )
             .await;
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, Unauthenticated);
+        assert!(matches!(err, ClientError::Unauthenticated(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_subscribe_by_id() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let vss_paths = vec![
             "Vehicle.Speed".to_string(),
@@ -1315,27 +1516,52 @@ mod tests {
         let path_id_map = client.resolve_ids_for_paths(vss_paths).await.unwrap();
 
         let signal_ids: Vec<i32> = path_id_map.values().copied().collect();
-        let response = client.subscribe_by_id(signal_ids, None, None).await;
+        let response = client
+            .subscribe_by_id(signal_ids, SubscribeOptions::default())
+            .await;
         assert!(response.is_ok());
     }
 
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_subscribe_resolved() {
+        use crate::proto::kuksa::val::v2::DataType;
+        use tokio_stream::StreamExt;
+
+        let client = KuksaClientV2::new_test_client(Some(Read));
+
+        let vss_paths = vec!["Vehicle.Speed".to_string()];
+        let mut stream = client
+            .subscribe_resolved(vss_paths, SubscribeOptions::default())
+            .await
+            .unwrap();
+
+        let values = stream.next().await.unwrap().unwrap();
+        let speed = values.get("Vehicle.Speed").unwrap();
+        assert_eq!(speed.data_type, DataType::Float);
+        assert_eq!(speed.unit.as_deref(), Some("km/h"));
+        assert_eq!(values.get_by_id(speed.id), Some(speed));
+    }
+
     #[tag(integration, insecure)]
     #[test]
     async fn test_subscribe_by_id_with_invalid_id_will_return_not_found() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let signal_ids = vec![i32::MAX];
-        let response = client.subscribe_by_id(signal_ids, None, None).await;
+        let response = client
+            .subscribe_by_id(signal_ids, SubscribeOptions::default())
+            .await;
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, NotFound);
+        assert!(matches!(err, ClientError::NotFound(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_subscribe_by_id_with_invalid_buffer_size_will_return_invalid_argument() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let vss_paths = vec![
             "Vehicle.Speed".to_string(),
@@ -1344,30 +1570,33 @@ mod tests {
         let path_id_map = client.resolve_ids_for_paths(vss_paths).await.unwrap();
 
         let signal_ids: Vec<i32> = path_id_map.values().copied().collect();
-        let response = client.subscribe_by_id(signal_ids, Some(2048), None).await;
+        let options = SubscribeOptions::new().with_buffer_size(2048);
+        let response = client.subscribe_by_id(signal_ids, options).await;
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, InvalidArgument);
+        assert!(matches!(err, ClientError::InvalidArgument(_)), "{err:?}");
     }
 
     #[tag(integration, insecure, authentication)]
     #[test]
     async fn test_subscribe_by_id_without_auth_token_return_unauthenticated() {
-        let mut client = KuksaClientV2::new_test_client(None);
+        let client = KuksaClientV2::new_test_client(None);
 
         let signal_ids = vec![0, 1, 2, 3, 4, 5];
-        let response = client.subscribe_by_id(signal_ids, Some(2048), None).await;
+        let response = client
+            .subscribe_by_id(signal_ids, SubscribeOptions::default())
+            .await;
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, Unauthenticated);
+        assert!(matches!(err, ClientError::Unauthenticated(_)), "{err:?}");
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_list_metadata() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let response = client
             .list_metadata(("Vehicle".to_string(), "*".to_string()))
@@ -1381,7 +1610,7 @@ mod tests {
     #[tag(integration, insecure)]
     #[test]
     async fn test_list_metadata_with_invalid_root_will_return_not_found() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let response = client
             .list_metadata(("InvalidRoot".to_string(), "*".to_string()))
@@ -1389,13 +1618,13 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, NotFound);
+        assert!(matches!(err, ClientError::NotFound(_)), "{err:?}");
     }
 
     #[tag(integration, insecure, authentication)]
     #[test]
     async fn test_lists_metadata_without_auth_token_will_return_unauthenticated() {
-        let mut client = KuksaClientV2::new_test_client(None);
+        let client = KuksaClientV2::new_test_client(None);
 
         let response = client
             .list_metadata(("Vehicle".to_string(), "*".to_string()))
@@ -1403,13 +1632,85 @@ mod tests {
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, Unauthenticated);
+        assert!(matches!(err, ClientError::Unauthenticated(_)), "{err:?}");
+    }
+
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_resolve_metadata_is_cached() {
+        let client = KuksaClientV2::new_test_client(Some(Read));
+
+        let paths = vec![
+            "Vehicle.Speed".to_string(),
+            "Vehicle.AverageSpeed".to_string(),
+            "Vehicle.Cabin.Door.*.*.IsOpen".to_string(),
+        ];
+        let metadata = client.resolve_metadata(paths).await.unwrap();
+        assert!(metadata.contains_key("Vehicle.Cabin.Door.Row1.DriverSide.IsOpen"));
+
+        let speed = &metadata["Vehicle.Speed"];
+        let registry = client.metadata_registry();
+        assert_eq!(registry.get_by_id(speed.id).as_ref(), Some(speed));
+    }
+
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_resolve_metadata_with_invalid_paths_will_return_not_found() {
+        let client = KuksaClientV2::new_test_client(Some(Read));
+
+        let paths = vec![
+            "Vehicle.Speed".to_string(),
+            "Vehicle.Some.Invalid.Path".to_string(),
+            "Vehicle.Other.Invalid.Path".to_string(),
+        ];
+        let err = client.resolve_metadata(paths).await.unwrap_err();
+
+        let ClientError::NotFound(request_error) = err else {
+            panic!("{err:?}");
+        };
+        assert_eq!(request_error.signals.len(), 2);
+    }
+
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_signal_handle_publishes_and_gets() {
+        let client = KuksaClientV2::new_test_client(Some(ReadWrite));
+
+        let speed = client.signal::<f32>("Vehicle.Speed").await.unwrap();
+        speed.publish(42.0).await.unwrap();
+        assert_eq!(speed.get().await.unwrap(), Some(42.0));
+    }
+
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_signal_handle_with_wrong_type_will_return_invalid_argument() {
+        let client = KuksaClientV2::new_test_client(Some(Read));
+
+        let err = client.signal::<bool>("Vehicle.Speed").await.unwrap_err();
+        assert!(matches!(err, ClientError::InvalidArgument(_)), "{err:?}");
+    }
+
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_cloned_clients_share_one_connection() {
+        let client = KuksaClientV2::new_test_client(Some(Read));
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move { client.get_value("Vehicle.Speed".to_string()).await })
+            })
+            .collect();
+        for task in tasks {
+            assert!(task.await.unwrap().is_ok());
+        }
+        assert!(client.basic_client.is_connected());
     }
 
     #[tag(integration, insecure)]
     #[test]
     async fn test_get_server_info() {
-        let mut client = KuksaClientV2::new_test_client(Some(Read));
+        let client = KuksaClientV2::new_test_client(Some(Read));
 
         let response = client.get_server_info().await;
         assert!(response.is_ok());
@@ -1423,22 +1724,13 @@ mod tests {
     #[tag(integration, insecure, authentication)]
     #[test]
     async fn test_get_server_info_without_auth_token_will_return_unauthenticated() {
-        let mut client = KuksaClientV2::new_test_client(None);
+        let client = KuksaClientV2::new_test_client(None);
 
         let response = client.get_server_info().await;
         assert!(response.is_err());
 
         let err = response.unwrap_err();
-        expect_status_code(err, Unauthenticated);
-    }
-
-    fn expect_status_code(err: ClientError, code: tonic::Code) {
-        match err {
-            ClientError::Status(status) => {
-                assert_eq!(status.code(), code);
-            }
-            _ => panic!("unexpected error"),
-        }
+        assert!(matches!(err, ClientError::Unauthenticated(_)), "{err:?}");
     }
 
     fn read_jwt(token_type: TokenType) -> String {
@@ -0,0 +1,167 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+//! Options of kuksa.val.v2 subscriptions.
+
+use crate::proto::kuksa::val::v2::{Filter, SampleInterval};
+use std::time::Duration;
+
+/// Maximum number of buffered updates of a subscription accepted by the databroker.
+pub const MAX_BUFFER_SIZE: u32 = 1000;
+/// Maximum length of a signal path accepted by the databroker.
+pub const MAX_REQUEST_PATH_LENGTH: usize = 1000;
+
+/// Options of a kuksa.val.v2 subscription, accepted by
+/// [`ClientTraitV2::subscribe`](super::ClientTraitV2::subscribe) and
+/// [`ClientTraitV2::subscribe_by_id`](super::ClientTraitV2::subscribe_by_id).
+///
+/// The limits of the databroker are checked before subscribing, violating them fails
+/// with [`ClientError::InvalidArgument`](super::ClientError::InvalidArgument).
+///
+/// ```no_run
+/// # use kuksa_rust_sdk::kuksa::val::v2::SubscribeOptions;
+/// # use kuksa_rust_sdk::kuksa::common::ClientTraitV2;
+/// # use kuksa_rust_sdk::kuksa::val::v2::KuksaClientV2;
+/// # use std::time::Duration;
+/// # async fn example(client: KuksaClientV2) {
+/// let options = SubscribeOptions::new()
+///     .with_buffer_size(10)
+///     .with_min_sample_interval(Duration::from_millis(100));
+/// let stream = client
+///     .subscribe(vec!["Vehicle.Speed".to_owned()], options)
+///     .await;
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubscribeOptions {
+    buffer_size: u32,
+    filter: Option<Filter>,
+}
+
+impl SubscribeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of updates buffered by the databroker for a slow subscriber before
+    /// the oldest ones are dropped, at most [`MAX_BUFFER_SIZE`]. 0 (the default)
+    /// leaves it to the databroker.
+    pub fn with_buffer_size(mut self, buffer_size: u32) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    /// Minimal interval between two updates, with millisecond precision.
+    pub fn with_min_sample_interval(mut self, interval: Duration) -> Self {
+        let interval_ms = u32::try_from(interval.as_millis()).unwrap_or(u32::MAX);
+        self.filter
+            .get_or_insert_with(Filter::default)
+            .min_sample_interval = Some(SampleInterval { interval_ms });
+        self
+    }
+
+    /// `duration_ms` of the filter, with millisecond precision.
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        let duration_ms = u32::try_from(duration.as_millis()).unwrap_or(u32::MAX);
+        self.filter.get_or_insert_with(Filter::default).duration_ms = duration_ms;
+        self
+    }
+
+    /// Replaces the whole filter, e.g. to set fields not covered by the other setters.
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn buffer_size(&self) -> u32 {
+        self.buffer_size
+    }
+
+    pub fn filter(&self) -> Option<&Filter> {
+        self.filter.as_ref()
+    }
+
+    /// Checks the limits of the databroker for a subscription to `signal_paths`.
+    pub(crate) fn validate_paths(&self, signal_paths: &[String]) -> Result<(), String> {
+        if signal_paths.is_empty() {
+            return Err("No signals to subscribe to".to_owned());
+        }
+        if let Some(path) = signal_paths
+            .iter()
+            .find(|path| path.len() > MAX_REQUEST_PATH_LENGTH)
+        {
+            return Err(format!(
+                "Path of {} characters exceeds the maximum of {MAX_REQUEST_PATH_LENGTH}",
+                path.len()
+            ));
+        }
+        self.validate_buffer_size()
+    }
+
+    /// Checks the limits of the databroker for a subscription to `signal_ids`.
+    pub(crate) fn validate_ids(&self, signal_ids: &[i32]) -> Result<(), String> {
+        if signal_ids.is_empty() {
+            return Err("No signals to subscribe to".to_owned());
+        }
+        self.validate_buffer_size()
+    }
+
+    fn validate_buffer_size(&self) -> Result<(), String> {
+        if self.buffer_size > MAX_BUFFER_SIZE {
+            return Err(format!(
+                "Buffer size {} exceeds the maximum of {MAX_BUFFER_SIZE}",
+                self.buffer_size
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(vec!["Vehicle.Speed".to_owned()], 1000 => Ok(()); "valid")]
+    #[test_case(vec![], 0 => Err("No signals to subscribe to".to_owned()); "no signals")]
+    #[test_case(vec!["Vehicle".repeat(200)], 0 => Err("Path of 1400 characters exceeds the maximum of 1000".to_owned()); "path too long")]
+    #[test_case(vec!["Vehicle.Speed".to_owned()], 2048 => Err("Buffer size 2048 exceeds the maximum of 1000".to_owned()); "buffer too large")]
+    fn test_subscribe_options_are_validated(
+        signal_paths: Vec<String>,
+        buffer_size: u32,
+    ) -> Result<(), String> {
+        let options = SubscribeOptions::new().with_buffer_size(buffer_size);
+        options.validate_paths(&signal_paths)
+    }
+
+    #[test]
+    fn test_subscribe_options_build_filter() {
+        let options = SubscribeOptions::new()
+            .with_min_sample_interval(Duration::from_millis(100))
+            .with_duration(Duration::from_secs(2));
+        assert_eq!(
+            options.filter(),
+            Some(&Filter {
+                duration_ms: 2000,
+                min_sample_interval: Some(SampleInterval { interval_ms: 100 }),
+            })
+        );
+        assert_eq!(SubscribeOptions::new().filter(), None);
+        assert_eq!(
+            SubscribeOptions::new().validate_ids(&[]),
+            Err("No signals to subscribe to".to_owned())
+        );
+    }
+}
@@ -0,0 +1,1321 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+//! Providers built on top of [`ClientTraitV2::open_provider_stream`](super::ClientTraitV2::open_provider_stream).
+//!
+//! A [`ProviderStream`] reads the responses of the databroker in a background task
+//! and dispatches them to the requests waiting for them, so several requests and
+//! actuations can be in flight on the same stream.
+//!
+//! Opened with [`ProviderStream::open`], the stream is re-opened after the connection
+//! to the databroker has been lost (on the endpoint the client fails over to, if
+//! failover endpoints are configured) and the actuators and signals claimed before
+//! are claimed again.
+//!
+//! ```no_run
+//! # use kuksa_rust_sdk::kuksa::common::ClientTraitV2;
+//! # use kuksa_rust_sdk::kuksa::val::v2::provider::ProviderStream;
+//! # use kuksa_rust_sdk::kuksa::val::v2::KuksaClientV2;
+//! # use kuksa_rust_sdk::v2_proto::Datapoint;
+//! # use std::collections::HashMap;
+//! # async fn example(client: KuksaClientV2, speed_id: i32, speed: Datapoint) {
+//! let stream = ProviderStream::open(&client).await.unwrap();
+//! let errors = stream
+//!     .publish_values(HashMap::from([(speed_id, speed)]))
+//!     .await
+//!     .unwrap();
+//! for (signal_id, error) in errors {
+//!     println!("Publishing {signal_id} failed: {}", error.message);
+//! }
+//! # }
+//! ```
+
+use super::subscription::{is_interruption, reestablish};
+use super::KuksaClientV2;
+use crate::kuksa::common::options::CancellationToken;
+use crate::kuksa::common::reconnect::ReconnectPolicy;
+use crate::kuksa::common::types::OpenProviderStream;
+use crate::kuksa::common::{ClientError, ClientTraitV2};
+use crate::proto::kuksa::val::v2::{
+    open_provider_stream_request, open_provider_stream_response, BatchActuateStreamResponse,
+    Datapoint, Error, ErrorCode, Filter, GetProviderValueResponse, OpenProviderStreamRequest,
+    OpenProviderStreamResponse, ProvideActuationRequest, ProvideSignalRequest,
+    PublishValuesRequest, SampleInterval, SignalId, UpdateFilterResponse, Value,
+};
+use futures_core::Stream;
+use log::{debug, warn};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_stream::StreamExt;
+use tonic::async_trait;
+
+type Responses =
+    Pin<Box<dyn Stream<Item = Result<OpenProviderStreamResponse, tonic::Status>> + Send>>;
+
+type Connection = (mpsc::Sender<OpenProviderStreamRequest>, Responses);
+
+/// Buffer of actuation commands not yet taken by the provider. Commands beyond it
+/// are rejected right away, so a slow provider never stalls the other responses.
+const COMMAND_BUFFER: usize = 32;
+
+/// Time to wait for the PublishValuesResponse, unless configured otherwise.
+const DEFAULT_PUBLISH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Errors of a publish per signal id, empty if all values were accepted.
+pub type PublishErrors = HashMap<i32, Error>;
+
+#[derive(Debug)]
+struct Claim<T> {
+    // Remembered once confirmed to claim them again on a re-opened stream, empty for re-claims
+    signals: T,
+    confirmed: Option<oneshot::Sender<Result<(), ClientError>>>,
+}
+
+#[derive(Debug, Default)]
+struct Demux {
+    // None while the stream is re-opened
+    requests: Option<mpsc::Sender<OpenProviderStreamRequest>>,
+    actuation_claims: VecDeque<Claim<Vec<SignalId>>>,
+    claimed_actuators: Vec<SignalId>,
+    actuations: Option<mpsc::Sender<Result<ActuationCommand, ClientError>>>,
+    signal_claims: VecDeque<Claim<HashMap<i32, SampleInterval>>>,
+    claimed_signals: HashMap<i32, SampleInterval>,
+    // Paths of the ids claimed, to check that the ids are the same on a re-opened stream
+    claimed_paths: HashMap<i32, String>,
+    // Unbounded as filter updates must not be lost, the databroker sends them rarely
+    signal_requests: Option<mpsc::UnboundedSender<Result<SignalRequest, ClientError>>>,
+    publishes: HashMap<u64, oneshot::Sender<Result<PublishErrors, ClientError>>>,
+    // Set once the stream is closed for good
+    closed: Option<ClientError>,
+}
+
+/// Re-opens a provider stream which has been interrupted.
+#[async_trait]
+trait Reopen: Send + Sync + 'static {
+    /// Error for the end of the stream, `None` if the databroker closed it.
+    fn error(&self, status: Option<tonic::Status>) -> ClientError {
+        end_error(status)
+    }
+
+    async fn reopen(&self) -> Result<Connection, ClientError>;
+
+    /// Path of the signal with the id `id` on the current connection, if known.
+    fn path_of(&self, id: i32) -> Option<String>;
+
+    /// Ids of the signals at `paths` on the current connection.
+    async fn resolve_ids(&self, paths: Vec<String>) -> Result<HashMap<String, i32>, ClientError>;
+}
+
+#[async_trait]
+impl Reopen for KuksaClientV2 {
+    fn error(&self, status: Option<tonic::Status>) -> ClientError {
+        match status {
+            Some(status) => self.basic_client.handle_status(status),
+            // The databroker closed the stream, e.g. because it is shutting down
+            None => {
+                self.basic_client.disconnect();
+                end_error(None)
+            }
+        }
+    }
+
+    async fn reopen(&self) -> Result<Connection, ClientError> {
+        let stream = reestablish(self, || self.open_provider_stream(None)).await?;
+        Ok((stream.sender, Box::pin(stream.receiver_stream)))
+    }
+
+    fn path_of(&self, id: i32) -> Option<String> {
+        self.metadata_registry().path_of(id)
+    }
+
+    async fn resolve_ids(&self, paths: Vec<String>) -> Result<HashMap<String, i32>, ClientError> {
+        self.resolve_ids_for_paths(paths).await
+    }
+}
+
+fn end_error(status: Option<tonic::Status>) -> ClientError {
+    match status {
+        Some(status) => ClientError::Status(status),
+        None => ClientError::Connection("Provider stream closed by databroker".to_owned()),
+    }
+}
+
+/// Provider stream with the responses of the databroker dispatched in the background.
+///
+/// The stream is closed when it is dropped.
+#[derive(Debug)]
+pub struct ProviderStream {
+    demux: Arc<Mutex<Demux>>,
+    task: JoinHandle<()>,
+    next_request_id: AtomicU64,
+    publish_timeout: Duration,
+}
+
+impl Drop for ProviderStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn lock(demux: &Mutex<Demux>) -> MutexGuard<'_, Demux> {
+    demux.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl ProviderStream {
+    /// Wraps a stream which is closed for good once the connection is lost.
+    pub fn new(stream: OpenProviderStream) -> Self {
+        Self::start(stream.sender, Box::pin(stream.receiver_stream))
+    }
+
+    /// Opens a provider stream which is re-opened after the connection to the
+    /// databroker has been lost, following the reconnect policy of `client`.
+    ///
+    /// The claims confirmed so far are sent again on the re-opened stream, which may
+    /// be connected to another endpoint or a restarted databroker. Signals claimed by
+    /// id are resolved again (their ids have to be resolved with `client`), and if
+    /// their ids differ the stream is closed with a [`ClientError::Connection`], as
+    /// the provider has to resolve and claim them again. Requests pending while the
+    /// stream is interrupted fail, and so do requests made until it has been
+    /// re-opened. The stream is closed for good if the reconnect policy gives up or
+    /// the databroker rejects a claim.
+    pub async fn open(client: &KuksaClientV2) -> Result<Self, ClientError> {
+        let stream = client.open_provider_stream(None).await?;
+        let connection = (stream.sender, Box::pin(stream.receiver_stream) as Responses);
+        Ok(Self::spawn(connection, Some(Box::new(client.clone()))))
+    }
+
+    pub(crate) fn start(
+        sender: mpsc::Sender<OpenProviderStreamRequest>,
+        responses: Responses,
+    ) -> Self {
+        Self::spawn((sender, responses), None)
+    }
+
+    fn spawn(connection: Connection, reopen: Option<Box<dyn Reopen>>) -> Self {
+        let demux = Arc::new(Mutex::new(Demux {
+            requests: Some(connection.0.clone()),
+            ..Demux::default()
+        }));
+        let task = tokio::spawn(run(connection, demux.clone(), reopen));
+        ProviderStream {
+            demux,
+            task,
+            next_request_id: AtomicU64::new(0),
+            publish_timeout: DEFAULT_PUBLISH_TIMEOUT,
+        }
+    }
+
+    /// Time to wait for the databroker to answer a publish, 5 seconds by default.
+    pub fn with_publish_timeout(mut self, timeout: Duration) -> Self {
+        self.publish_timeout = timeout;
+        self
+    }
+
+    /// Sends a request without waiting for a response.
+    pub async fn send(&self, request: OpenProviderStreamRequest) -> Result<(), ClientError> {
+        let sender = {
+            let demux = lock(&self.demux);
+            demux.requests.clone().ok_or_else(|| unavailable(&demux))?
+        };
+        self.send_to(&sender, request).await
+    }
+
+    async fn send_to(
+        &self,
+        sender: &mpsc::Sender<OpenProviderStreamRequest>,
+        request: OpenProviderStreamRequest,
+    ) -> Result<(), ClientError> {
+        sender.send(request).await.map_err(|_| self.closed_error())
+    }
+
+    fn closed_error(&self) -> ClientError {
+        lock(&self.demux)
+            .closed
+            .clone()
+            .unwrap_or_else(|| ClientError::Connection("Provider stream closed".to_owned()))
+    }
+
+    /// Claims the actuators and waits until the databroker confirmed the claim.
+    ///
+    /// The databroker closes the stream if the claim is rejected, e.g. with
+    /// [`ClientError::AlreadyExists`] if another provider owns one of the actuators.
+    pub async fn provide_actuation(&self, actuators: Vec<SignalId>) -> Result<(), ClientError> {
+        let signals: Vec<String> = actuators.iter().map(signal_name).collect();
+        let (confirmed, confirmation) = oneshot::channel();
+        let sender = {
+            let mut demux = lock(&self.demux);
+            // Queued along with taking the sender, a claim sent on a re-opened
+            // stream must not be matched with a confirmation of the re-claims
+            let Some(sender) = demux.requests.clone() else {
+                return Err(unavailable(&demux).with_context("provide_actuation", signals));
+            };
+            demux.actuation_claims.push_back(Claim {
+                signals: actuators.clone(),
+                confirmed: Some(confirmed),
+            });
+            sender
+        };
+        self.send_to(&sender, provide_actuation_request(actuators))
+            .await?;
+        let result = match confirmation.await {
+            Ok(result) => result,
+            Err(_) => Err(self.closed_error()),
+        };
+        result.map_err(|err| err.with_context("provide_actuation", signals))
+    }
+
+    /// Publishes the values of several signals, identified by their id.
+    ///
+    /// Resolves with the errors the databroker reported per signal once it answered
+    /// the request, or fails with [`ClientError::DeadlineExceeded`] if it does not
+    /// answer in time. Publishes can be issued concurrently, the answers are matched
+    /// to them by request id.
+    pub async fn publish_values(
+        &self,
+        data_points: HashMap<i32, Datapoint>,
+    ) -> Result<PublishErrors, ClientError> {
+        let signals: Vec<String> = data_points.keys().map(i32::to_string).collect();
+        let request_id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        let (answered, answer) = oneshot::channel();
+        let sender = {
+            let mut demux = lock(&self.demux);
+            let Some(sender) = demux.requests.clone() else {
+                return Err(unavailable(&demux).with_context("publish_values", signals));
+            };
+            demux.publishes.insert(request_id, answered);
+            sender
+        };
+        let request = OpenProviderStreamRequest {
+            action: Some(open_provider_stream_request::Action::PublishValuesRequest(
+                PublishValuesRequest {
+                    request_id,
+                    data_points,
+                },
+            )),
+        };
+        let result = match self.send_to(&sender, request).await {
+            Ok(()) => match tokio::time::timeout(self.publish_timeout, answer).await {
+                Ok(Ok(result)) => result,
+                Ok(Err(_)) => Err(self.closed_error()),
+                Err(_) => Err(ClientError::DeadlineExceeded(self.publish_timeout)),
+            },
+            Err(err) => Err(err),
+        };
+        if result.is_err() {
+            lock(&self.demux).publishes.remove(&request_id);
+        }
+        result.map_err(|err| err.with_context("publish_values", signals))
+    }
+
+    /// Returns the commands of all actuators claimed on this stream. Commands
+    /// received before are dropped, a later call replaces the receiver.
+    pub fn actuation_commands(&self) -> ActuationCommands {
+        let mut demux = lock(&self.demux);
+        let demux = &mut *demux;
+        let receiver = register(&demux.closed, &mut demux.actuations);
+        ActuationCommands { receiver }
+    }
+
+    /// Claims the signals (sensors and attributes) with the sample interval the
+    /// provider is able to deliver, and waits until the databroker confirmed the claim.
+    ///
+    /// The databroker then tells the provider through [`SignalRequest::UpdateFilter`]
+    /// how often the subscribers need the values.
+    pub async fn provide_signals(
+        &self,
+        sample_intervals: HashMap<i32, SampleInterval>,
+    ) -> Result<(), ClientError> {
+        let signals: Vec<String> = sample_intervals.keys().map(i32::to_string).collect();
+        let (confirmed, confirmation) = oneshot::channel();
+        let sender = {
+            let mut demux = lock(&self.demux);
+            let Some(sender) = demux.requests.clone() else {
+                return Err(unavailable(&demux).with_context("provide_signals", signals));
+            };
+            demux.signal_claims.push_back(Claim {
+                signals: sample_intervals.clone(),
+                confirmed: Some(confirmed),
+            });
+            sender
+        };
+        self.send_to(&sender, provide_signal_request(sample_intervals))
+            .await?;
+        let result = match confirmation.await {
+            Ok(result) => result,
+            Err(_) => Err(self.closed_error()),
+        };
+        result.map_err(|err| err.with_context("provide_signals", signals))
+    }
+
+    /// Returns the requests of the databroker for the signals claimed on this stream.
+    /// Requests received before are dropped, a later call replaces the receiver.
+    pub fn signal_requests(&self) -> SignalRequests {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let mut demux = lock(&self.demux);
+        if let Some(err) = &demux.closed {
+            // Ends immediately after reporting why
+            let _ = sender.send(Err(err.clone()));
+        } else {
+            demux.signal_requests = Some(sender);
+        }
+        SignalRequests { receiver }
+    }
+}
+
+// Reason why no requests can be sent on the stream
+fn unavailable(demux: &Demux) -> ClientError {
+    demux
+        .closed
+        .clone()
+        .unwrap_or_else(|| ClientError::Connection("Provider stream is being re-opened".to_owned()))
+}
+
+fn provide_actuation_request(actuators: Vec<SignalId>) -> OpenProviderStreamRequest {
+    OpenProviderStreamRequest {
+        action: Some(
+            open_provider_stream_request::Action::ProvideActuationRequest(
+                ProvideActuationRequest {
+                    actuator_identifiers: actuators,
+                },
+            ),
+        ),
+    }
+}
+
+fn provide_signal_request(
+    sample_intervals: HashMap<i32, SampleInterval>,
+) -> OpenProviderStreamRequest {
+    OpenProviderStreamRequest {
+        action: Some(open_provider_stream_request::Action::ProvideSignalRequest(
+            ProvideSignalRequest {
+                signals_sample_intervals: sample_intervals,
+            },
+        )),
+    }
+}
+
+fn register<T>(
+    closed: &Option<ClientError>,
+    slot: &mut Option<mpsc::Sender<Result<T, ClientError>>>,
+) -> mpsc::Receiver<Result<T, ClientError>> {
+    let (sender, receiver) = mpsc::channel(COMMAND_BUFFER);
+    if let Some(err) = closed {
+        // Ends immediately after reporting why
+        let _ = sender.try_send(Err(err.clone()));
+    } else {
+        *slot = Some(sender);
+    }
+    receiver
+}
+
+fn signal_name(signal_id: &SignalId) -> String {
+    use crate::proto::kuksa::val::v2::signal_id::Signal;
+    match &signal_id.signal {
+        Some(Signal::Path(path)) => path.clone(),
+        Some(Signal::Id(id)) => id.to_string(),
+        None => String::new(),
+    }
+}
+
+async fn run(
+    mut connection: Connection,
+    demux: Arc<Mutex<Demux>>,
+    reopen: Option<Box<dyn Reopen>>,
+) {
+    let reason = loop {
+        let (requests, mut responses) = connection;
+        let status = loop {
+            match responses.next().await {
+                Some(Ok(response)) => dispatch(&demux, &requests, response, reopen.as_deref()),
+                Some(Err(status)) => break Some(status),
+                None => break None,
+            }
+        };
+        let interruption = match &reopen {
+            Some(reopen) => reopen.error(status),
+            None => end_error(status),
+        };
+        let Some(reopen) = reopen.as_ref().filter(|_| is_interruption(&interruption)) else {
+            break interruption;
+        };
+        warn!("Provider stream interrupted: {interruption}, re-opening it");
+        fail_pending(&demux, &interruption);
+
+        connection = match reopen.reopen().await {
+            Ok(connection) => connection,
+            Err(err) => break err,
+        };
+        if let Err(err) = check_ids(&demux, reopen.as_ref()).await {
+            break err;
+        }
+        reclaim(&demux, &connection.0).await;
+    };
+    debug!("Provider stream ended: {reason}");
+
+    let (actuations, signal_requests) = {
+        let mut demux = lock(&demux);
+        demux.closed = Some(reason.clone());
+        (demux.actuations.take(), demux.signal_requests.take())
+    };
+    fail_pending(&demux, &reason);
+    // Without room for the error the consumers only see the end of the stream
+    if let Some(actuations) = actuations {
+        let _ = actuations.try_send(Err(reason.clone()));
+    }
+    if let Some(signal_requests) = signal_requests {
+        let _ = signal_requests.send(Err(reason));
+    }
+}
+
+// Fails the claims and publishes waiting for an answer of the lost stream.
+fn fail_pending(demux: &Mutex<Demux>, reason: &ClientError) {
+    let (claims, publishes) = {
+        let mut demux = lock(demux);
+        demux.requests = None;
+        let claims: Vec<_> = std::mem::take(&mut demux.actuation_claims)
+            .into_iter()
+            .filter_map(|claim| claim.confirmed)
+            .chain(
+                std::mem::take(&mut demux.signal_claims)
+                    .into_iter()
+                    .filter_map(|claim| claim.confirmed),
+            )
+            .collect();
+        (claims, std::mem::take(&mut demux.publishes))
+    };
+    for claim in claims {
+        let _ = claim.send(Err(reason.clone()));
+    }
+    for publish in publishes.into_values() {
+        let _ = publish.send(Err(reason.clone()));
+    }
+}
+
+// Ids are assigned by the databroker, the claims by id are only valid on the re-opened
+// stream if the signals have the same ids there.
+async fn check_ids(demux: &Mutex<Demux>, reopen: &dyn Reopen) -> Result<(), ClientError> {
+    let (ids, paths) = {
+        let demux = lock(demux);
+        let ids: Vec<i32> = demux
+            .claimed_actuators
+            .iter()
+            .filter_map(signal_id)
+            .chain(demux.claimed_signals.keys().copied())
+            .collect();
+        (ids, demux.claimed_paths.clone())
+    };
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let ids_changed =
+        || ClientError::Connection("Signal ids differ on the re-opened provider stream".to_owned());
+    let Some(claimed): Option<Vec<String>> = ids.iter().map(|id| paths.get(id).cloned()).collect()
+    else {
+        return Err(ids_changed());
+    };
+    let resolved = reopen.resolve_ids(claimed).await?;
+    if ids.iter().all(|id| {
+        resolved
+            .get(&paths[id])
+            .is_some_and(|resolved| resolved == id)
+    }) {
+        Ok(())
+    } else {
+        Err(ids_changed())
+    }
+}
+
+fn signal_id(signal_id: &SignalId) -> Option<i32> {
+    use crate::proto::kuksa::val::v2::signal_id::Signal;
+    match signal_id.signal {
+        Some(Signal::Id(id)) => Some(id),
+        _ => None,
+    }
+}
+
+// Claims the actuators and signals of the lost stream on the re-opened one before
+// requests of the provider are sent on it.
+async fn reclaim(demux: &Mutex<Demux>, requests: &mpsc::Sender<OpenProviderStreamRequest>) {
+    let mut claims = Vec::new();
+    {
+        let mut demux = lock(demux);
+        if !demux.claimed_actuators.is_empty() {
+            claims.push(provide_actuation_request(demux.claimed_actuators.clone()));
+            demux.actuation_claims.push_back(Claim {
+                signals: Vec::new(),
+                confirmed: None,
+            });
+        }
+        if !demux.claimed_signals.is_empty() {
+            claims.push(provide_signal_request(demux.claimed_signals.clone()));
+            demux.signal_claims.push_back(Claim {
+                signals: HashMap::new(),
+                confirmed: None,
+            });
+        }
+    }
+    for claim in claims {
+        // A failure is reported by the responses of the re-opened stream
+        if requests.send(claim).await.is_err() {
+            break;
+        }
+    }
+    lock(demux).requests = Some(requests.clone());
+}
+
+// Never waits for the consumers, otherwise a provider not polling its commands would
+// keep the answers to its publishes from being dispatched.
+fn dispatch(
+    demux: &Mutex<Demux>,
+    requests: &mpsc::Sender<OpenProviderStreamRequest>,
+    response: OpenProviderStreamResponse,
+    reopen: Option<&dyn Reopen>,
+) {
+    use open_provider_stream_response::Action;
+    match response.action {
+        Some(Action::ProvideActuationResponse(_)) => {
+            let mut demux = lock(demux);
+            match demux.actuation_claims.pop_front() {
+                Some(claim) => {
+                    if let Some(reopen) = reopen {
+                        let ids = claim.signals.iter().filter_map(signal_id);
+                        remember_paths(&mut demux, reopen, ids);
+                    }
+                    demux.claimed_actuators.extend(claim.signals);
+                    if let Some(confirmed) = claim.confirmed {
+                        let _ = confirmed.send(Ok(()));
+                    }
+                }
+                None => warn!("Received unexpected ProvideActuationResponse"),
+            }
+        }
+        Some(Action::PublishValuesResponse(response)) => {
+            match lock(demux).publishes.remove(&response.request_id) {
+                Some(publish) => {
+                    let _ = publish.send(Ok(response.status));
+                }
+                // The publish timed out or was dropped
+                None => debug!("No publish waiting for {}", response.request_id),
+            }
+        }
+        Some(Action::BatchActuateStreamRequest(batch)) => {
+            let Some(actuations) = lock(demux).actuations.clone() else {
+                warn!("Dropping actuation request, no actuators claimed");
+                return;
+            };
+            for request in batch.actuate_requests {
+                let command = ActuationCommand {
+                    signal_id: request.signal_id.unwrap_or_default(),
+                    value: request.value.unwrap_or_default(),
+                    replies: requests.clone(),
+                };
+                if let Err(err) = actuations.try_send(Ok(command)) {
+                    let (reason, command) = match err {
+                        mpsc::error::TrySendError::Full(command) => ("overloaded", command),
+                        mpsc::error::TrySendError::Closed(command) => ("not listening", command),
+                    };
+                    if let Ok(command) = command {
+                        warn!("Rejecting actuation, provider {reason}");
+                        reject(command, format!("Provider {reason}"));
+                    }
+                }
+            }
+        }
+        Some(Action::ProvideSignalResponse(_)) => {
+            let mut demux = lock(demux);
+            match demux.signal_claims.pop_front() {
+                Some(claim) => {
+                    if let Some(reopen) = reopen {
+                        let ids = claim.signals.keys().copied();
+                        remember_paths(&mut demux, reopen, ids);
+                    }
+                    demux.claimed_signals.extend(claim.signals);
+                    if let Some(confirmed) = claim.confirmed {
+                        let _ = confirmed.send(Ok(()));
+                    }
+                }
+                None => warn!("Received unexpected ProvideSignalResponse"),
+            }
+        }
+        Some(Action::UpdateFilterRequest(request)) => {
+            let update = SignalRequest::UpdateFilter(FilterUpdate {
+                request_id: request.request_id,
+                filters: request.filters_update,
+                replies: requests.clone(),
+            });
+            forward_signal_request(demux, update);
+        }
+        Some(Action::GetProviderValueRequest(request)) => {
+            let get = SignalRequest::GetValues(ValueRequest {
+                request_id: request.request_id,
+                signal_ids: request.signal_id,
+                replies: requests.clone(),
+            });
+            forward_signal_request(demux, get);
+        }
+        other => debug!("Ignoring provider stream response {other:?}"),
+    }
+}
+
+fn remember_paths(demux: &mut Demux, reopen: &dyn Reopen, ids: impl Iterator<Item = i32>) {
+    for id in ids {
+        if let Some(path) = reopen.path_of(id) {
+            demux.claimed_paths.insert(id, path);
+        }
+    }
+}
+
+fn forward_signal_request(demux: &Mutex<Demux>, request: SignalRequest) {
+    let Some(signal_requests) = lock(demux).signal_requests.clone() else {
+        warn!("Dropping signal request, no signals claimed");
+        return;
+    };
+    if signal_requests.send(Ok(request)).is_err() {
+        debug!("Signal requests dropped by provider");
+    }
+}
+
+// Answers an actuation the provider cannot take without waiting for room on the stream.
+fn reject(command: ActuationCommand, message: String) {
+    let response = BatchActuateStreamResponse {
+        signal_id: Some(command.signal_id),
+        error: Some(Error {
+            code: ErrorCode::Unspecified as i32,
+            message,
+        }),
+    };
+    let request = OpenProviderStreamRequest {
+        action: Some(open_provider_stream_request::Action::BatchActuateStreamResponse(response)),
+    };
+    if command.replies.try_send(request).is_err() {
+        warn!("Dropping actuation reply, provider stream full");
+    }
+}
+
+/// Actuation requested by the databroker for one of the claimed actuators.
+#[derive(Debug)]
+pub struct ActuationCommand {
+    /// Actuator as identified by the databroker, usually by its id.
+    pub signal_id: SignalId,
+    pub value: Value,
+    replies: mpsc::Sender<OpenProviderStreamRequest>,
+}
+
+impl ActuationCommand {
+    /// Reports the outcome of the actuation to the databroker.
+    pub async fn reply(self, result: Result<(), Error>) -> Result<(), ClientError> {
+        let response = BatchActuateStreamResponse {
+            signal_id: Some(self.signal_id),
+            error: result.err(),
+        };
+        let action = open_provider_stream_request::Action::BatchActuateStreamResponse(response);
+        reply(&self.replies, action).await
+    }
+}
+
+/// Stream of the [`ActuationCommand`]s of a [`ProviderStream`].
+///
+/// If the provider stream is closed, the last item is the error which closed it.
+#[derive(Debug)]
+pub struct ActuationCommands {
+    receiver: mpsc::Receiver<Result<ActuationCommand, ClientError>>,
+}
+
+impl Stream for ActuationCommands {
+    type Item = Result<ActuationCommand, ClientError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// Request of the databroker for the signals claimed with [`ProviderStream::provide_signals`].
+#[derive(Debug)]
+pub enum SignalRequest {
+    UpdateFilter(FilterUpdate),
+    GetValues(ValueRequest),
+}
+
+/// Sample rates the subscribers of the signals need, must be confirmed with
+/// [`FilterUpdate::reply`].
+#[derive(Debug)]
+pub struct FilterUpdate {
+    /// Filter per signal id. A filter without `min_sample_interval` means that no
+    /// subscriber needs the values at a specific rate.
+    pub filters: HashMap<i32, Filter>,
+    request_id: u64,
+    replies: mpsc::Sender<OpenProviderStreamRequest>,
+}
+
+impl FilterUpdate {
+    /// Confirms that the filters have been applied.
+    pub async fn reply(self) -> Result<(), ClientError> {
+        let response = UpdateFilterResponse {
+            request_id: self.request_id,
+        };
+        let action = open_provider_stream_request::Action::UpdateFilterResponse(response);
+        reply(&self.replies, action).await
+    }
+}
+
+/// Request for the current values of signals, must be answered with [`ValueRequest::reply`].
+#[derive(Debug)]
+pub struct ValueRequest {
+    pub signal_ids: Vec<i32>,
+    request_id: u64,
+    replies: mpsc::Sender<OpenProviderStreamRequest>,
+}
+
+impl ValueRequest {
+    /// Sends the current values, signals without a value may be left out.
+    pub async fn reply(self, entries: HashMap<i32, Datapoint>) -> Result<(), ClientError> {
+        let response = GetProviderValueResponse {
+            request_id: self.request_id,
+            entries,
+        };
+        let action = open_provider_stream_request::Action::GetProviderValueResponse(response);
+        reply(&self.replies, action).await
+    }
+}
+
+async fn reply(
+    replies: &mpsc::Sender<OpenProviderStreamRequest>,
+    action: open_provider_stream_request::Action,
+) -> Result<(), ClientError> {
+    let request = OpenProviderStreamRequest {
+        action: Some(action),
+    };
+    replies
+        .send(request)
+        .await
+        .map_err(|_| ClientError::Connection("Provider stream closed".to_owned()))
+}
+
+/// Stream of the [`SignalRequest`]s of a [`ProviderStream`].
+///
+/// If the provider stream is closed, the last item is the error which closed it.
+#[derive(Debug)]
+pub struct SignalRequests {
+    receiver: mpsc::UnboundedReceiver<Result<SignalRequest, ClientError>>,
+}
+
+impl Stream for SignalRequests {
+    type Item = Result<SignalRequest, ClientError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// Provider of actuators returned by [`ClientTraitV2::provide_actuation`](super::ClientTraitV2::provide_actuation).
+///
+/// Yields an [`ActuationCommand`] for every actuation requested by the databroker,
+/// which has to be answered with [`ActuationCommand::reply`]. The actuators stay
+/// claimed until the provider is dropped.
+#[derive(Debug)]
+pub struct ActuationProvider {
+    stream: ProviderStream,
+    commands: ActuationCommands,
+}
+
+impl ActuationProvider {
+    /// Claims `actuators` on `stream`.
+    pub async fn start(
+        stream: ProviderStream,
+        actuators: Vec<SignalId>,
+    ) -> Result<Self, ClientError> {
+        let commands = stream.actuation_commands();
+        stream.provide_actuation(actuators).await?;
+        Ok(ActuationProvider { stream, commands })
+    }
+
+    /// The underlying stream, e.g. to claim further actuators.
+    pub fn provider_stream(&self) -> &ProviderStream {
+        &self.stream
+    }
+}
+
+impl Stream for ActuationProvider {
+    type Item = Result<ActuationCommand, ClientError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.commands).poll_next(cx)
+    }
+}
+
+/// Claims signals with `claim` and serves them with `serve` until `shutdown` is
+/// cancelled, claiming them again after the provider stream has been interrupted.
+///
+/// `claim` is expected to resolve the ids of the signals again, they may differ on the
+/// endpoint the client reconnected to. Returns the error of `claim` or `serve` if it
+/// is not caused by the connection, e.g. if the databroker rejects the claim.
+pub(super) async fn keep_claimed<T, C, CFut, S, SFut>(
+    claim: C,
+    serve: S,
+    policy: ReconnectPolicy,
+    shutdown: &CancellationToken,
+) -> Result<(), ClientError>
+where
+    C: Fn() -> CFut,
+    CFut: Future<Output = Result<T, ClientError>>,
+    S: Fn(T) -> SFut,
+    SFut: Future<Output = Result<(), ClientError>>,
+{
+    let mut attempt: u32 = 0;
+    loop {
+        let claimed = tokio::select! {
+            _ = shutdown.cancelled() => return Ok(()),
+            claimed = claim() => claimed,
+        };
+        let err = match claimed {
+            Ok(claimed) => {
+                attempt = 0;
+                match serve(claimed).await {
+                    Ok(()) => return Ok(()),
+                    Err(err) => err,
+                }
+            }
+            Err(err) => err,
+        };
+        if !is_interruption(&err) {
+            return Err(err);
+        }
+        attempt = attempt.saturating_add(1);
+        warn!("Provider interrupted: {err}, claiming the signals again");
+        tokio::select! {
+            _ = shutdown.cancelled() => return Ok(()),
+            _ = tokio::time::sleep(policy.backoff(attempt)) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::kuksa::val::v2::{
+        signal_id::Signal, value::TypedValue, ActuateRequest, BatchActuateStreamRequest,
+        ProvideActuationResponse, ProvideSignalResponse, PublishValuesResponse,
+    };
+    use open_provider_stream_response::Action;
+
+    struct Databroker {
+        requests: mpsc::Receiver<OpenProviderStreamRequest>,
+        responses: mpsc::Sender<Result<OpenProviderStreamResponse, tonic::Status>>,
+    }
+
+    impl Databroker {
+        async fn respond(&self, action: Action) {
+            let response = OpenProviderStreamResponse {
+                action: Some(action),
+            };
+            self.responses.send(Ok(response)).await.unwrap();
+        }
+    }
+
+    fn connection() -> (Connection, Databroker) {
+        let (sender, requests) = mpsc::channel(8);
+        let (responses, receiver) = mpsc::channel(8);
+        let stream: Responses = Box::pin(tokio_stream::wrappers::ReceiverStream::new(receiver));
+        (
+            (sender, stream),
+            Databroker {
+                requests,
+                responses,
+            },
+        )
+    }
+
+    fn provider_stream() -> (ProviderStream, Databroker) {
+        let ((sender, responses), databroker) = connection();
+        (ProviderStream::start(sender, responses), databroker)
+    }
+
+    // Connection the client fails over to, Vehicle.Speed has the id 3 on the primary
+    struct Standby {
+        connection: Mutex<Option<Connection>>,
+        speed_id: i32,
+    }
+
+    fn failover_to(connection: Connection, speed_id: i32) -> Option<Box<dyn Reopen>> {
+        Some(Box::new(Standby {
+            connection: Mutex::new(Some(connection)),
+            speed_id,
+        }))
+    }
+
+    #[async_trait]
+    impl Reopen for Standby {
+        async fn reopen(&self) -> Result<Connection, ClientError> {
+            self.connection
+                .lock()
+                .unwrap()
+                .take()
+                .ok_or_else(|| ClientError::Connection("No standby left".to_owned()))
+        }
+
+        fn path_of(&self, id: i32) -> Option<String> {
+            (id == 3).then(|| "Vehicle.Speed".to_owned())
+        }
+
+        async fn resolve_ids(
+            &self,
+            paths: Vec<String>,
+        ) -> Result<HashMap<String, i32>, ClientError> {
+            assert_eq!(paths, vec!["Vehicle.Speed".to_owned()]);
+            Ok(HashMap::from([("Vehicle.Speed".to_owned(), self.speed_id)]))
+        }
+    }
+
+    fn path(path: &str) -> SignalId {
+        SignalId {
+            signal: Some(Signal::Path(path.to_owned())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_actuation_commands_are_replied() {
+        let (stream, mut databroker) = provider_stream();
+        let claim = tokio::spawn(ActuationProvider::start(
+            stream,
+            vec![path("Vehicle.ADAS.ABS.IsEnabled")],
+        ));
+
+        let request = databroker.requests.recv().await.unwrap();
+        assert!(matches!(
+            request.action,
+            Some(open_provider_stream_request::Action::ProvideActuationRequest(_))
+        ));
+        databroker
+            .respond(Action::ProvideActuationResponse(
+                ProvideActuationResponse {},
+            ))
+            .await;
+        let mut provider = claim.await.unwrap().unwrap();
+
+        let value = Value {
+            typed_value: Some(TypedValue::Bool(true)),
+        };
+        databroker
+            .respond(Action::BatchActuateStreamRequest(
+                BatchActuateStreamRequest {
+                    actuate_requests: vec![ActuateRequest {
+                        signal_id: Some(SignalId {
+                            signal: Some(Signal::Id(7)),
+                        }),
+                        value: Some(value.clone()),
+                    }],
+                },
+            ))
+            .await;
+        let command = provider.next().await.unwrap().unwrap();
+        assert_eq!(command.value, value);
+        command.reply(Ok(())).await.unwrap();
+
+        let reply = databroker.requests.recv().await.unwrap();
+        match reply.action {
+            Some(open_provider_stream_request::Action::BatchActuateStreamResponse(response)) => {
+                assert_eq!(response.signal_id.unwrap().signal, Some(Signal::Id(7)));
+                assert_eq!(response.error, None);
+            }
+            other => panic!("unexpected request {other:?}"),
+        }
+    }
+
+    fn publish_request(request: OpenProviderStreamRequest) -> PublishValuesRequest {
+        match request.action {
+            Some(open_provider_stream_request::Action::PublishValuesRequest(request)) => request,
+            other => panic!("unexpected request {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish_answers_are_correlated() {
+        let (stream, mut databroker) = provider_stream();
+        let databroker = async {
+            let first = publish_request(databroker.requests.recv().await.unwrap());
+            let second = publish_request(databroker.requests.recv().await.unwrap());
+            // Answered out of order, rejecting signal 2
+            for request in [second, first] {
+                let status = request
+                    .data_points
+                    .into_keys()
+                    .filter(|id| *id == 2)
+                    .map(|id| {
+                        let error = Error {
+                            code: crate::proto::kuksa::val::v2::ErrorCode::NotFound as i32,
+                            message: "unknown signal".to_owned(),
+                        };
+                        (id, error)
+                    })
+                    .collect();
+                databroker
+                    .respond(Action::PublishValuesResponse(PublishValuesResponse {
+                        request_id: request.request_id,
+                        status,
+                    }))
+                    .await;
+            }
+        };
+
+        let (accepted, rejected, ()) = tokio::join!(
+            stream.publish_values(HashMap::from([(1, Datapoint::default())])),
+            stream.publish_values(HashMap::from([(2, Datapoint::default())])),
+            databroker,
+        );
+        assert!(accepted.unwrap().is_empty());
+        assert_eq!(
+            rejected.unwrap().keys().copied().collect::<Vec<_>>(),
+            vec![2]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_publish_times_out() {
+        let (stream, _databroker) = provider_stream();
+        let stream = stream.with_publish_timeout(Duration::from_millis(10));
+
+        let err = stream
+            .publish_values(HashMap::from([(1, Datapoint::default())]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ClientError::DeadlineExceeded(_)), "{err:?}");
+        assert!(lock(&stream.demux).publishes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_publish_resolves_while_actuations_are_not_polled() {
+        let (stream, mut databroker) = provider_stream();
+        let claim = tokio::spawn(ActuationProvider::start(
+            stream,
+            vec![path("Vehicle.ADAS.ABS.IsEnabled")],
+        ));
+        databroker.requests.recv().await.unwrap();
+        databroker
+            .respond(Action::ProvideActuationResponse(
+                ProvideActuationResponse {},
+            ))
+            .await;
+        let provider = claim.await.unwrap().unwrap();
+
+        let actuate_requests = (0..COMMAND_BUFFER as i32 + 2)
+            .map(|id| ActuateRequest {
+                signal_id: Some(SignalId {
+                    signal: Some(Signal::Id(id)),
+                }),
+                value: Some(Value::default()),
+            })
+            .collect();
+        databroker
+            .respond(Action::BatchActuateStreamRequest(
+                BatchActuateStreamRequest { actuate_requests },
+            ))
+            .await;
+
+        // Commands beyond the buffer are rejected instead of waiting for the provider
+        let overflow = COMMAND_BUFFER as i32;
+        for id in [overflow, overflow + 1] {
+            match databroker.requests.recv().await.unwrap().action {
+                Some(open_provider_stream_request::Action::BatchActuateStreamResponse(
+                    response,
+                )) => {
+                    assert_eq!(response.signal_id.unwrap().signal, Some(Signal::Id(id)));
+                    assert!(response.error.is_some());
+                }
+                other => panic!("unexpected request {other:?}"),
+            }
+        }
+
+        let databroker = async {
+            let request = publish_request(databroker.requests.recv().await.unwrap());
+            databroker
+                .respond(Action::PublishValuesResponse(PublishValuesResponse {
+                    request_id: request.request_id,
+                    status: HashMap::new(),
+                }))
+                .await;
+        };
+        let (published, ()) = tokio::join!(
+            provider
+                .provider_stream()
+                .publish_values(HashMap::from([(1, Datapoint::default())])),
+            databroker,
+        );
+        assert!(published.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_claim_fails() {
+        let (stream, mut databroker) = provider_stream();
+        let claim = tokio::spawn(ActuationProvider::start(
+            stream,
+            vec![path("Vehicle.ADAS.ABS.IsEnabled")],
+        ));
+
+        databroker.requests.recv().await.unwrap();
+        databroker
+            .responses
+            .send(Err(tonic::Status::already_exists(
+                "Provider already claimed",
+            )))
+            .await
+            .unwrap();
+        match claim.await.unwrap() {
+            Err(ClientError::AlreadyExists(err)) => {
+                assert_eq!(err.operation, "provide_actuation");
+                assert_eq!(err.signals, vec!["Vehicle.ADAS.ABS.IsEnabled".to_owned()]);
+            }
+            other => panic!("unexpected result {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_claims_are_renewed_after_failover() {
+        let (primary_connection, mut primary) = connection();
+        let (standby_connection, mut standby) = connection();
+        let stream = ProviderStream::spawn(primary_connection, failover_to(standby_connection, 3));
+        let mut commands = stream.actuation_commands();
+        let actuators = vec![path("Vehicle.ADAS.ABS.IsEnabled")];
+        let sample_intervals = HashMap::from([(3, SampleInterval { interval_ms: 100 })]);
+
+        let claims = async {
+            stream.provide_actuation(actuators.clone()).await.unwrap();
+            stream
+                .provide_signals(sample_intervals.clone())
+                .await
+                .unwrap();
+        };
+        let primary_answers = async {
+            primary.requests.recv().await.unwrap();
+            primary
+                .respond(Action::ProvideActuationResponse(
+                    ProvideActuationResponse {},
+                ))
+                .await;
+            primary.requests.recv().await.unwrap();
+            primary
+                .respond(Action::ProvideSignalResponse(ProvideSignalResponse {}))
+                .await;
+        };
+        tokio::join!(claims, primary_answers);
+
+        primary
+            .responses
+            .send(Err(tonic::Status::unavailable("Connection lost")))
+            .await
+            .unwrap();
+        match standby.requests.recv().await.unwrap().action {
+            Some(open_provider_stream_request::Action::ProvideActuationRequest(request)) => {
+                assert_eq!(request.actuator_identifiers, actuators);
+            }
+            other => panic!("unexpected request {other:?}"),
+        }
+        match standby.requests.recv().await.unwrap().action {
+            Some(open_provider_stream_request::Action::ProvideSignalRequest(request)) => {
+                assert_eq!(request.signals_sample_intervals, sample_intervals);
+            }
+            other => panic!("unexpected request {other:?}"),
+        }
+        standby
+            .respond(Action::ProvideActuationResponse(
+                ProvideActuationResponse {},
+            ))
+            .await;
+        standby
+            .respond(Action::ProvideSignalResponse(ProvideSignalResponse {}))
+            .await;
+
+        // Commands of the standby arrive on the same stream
+        standby
+            .respond(Action::BatchActuateStreamRequest(
+                BatchActuateStreamRequest {
+                    actuate_requests: vec![ActuateRequest {
+                        signal_id: Some(SignalId {
+                            signal: Some(Signal::Id(7)),
+                        }),
+                        value: Some(Value::default()),
+                    }],
+                },
+            ))
+            .await;
+        let command = commands.next().await.unwrap().unwrap();
+        assert_eq!(command.signal_id.signal, Some(Signal::Id(7)));
+
+        let standby_answer = async {
+            let request = publish_request(standby.requests.recv().await.unwrap());
+            standby
+                .respond(Action::PublishValuesResponse(PublishValuesResponse {
+                    request_id: request.request_id,
+                    status: HashMap::new(),
+                }))
+                .await;
+        };
+        let (published, ()) = tokio::join!(
+            stream.publish_values(HashMap::from([(3, Datapoint::default())])),
+            standby_answer,
+        );
+        assert!(published.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stream_is_closed_if_ids_differ_after_failover() {
+        let (primary_connection, mut primary) = connection();
+        let (standby_connection, _standby) = connection();
+        let stream = ProviderStream::spawn(primary_connection, failover_to(standby_connection, 4));
+        let mut requests = stream.signal_requests();
+
+        let primary_answer = async {
+            primary.requests.recv().await.unwrap();
+            primary
+                .respond(Action::ProvideSignalResponse(ProvideSignalResponse {}))
+                .await;
+        };
+        let (claimed, ()) = tokio::join!(
+            stream.provide_signals(HashMap::from([(3, SampleInterval { interval_ms: 100 })])),
+            primary_answer,
+        );
+        claimed.unwrap();
+
+        primary
+            .responses
+            .send(Err(tonic::Status::unavailable("Connection lost")))
+            .await
+            .unwrap();
+        match requests.next().await {
+            Some(Err(err @ ClientError::Connection(_))) => assert!(is_interruption(&err)),
+            other => panic!("unexpected request {other:?}"),
+        }
+        assert!(requests.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_closed_stream_ends_commands() {
+        let (stream, databroker) = provider_stream();
+        let mut commands = stream.actuation_commands();
+        drop(databroker);
+
+        assert!(matches!(
+            commands.next().await,
+            Some(Err(ClientError::Connection(_)))
+        ));
+        assert!(commands.next().await.is_none());
+    }
+}
@@ -0,0 +1,420 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+//! Runtime providing sensor values at the rate their subscribers need.
+//!
+//! ```no_run
+//! # use kuksa_rust_sdk::kuksa::common::options::CancellationToken;
+//! # use kuksa_rust_sdk::kuksa::val::v2::sensor::SensorRuntime;
+//! # use kuksa_rust_sdk::kuksa::val::v2::KuksaClientV2;
+//! # use kuksa_rust_sdk::v2_proto::{value::TypedValue, Value};
+//! # use std::time::Duration;
+//! # async fn example(client: KuksaClientV2) {
+//! let shutdown = CancellationToken::new();
+//! let result = SensorRuntime::new()
+//!     .sensor_fn("Vehicle.Speed", Duration::from_millis(10), || async {
+//!         Some(Value {
+//!             typed_value: Some(TypedValue::Float(42.0)),
+//!         })
+//!     })
+//!     .run(&client, shutdown)
+//!     .await;
+//! # }
+//! ```
+
+use super::provider::{keep_claimed, ProviderStream, SignalRequest, ValueRequest};
+use super::KuksaClientV2;
+use crate::kuksa::common::options::CancellationToken;
+use crate::kuksa::common::ClientError;
+use crate::proto::kuksa::val::v2::{Datapoint, Filter, SampleInterval, Value};
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tokio_stream::StreamExt;
+use tonic::async_trait;
+
+/// Source of the values of one sensor.
+#[async_trait]
+pub trait SensorSampler: Send + Sync + 'static {
+    /// Reads the current value, `None` if there is none available.
+    async fn sample(&self) -> Option<Value>;
+}
+
+/// [`SensorSampler`] calling an async closure.
+struct FnSampler<F>(F);
+
+#[async_trait]
+impl<F, Fut> SensorSampler for FnSampler<F>
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Option<Value>> + Send,
+{
+    async fn sample(&self) -> Option<Value> {
+        (self.0)().await
+    }
+}
+
+/// Shortest interval a sensor is published at, even if the sensor and the
+/// subscribers allow a shorter one.
+const MIN_PUBLISH_INTERVAL: Duration = Duration::from_millis(1);
+
+#[derive(Clone)]
+struct Sensor {
+    // Fastest rate the sampler can deliver
+    min_interval: Duration,
+    sampler: Arc<dyn SensorSampler>,
+}
+
+/// Provider of sensors publishing their values as often as the subscribers need.
+///
+/// The databroker announces the sample interval requested by the subscribers of a
+/// sensor with a filter update. The sensor is sampled at that interval, but never
+/// faster than its own minimal interval, or at its minimal interval if the filter
+/// requests no interval. Sensors are not sampled before the databroker sent a filter
+/// for them. Requests of the databroker for the current values are answered by
+/// sampling the sensors on demand.
+#[derive(Default)]
+pub struct SensorRuntime {
+    sensors: HashMap<String, Sensor>,
+}
+
+impl SensorRuntime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the sampler of the sensor `path`, replacing a previous one.
+    /// `min_interval` is the fastest rate the sampler is able to deliver values at.
+    pub fn sensor(
+        mut self,
+        path: impl Into<String>,
+        min_interval: Duration,
+        sampler: impl SensorSampler,
+    ) -> Self {
+        let sensor = Sensor {
+            min_interval,
+            sampler: Arc::new(sampler),
+        };
+        self.sensors.insert(path.into(), sensor);
+        self
+    }
+
+    /// Registers an async closure as sampler of the sensor `path`.
+    pub fn sensor_fn<F, Fut>(self, path: impl Into<String>, min_interval: Duration, f: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<Value>> + Send,
+    {
+        self.sensor(path, min_interval, FnSampler(f))
+    }
+
+    /// Claims the sensors and publishes their values until `shutdown` is cancelled.
+    ///
+    /// The provider stream is re-opened and the sensors claimed again after the
+    /// connection has been lost. If the reconnect policy of the client gives up or
+    /// the ids of the sensors differ after reconnecting, the ids are resolved again
+    /// and the sensors are claimed on a new provider stream after the backoff of the
+    /// policy. Returns the error if the databroker rejects the claim.
+    pub async fn run(
+        self,
+        client: &KuksaClientV2,
+        shutdown: CancellationToken,
+    ) -> Result<(), ClientError> {
+        let paths: Vec<String> = self.sensors.keys().cloned().collect();
+        let policy = client
+            .basic_client
+            .get_reconnect_policy()
+            .unwrap_or_default();
+        let claim = || async {
+            // The provider protocol identifies the signals only by id
+            let ids = client.resolve_ids_for_paths(paths.clone()).await?;
+            let stream = ProviderStream::open(client).await?;
+            Ok::<_, ClientError>((ids, Arc::new(stream)))
+        };
+        let (runtime, shutdown) = (&self, &shutdown);
+        let serve = |(ids, stream)| async move { runtime.serve(ids, stream, shutdown).await };
+        keep_claimed(claim, serve, policy, shutdown).await
+    }
+
+    async fn serve(
+        &self,
+        ids: HashMap<String, i32>,
+        stream: Arc<ProviderStream>,
+        shutdown: &CancellationToken,
+    ) -> Result<(), ClientError> {
+        let sensors: HashMap<i32, Sensor> = self
+            .sensors
+            .iter()
+            .filter_map(|(path, sensor)| Some((*ids.get(path)?, sensor.clone())))
+            .collect();
+
+        let mut requests = stream.signal_requests();
+        let sample_intervals = sensors
+            .iter()
+            .map(|(id, sensor)| {
+                let interval_ms =
+                    u32::try_from(sensor.min_interval.as_millis()).unwrap_or(u32::MAX);
+                (*id, SampleInterval { interval_ms })
+            })
+            .collect();
+        stream.provide_signals(sample_intervals).await?;
+
+        let mut tasks = JoinSet::new();
+        let mut intervals = HashMap::with_capacity(sensors.len());
+        for (id, sensor) in &sensors {
+            // Nothing is published until the databroker asks for it
+            let (interval, receiver) = watch::channel(None);
+            intervals.insert(*id, interval);
+            tasks.spawn(publish(
+                stream.clone(),
+                *id,
+                sensor.sampler.clone(),
+                receiver,
+            ));
+        }
+
+        let result = loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break Ok(()),
+                request = requests.next() => match request {
+                    Some(Ok(SignalRequest::UpdateFilter(update))) => {
+                        for (id, filter) in &update.filters {
+                            match (intervals.get(id), sensors.get(id)) {
+                                (Some(interval), Some(sensor)) => {
+                                    let period = publish_interval(filter, sensor.min_interval);
+                                    interval.send_replace(Some(period));
+                                }
+                                _ => debug!("Ignoring filter of unknown signal {id}"),
+                            }
+                        }
+                        if let Err(err) = update.reply().await {
+                            debug!("Could not confirm filter update: {err}");
+                        }
+                    }
+                    Some(Ok(SignalRequest::GetValues(request))) => {
+                        let samplers = request
+                            .signal_ids
+                            .iter()
+                            .filter_map(|id| Some((*id, sensors.get(id)?.sampler.clone())))
+                            .collect();
+                        tasks.spawn(answer(request, samplers));
+                    }
+                    Some(Err(err)) => break Err(err),
+                    None => break Err(ClientError::Connection("Provider stream closed".to_owned())),
+                },
+            }
+        };
+        tasks.abort_all();
+        result
+    }
+}
+
+/// Interval to publish at for the filter of the subscribers, the minimal interval
+/// of the sensor if they need the values at no specific rate.
+fn publish_interval(filter: &Filter, min_interval: Duration) -> Duration {
+    let requested = filter
+        .min_sample_interval
+        .as_ref()
+        .map_or(Duration::ZERO, |interval| {
+            Duration::from_millis(interval.interval_ms.into())
+        });
+    requested.max(min_interval).max(MIN_PUBLISH_INTERVAL)
+}
+
+fn datapoint(value: Value) -> Datapoint {
+    Datapoint {
+        timestamp: Some(SystemTime::now().into()),
+        value: Some(value),
+    }
+}
+
+async fn publish(
+    stream: Arc<ProviderStream>,
+    id: i32,
+    sampler: Arc<dyn SensorSampler>,
+    mut interval: watch::Receiver<Option<Duration>>,
+) {
+    loop {
+        let current = *interval.borrow_and_update();
+        let Some(period) = current else {
+            if interval.changed().await.is_err() {
+                return;
+            }
+            continue;
+        };
+        tokio::select! {
+            changed = interval.changed() => if changed.is_err() {
+                return;
+            },
+            _ = tokio::time::sleep(period) => {
+                let Some(value) = sampler.sample().await else {
+                    continue;
+                };
+                match stream.publish_values(HashMap::from([(id, datapoint(value))])).await {
+                    Ok(errors) => {
+                        if let Some(error) = errors.get(&id) {
+                            warn!("Value of signal {id} rejected: {}", error.message);
+                        }
+                    }
+                    Err(err) => debug!("Could not publish signal {id}: {err}"),
+                }
+            }
+        }
+    }
+}
+
+async fn answer(request: ValueRequest, samplers: HashMap<i32, Arc<dyn SensorSampler>>) {
+    let mut entries = HashMap::with_capacity(samplers.len());
+    for (id, sampler) in samplers {
+        if let Some(value) = sampler.sample().await {
+            entries.insert(id, datapoint(value));
+        }
+    }
+    if let Err(err) = request.reply(entries).await {
+        debug!("Could not answer value request: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::kuksa::val::v2::{
+        open_provider_stream_request, open_provider_stream_response, value::TypedValue,
+        GetProviderValueRequest, OpenProviderStreamResponse, ProvideSignalResponse,
+        UpdateFilterRequest,
+    };
+    use open_provider_stream_request::Action as Request;
+    use open_provider_stream_response::Action as Response;
+    use test_case::test_case;
+    use tokio::sync::mpsc;
+
+    fn filter(interval_ms: Option<u32>) -> Filter {
+        Filter {
+            min_sample_interval: interval_ms.map(|interval_ms| SampleInterval { interval_ms }),
+            ..Default::default()
+        }
+    }
+
+    #[test_case(Some(100), 10 => Duration::from_millis(100); "requested interval")]
+    #[test_case(Some(1), 10 => Duration::from_millis(10); "limited by sensor")]
+    #[test_case(None, 10 => Duration::from_millis(10); "no interval requested")]
+    #[test_case(Some(0), 0 => MIN_PUBLISH_INTERVAL; "never zero")]
+    #[test_case(None, 0 => MIN_PUBLISH_INTERVAL; "no interval requested of unlimited sensor")]
+    fn test_publish_interval(interval_ms: Option<u32>, min_interval_ms: u64) -> Duration {
+        publish_interval(&filter(interval_ms), Duration::from_millis(min_interval_ms))
+    }
+
+    async fn respond(
+        responses: &mpsc::Sender<Result<OpenProviderStreamResponse, tonic::Status>>,
+        action: Response,
+    ) {
+        let response = OpenProviderStreamResponse {
+            action: Some(action),
+        };
+        responses.send(Ok(response)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sensor_follows_databroker_requests() {
+        let (sender, mut requests) = mpsc::channel(8);
+        let (responses, receiver) = mpsc::channel(8);
+        let stream = ProviderStream::start(
+            sender,
+            Box::pin(tokio_stream::wrappers::ReceiverStream::new(receiver)),
+        );
+
+        let runtime = SensorRuntime::new().sensor_fn("Vehicle.Speed", Duration::ZERO, || async {
+            Some(Value {
+                typed_value: Some(TypedValue::Float(42.0)),
+            })
+        });
+        let ids = HashMap::from([("Vehicle.Speed".to_owned(), 7)]);
+        let shutdown = CancellationToken::new();
+        let serving = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { runtime.serve(ids, Arc::new(stream), &shutdown).await }
+        });
+
+        match requests.recv().await.unwrap().action {
+            Some(Request::ProvideSignalRequest(request)) => {
+                assert_eq!(
+                    request.signals_sample_intervals,
+                    HashMap::from([(7, SampleInterval { interval_ms: 0 })])
+                );
+            }
+            other => panic!("unexpected request {other:?}"),
+        }
+        respond(
+            &responses,
+            Response::ProvideSignalResponse(ProvideSignalResponse {}),
+        )
+        .await;
+
+        respond(
+            &responses,
+            Response::UpdateFilterRequest(UpdateFilterRequest {
+                request_id: 3,
+                filters_update: HashMap::from([(7, filter(Some(1)))]),
+            }),
+        )
+        .await;
+        assert!(matches!(
+            requests.recv().await.unwrap().action,
+            Some(Request::UpdateFilterResponse(response)) if response.request_id == 3
+        ));
+        match requests.recv().await.unwrap().action {
+            Some(Request::PublishValuesRequest(request)) => {
+                assert!(request.data_points.contains_key(&7));
+            }
+            other => panic!("unexpected request {other:?}"),
+        }
+
+        // Publishing continues at the minimal interval, the value is also sampled on demand
+        respond(
+            &responses,
+            Response::UpdateFilterRequest(UpdateFilterRequest {
+                request_id: 4,
+                filters_update: HashMap::from([(7, filter(None))]),
+            }),
+        )
+        .await;
+        respond(
+            &responses,
+            Response::GetProviderValueRequest(GetProviderValueRequest {
+                request_id: 5,
+                signal_id: vec![7],
+            }),
+        )
+        .await;
+        loop {
+            match requests.recv().await.unwrap().action {
+                Some(Request::GetProviderValueResponse(response)) => {
+                    assert_eq!(response.request_id, 5);
+                    assert!(response.entries.contains_key(&7));
+                    break;
+                }
+                Some(Request::PublishValuesRequest(_) | Request::UpdateFilterResponse(_)) => {}
+                other => panic!("unexpected request {other:?}"),
+            }
+        }
+
+        shutdown.cancel();
+        serving.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,265 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+//! Handles to a single signal with values of a fixed Rust type.
+//!
+//! ```no_run
+//! # use kuksa_rust_sdk::kuksa::val::v2::KuksaClientV2;
+//! # async fn example(client: KuksaClientV2) {
+//! let speed = client.signal::<f32>("Vehicle.Speed").await.unwrap();
+//! speed.publish(30.0).await.unwrap();
+//! assert_eq!(speed.get().await.unwrap(), Some(30.0));
+//! # }
+//! ```
+
+use super::updates::{SignalUpdate, SignalUpdateError, TypedSignalUpdates};
+use super::KuksaClientV2;
+use super::SubscribeOptions;
+use crate::kuksa::common::types::SignalRef;
+use crate::kuksa::common::{ClientError, ClientTraitV2};
+use crate::proto::kuksa::val::v2::value::TypedValue;
+use crate::proto::kuksa::val::v2::{DataType, Metadata, Value};
+use futures_core::Stream;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+/// Rust type which can hold the values of signals of some VSS data types.
+pub trait SignalType: TryFrom<TypedValue> + Into<TypedValue> + Send + 'static {
+    /// Returns whether values of signals of `data_type` can be represented by this type.
+    fn accepts(data_type: DataType) -> bool;
+}
+
+macro_rules! impl_signal_type {
+    ($($type:ty => $($data_type:ident)|+),* $(,)?) => {
+        $(
+            impl SignalType for $type {
+                fn accepts(data_type: DataType) -> bool {
+                    matches!(data_type, $(DataType::$data_type)|+)
+                }
+            }
+        )*
+    };
+}
+
+// Signals of the smaller integer types are transferred as 32 bit values
+impl_signal_type!(
+    String => String,
+    bool => Boolean,
+    i32 => Int8 | Int16 | Int32,
+    i64 => Int64,
+    u32 => Uint8 | Uint16 | Uint32,
+    u64 => Uint64,
+    f32 => Float,
+    f64 => Double,
+    Vec<String> => StringArray,
+    Vec<bool> => BooleanArray,
+    Vec<i32> => Int8Array | Int16Array | Int32Array,
+    Vec<i64> => Int64Array,
+    Vec<u32> => Uint8Array | Uint16Array | Uint32Array,
+    Vec<u64> => Uint64Array,
+    Vec<f32> => FloatArray,
+    Vec<f64> => DoubleArray,
+);
+
+/// Handle to a signal with values of type `T`, returned by [`KuksaClientV2::signal`].
+///
+/// The signal is addressed by its id, which has been resolved when creating the handle.
+#[derive(Debug, Clone)]
+pub struct Signal<T> {
+    client: KuksaClientV2,
+    metadata: Metadata,
+    value_type: PhantomData<fn() -> T>,
+}
+
+impl<T: SignalType> Signal<T> {
+    pub fn path(&self) -> &str {
+        &self.metadata.path
+    }
+
+    pub fn id(&self) -> i32 {
+        self.metadata.id
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    fn signal_ref(&self) -> SignalRef {
+        SignalRef::Id(self.metadata.id)
+    }
+
+    /// Returns the current value, `None` if the signal has no value (yet).
+    ///
+    /// Returns the errors of [`ClientTraitV2::get_value`].
+    pub async fn get(&self) -> Result<Option<T>, ClientError> {
+        let datapoint = self.client.get_value(self.signal_ref()).await?;
+        let value = datapoint.and_then(|datapoint| datapoint.value?.typed_value);
+        match value.map(T::try_from) {
+            None => Ok(None),
+            Some(Ok(value)) => Ok(Some(value)),
+            Some(Err(_)) => Err(super::invalid_argument(
+                "get",
+                vec![self.metadata.path.clone()],
+                format!(
+                    "Value cannot be decoded into {}",
+                    std::any::type_name::<T>()
+                ),
+            )),
+        }
+    }
+
+    /// Publishes the current value of a sensor or attribute.
+    ///
+    /// Returns the errors of [`ClientTraitV2::publish_value`].
+    pub async fn publish(&self, value: T) -> Result<(), ClientError> {
+        self.client
+            .publish_value(self.signal_ref(), to_value(value))
+            .await
+    }
+
+    /// Requests the actuator to change to `value`.
+    ///
+    /// Returns the errors of [`ClientTraitV2::actuate`].
+    pub async fn actuate(&self, value: T) -> Result<(), ClientError> {
+        self.client
+            .actuate(self.signal_ref(), to_value(value))
+            .await
+    }
+
+    /// Subscribes to the values of the signal.
+    ///
+    /// Returns the errors of [`ClientTraitV2::subscribe`].
+    pub async fn subscribe(&self) -> Result<SignalSubscription<T>, ClientError> {
+        let updates = self
+            .client
+            .subscribe_updates(
+                vec![self.metadata.path.clone()],
+                SubscribeOptions::default(),
+            )
+            .await?;
+        Ok(SignalSubscription {
+            updates: updates.typed(),
+        })
+    }
+}
+
+fn to_value(value: impl Into<TypedValue>) -> Value {
+    Value {
+        typed_value: Some(value.into()),
+    }
+}
+
+/// Stream of the values of a [`Signal`], updates without a value are skipped.
+#[derive(Debug)]
+pub struct SignalSubscription<T> {
+    updates: TypedSignalUpdates<T>,
+}
+
+impl<T: SignalType> Stream for SignalSubscription<T> {
+    type Item = Result<T, SignalUpdateError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let item = match ready!(Pin::new(&mut self.updates).poll_next(cx)) {
+                Some(Ok(SignalUpdate { value: None, .. })) => continue,
+                Some(Ok(SignalUpdate {
+                    value: Some(value), ..
+                })) => Ok(value),
+                Some(Err(err)) => Err(err),
+                None => return Poll::Ready(None),
+            };
+            return Poll::Ready(Some(item));
+        }
+    }
+}
+
+impl KuksaClientV2 {
+    /// Returns a handle to the signal at `path` with values of type `T`.
+    ///
+    /// The metadata of the signal is resolved with [`KuksaClientV2::resolve_metadata`]
+    /// and its data type is checked against `T`, so a mismatch is reported here
+    /// instead of by the first call.
+    ///
+    /// Returns (GRPC error code):
+    ///   NOT_FOUND if the signal does not exist.
+    ///   INVALID_ARGUMENT if the data type of the signal cannot be represented by `T`.
+    ///   UNAUTHENTICATED if no credentials provided or credentials has expired
+    ///
+    pub async fn signal<T: SignalType>(
+        &self,
+        path: impl Into<String>,
+    ) -> Result<Signal<T>, ClientError> {
+        let path = path.into();
+        let mut resolved = self
+            .resolve_metadata(vec![path.clone()])
+            .await
+            .map_err(|err| err.with_context("signal", vec![]))?;
+        let Some(metadata) = resolved.remove(&path) else {
+            // Patterns may resolve to several signals
+            return Err(super::invalid_argument(
+                "signal",
+                vec![path],
+                "Path must address a single signal".to_owned(),
+            ));
+        };
+        let data_type = DataType::try_from(metadata.data_type).unwrap_or(DataType::Unspecified);
+        if !T::accepts(data_type) {
+            return Err(super::invalid_argument(
+                "signal",
+                vec![path],
+                format!(
+                    "Signal of type {} cannot hold values of {}",
+                    data_type.as_str_name(),
+                    std::any::type_name::<T>()
+                ),
+            ));
+        }
+        Ok(Signal {
+            client: self.clone(),
+            metadata,
+            value_type: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(DataType::Float, true; "same type")]
+    #[test_case(DataType::Double, false; "other type")]
+    #[test_case(DataType::FloatArray, false; "array")]
+    fn test_f32_accepts(data_type: DataType, expected: bool) {
+        assert_eq!(f32::accepts(data_type), expected);
+    }
+
+    #[test_case(DataType::Uint8, true; "uint8")]
+    #[test_case(DataType::Uint16, true; "uint16")]
+    #[test_case(DataType::Uint32, true; "uint32")]
+    #[test_case(DataType::Uint64, false; "uint64")]
+    #[test_case(DataType::Int8, false; "int8")]
+    fn test_u32_accepts(data_type: DataType, expected: bool) {
+        assert_eq!(u32::accepts(data_type), expected);
+    }
+
+    #[test]
+    fn test_arrays_accept_array_types() {
+        assert!(Vec::<String>::accepts(DataType::StringArray));
+        assert!(!Vec::<String>::accepts(DataType::String));
+        assert!(Vec::<i32>::accepts(DataType::Int16Array));
+    }
+}
@@ -0,0 +1,521 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+use super::SubscribeOptions;
+use super::{KuksaClientV2, ValStub};
+use crate::kuksa::common::{Client, ClientError, ClientTraitV2};
+use crate::proto::kuksa::val::v2::{
+    DataType, Datapoint, Metadata, SubscribeByIdRequest, SubscribeByIdResponse, SubscribeRequest,
+    SubscribeResponse,
+};
+use futures_core::Stream;
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tonic::{async_trait, Streaming};
+
+/// Event emitted by a [`ResilientSubscription`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubscriptionEvent<T> {
+    /// Update received from the databroker.
+    Update(T),
+    /// The subscription was interrupted and has been re-established.
+    /// Values published in between may have been missed, the update following
+    /// the gap contains the current values of all subscribed signals.
+    Gap,
+}
+
+/// Subscription which keeps its request and transparently re-subscribes
+/// after the connection to the databroker has been lost.
+///
+/// The stream only ends if the databroker rejects the subscription (e.g. because
+/// of missing permissions) or if the reconnect policy of the client gives up.
+/// In both cases the last item is the corresponding error.
+pub struct ResilientSubscription<T> {
+    receiver: mpsc::Receiver<Result<SubscriptionEvent<T>, ClientError>>,
+    task: JoinHandle<()>,
+}
+
+pub type ResilientSubscribeStream = ResilientSubscription<SubscribeResponse>;
+pub type ResilientSubscribeByIdStream = ResilientSubscription<SubscribeByIdResponse>;
+
+#[async_trait]
+trait ResubscribeRequest: Clone + Send + Sync + 'static {
+    type Response: Send + 'static;
+
+    async fn subscribe(
+        &self,
+        client: &KuksaClientV2,
+    ) -> Result<Streaming<Self::Response>, ClientError>;
+}
+
+#[async_trait]
+impl ResubscribeRequest for SubscribeRequest {
+    type Response = SubscribeResponse;
+
+    async fn subscribe(
+        &self,
+        client: &KuksaClientV2,
+    ) -> Result<Streaming<Self::Response>, ClientError> {
+        let response = client
+            .basic_client
+            .call_stub_streaming(|mut stub: ValStub| {
+                let request = self.clone();
+                async move { stub.subscribe(request).await }
+            })
+            .await?;
+        Ok(response.into_inner())
+    }
+}
+
+#[async_trait]
+impl ResubscribeRequest for SubscribeByIdRequest {
+    type Response = SubscribeByIdResponse;
+
+    async fn subscribe(
+        &self,
+        client: &KuksaClientV2,
+    ) -> Result<Streaming<Self::Response>, ClientError> {
+        let response = client
+            .basic_client
+            .call_stub_streaming(|mut stub: ValStub| {
+                let request = self.clone();
+                async move { stub.subscribe_by_id(request).await }
+            })
+            .await?;
+        Ok(response.into_inner())
+    }
+}
+
+impl<T: Send + 'static> ResilientSubscription<T> {
+    async fn start<R>(client: KuksaClientV2, request: R, buffer: usize) -> Result<Self, ClientError>
+    where
+        R: ResubscribeRequest<Response = T>,
+    {
+        // The initial subscription is done eagerly to report invalid requests to the caller
+        let stream = request.subscribe(&client).await?;
+        let (sender, receiver) = mpsc::channel(buffer.max(1));
+        let task = tokio::spawn(run(client, request, stream, sender));
+        Ok(ResilientSubscription { receiver, task })
+    }
+}
+
+impl<T> Stream for ResilientSubscription<T> {
+    type Item = Result<SubscriptionEvent<T>, ClientError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl<T> Drop for ResilientSubscription<T> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Returns true if the subscription ended because of the connection and not
+/// because the databroker rejected it.
+pub(super) fn is_interruption(err: &ClientError) -> bool {
+    match err {
+        ClientError::Connection(_) | ClientError::Unavailable(_) => true,
+        ClientError::Status(status) => {
+            status.code() == tonic::Code::Unavailable || std::error::Error::source(status).is_some()
+        }
+        _ => false,
+    }
+}
+
+async fn run<R: ResubscribeRequest>(
+    client: KuksaClientV2,
+    request: R,
+    mut stream: Streaming<R::Response>,
+    sender: mpsc::Sender<Result<SubscriptionEvent<R::Response>, ClientError>>,
+) {
+    loop {
+        let interruption = match stream.message().await {
+            Ok(Some(response)) => {
+                if sender
+                    .send(Ok(SubscriptionEvent::Update(response)))
+                    .await
+                    .is_err()
+                {
+                    // Subscription has been dropped
+                    return;
+                }
+                continue;
+            }
+            // The databroker closed the stream, e.g. because it is shutting down
+            Ok(None) => {
+                client.basic_client.disconnect();
+                ClientError::Connection("Subscription closed by databroker".to_owned())
+            }
+            Err(status) => client.basic_client.handle_status(status),
+        };
+
+        if !is_interruption(&interruption) {
+            let _ = sender.send(Err(interruption)).await;
+            return;
+        }
+        warn!("Subscription interrupted: {interruption}, re-subscribing");
+
+        stream = match resubscribe(&client, &request).await {
+            Ok(stream) => stream,
+            Err(err) => {
+                let _ = sender.send(Err(err)).await;
+                return;
+            }
+        };
+        if sender.send(Ok(SubscriptionEvent::Gap)).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn resubscribe<R: ResubscribeRequest>(
+    client: &KuksaClientV2,
+    request: &R,
+) -> Result<Streaming<R::Response>, ClientError> {
+    reestablish(client, || request.subscribe(client)).await
+}
+
+/// Re-opens a stream with `open` after it has been interrupted, following the
+/// reconnect policy of the client.
+pub(super) async fn reestablish<T, F, Fut>(
+    client: &KuksaClientV2,
+    open: F,
+) -> Result<T, ClientError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, ClientError>>,
+{
+    let policy = client
+        .basic_client
+        .get_reconnect_policy()
+        .unwrap_or_default();
+    let mut attempt: u32 = 0;
+    loop {
+        attempt += 1;
+        // Only this loop applies the policy, the channel is connected with single attempts
+        let result = match client.basic_client.connect_attempt(attempt).await {
+            Ok(()) => open().await,
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(stream) => {
+                debug!("Re-established stream after {attempt} attempt(s)");
+                return Ok(stream);
+            }
+            Err(err) if is_interruption(&err) && policy.should_retry(attempt) => {
+                tokio::time::sleep(policy.backoff(attempt)).await;
+            }
+            Err(err) => {
+                if !client.basic_client.is_connected() {
+                    client.basic_client.disconnect();
+                }
+                return Err(err);
+            }
+        }
+    }
+}
+
+impl KuksaClientV2 {
+    /// Subscribe to a set of signals using string path parameters and keep the
+    /// subscription alive across reconnects.
+    ///
+    /// Takes the same parameters as [`ClientTraitV2::subscribe`](super::ClientTraitV2::subscribe)
+    /// and returns the same errors if the initial subscription fails. The subscription
+    /// runs on a clone of this client, the reconnect policy set on this client is used
+    /// to re-subscribe ([`ReconnectPolicy::default`](crate::kuksa::common::reconnect::ReconnectPolicy::default) if none is set).
+    /// If failover endpoints are configured, the subscription is re-established on
+    /// the endpoint the client fails over to.
+    pub async fn subscribe_resilient(
+        &self,
+        signal_paths: Vec<String>,
+        options: SubscribeOptions,
+    ) -> Result<ResilientSubscribeStream, ClientError> {
+        options.validate_paths(&signal_paths).map_err(|message| {
+            super::invalid_argument("subscribe_resilient", signal_paths.clone(), message)
+        })?;
+        let request = SubscribeRequest {
+            signal_paths,
+            buffer_size: options.buffer_size(),
+            filter: options.filter().cloned(),
+        };
+        ResilientSubscription::start(self.clone(), request, options.buffer_size() as usize).await
+    }
+
+    /// Subscribe to a set of signals using i32 id parameters and keep the
+    /// subscription alive across reconnects.
+    ///
+    /// See [`KuksaClientV2::subscribe_resilient`].
+    pub async fn subscribe_by_id_resilient(
+        &self,
+        signal_ids: Vec<i32>,
+        options: SubscribeOptions,
+    ) -> Result<ResilientSubscribeByIdStream, ClientError> {
+        options.validate_ids(&signal_ids).map_err(|message| {
+            let signals = signal_ids.iter().map(i32::to_string).collect();
+            super::invalid_argument("subscribe_by_id_resilient", signals, message)
+        })?;
+        let request = SubscribeByIdRequest {
+            signal_ids,
+            buffer_size: options.buffer_size(),
+            filter: options.filter().cloned(),
+        };
+        ResilientSubscription::start(self.clone(), request, options.buffer_size() as usize).await
+    }
+
+    /// Subscribe to a set of signals by their ids, but receive the updates keyed by
+    /// path together with the data type and unit of each signal.
+    ///
+    /// The paths are resolved with [`KuksaClientV2::resolve_metadata`], so they may
+    /// contain wildcards. Returns the errors of `resolve_metadata` and of
+    /// [`ClientTraitV2::subscribe_by_id`](super::ClientTraitV2::subscribe_by_id).
+    pub async fn subscribe_resolved(
+        &self,
+        signal_paths: Vec<String>,
+        options: SubscribeOptions,
+    ) -> Result<ResolvedSubscription, ClientError> {
+        let metadata = self
+            .resolve_metadata(signal_paths)
+            .await
+            .map_err(|err| err.with_context("subscribe_resolved", vec![]))?;
+        let signals: HashMap<i32, SignalInfo> = metadata
+            .into_values()
+            .map(|metadata| (metadata.id, SignalInfo::from(metadata)))
+            .collect();
+        let stream = self
+            .subscribe_by_id(signals.keys().copied().collect(), options)
+            .await?;
+        Ok(ResolvedSubscription {
+            client: self.basic_client.clone(),
+            signals: Arc::new(signals),
+            stream,
+        })
+    }
+}
+
+/// Update of a signal received by a [`ResolvedSubscription`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalValue {
+    pub id: i32,
+    pub path: String,
+    pub data_type: DataType,
+    /// Unit of the signal as defined in VSS, e.g. `km/h`, if it has one.
+    pub unit: Option<String>,
+    pub datapoint: Datapoint,
+}
+
+/// Updates received with one response of a [`ResolvedSubscription`], which can be
+/// looked up by path or by id.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SignalValues {
+    by_path: HashMap<String, SignalValue>,
+    paths_by_id: HashMap<i32, String>,
+}
+
+impl SignalValues {
+    pub fn get(&self, path: &str) -> Option<&SignalValue> {
+        self.by_path.get(path)
+    }
+
+    pub fn get_by_id(&self, id: i32) -> Option<&SignalValue> {
+        self.by_path.get(self.paths_by_id.get(&id)?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_path.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_path.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SignalValue> {
+        self.by_path.values()
+    }
+}
+
+impl IntoIterator for SignalValues {
+    type Item = SignalValue;
+    type IntoIter = std::collections::hash_map::IntoValues<String, SignalValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.by_path.into_values()
+    }
+}
+
+/// Metadata of a signal carried along with its updates.
+#[derive(Debug)]
+struct SignalInfo {
+    path: String,
+    data_type: DataType,
+    unit: Option<String>,
+}
+
+impl From<Metadata> for SignalInfo {
+    fn from(metadata: Metadata) -> Self {
+        SignalInfo {
+            data_type: DataType::try_from(metadata.data_type).unwrap_or(DataType::Unspecified),
+            unit: Some(metadata.unit).filter(|unit| !unit.is_empty()),
+            path: metadata.path,
+        }
+    }
+}
+
+fn to_signal_values(
+    signals: &HashMap<i32, SignalInfo>,
+    response: SubscribeByIdResponse,
+) -> SignalValues {
+    let mut values = SignalValues::default();
+    for (id, datapoint) in response.entries {
+        let Some(signal) = signals.get(&id) else {
+            debug!("Ignoring update of unknown signal {id}");
+            continue;
+        };
+        values.paths_by_id.insert(id, signal.path.clone());
+        values.by_path.insert(
+            signal.path.clone(),
+            SignalValue {
+                id,
+                path: signal.path.clone(),
+                data_type: signal.data_type,
+                unit: signal.unit.clone(),
+                datapoint,
+            },
+        );
+    }
+    values
+}
+
+/// Subscription by id returned by [`KuksaClientV2::subscribe_resolved`], yielding
+/// the updates as [`SignalValues`].
+///
+/// Ends when the databroker closes the subscription, an error ends it as well.
+#[derive(Debug)]
+pub struct ResolvedSubscription {
+    client: Client,
+    signals: Arc<HashMap<i32, SignalInfo>>,
+    stream: Streaming<SubscribeByIdResponse>,
+}
+
+impl Stream for ResolvedSubscription {
+    type Item = Result<SignalValues, ClientError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = match Pin::new(&mut self.stream).poll_next(cx) {
+            Poll::Ready(Some(Ok(response))) => Ok(to_signal_values(&self.signals, response)),
+            Poll::Ready(Some(Err(status))) => Err(self
+                .client
+                .handle_status(status)
+                .with_context("subscribe_resolved", vec![])),
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
+        };
+        Poll::Ready(Some(item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transport_errors_are_interruptions() {
+        let mut status = tonic::Status::unknown("error reading a body from connection");
+        status.set_source(Arc::new(std::io::Error::from(
+            std::io::ErrorKind::ConnectionReset,
+        )));
+        assert!(is_interruption(&ClientError::Status(status)));
+        assert!(is_interruption(&ClientError::Connection(
+            "Failed to connect".to_owned()
+        )));
+        assert!(is_interruption(&ClientError::Status(
+            tonic::Status::unavailable("shutting down")
+        )));
+    }
+
+    #[test]
+    fn test_rejections_are_not_interruptions() {
+        assert!(!is_interruption(&ClientError::Status(
+            tonic::Status::permission_denied("no access")
+        )));
+        assert!(!is_interruption(&ClientError::Status(
+            tonic::Status::not_found("unknown path")
+        )));
+    }
+
+    #[test]
+    fn test_updates_are_keyed_by_path_and_id() {
+        let signals = HashMap::from([(
+            7,
+            SignalInfo::from(Metadata {
+                id: 7,
+                path: "Vehicle.Speed".to_owned(),
+                data_type: DataType::Float as i32,
+                unit: "km/h".to_owned(),
+                ..Default::default()
+            }),
+        )]);
+        let response = SubscribeByIdResponse {
+            entries: HashMap::from([(7, Datapoint::default()), (8, Datapoint::default())]),
+        };
+
+        let values = to_signal_values(&signals, response);
+        assert_eq!(values.len(), 1);
+        let speed = values.get("Vehicle.Speed").unwrap();
+        assert_eq!(values.get_by_id(7), Some(speed));
+        assert_eq!(speed.data_type, DataType::Float);
+        assert_eq!(speed.unit.as_deref(), Some("km/h"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_resubscribe_makes_attempts_of_policy_only() {
+        use crate::kuksa::common::reconnect::ReconnectPolicy;
+        use crate::kuksa::common::{to_uri, ConnectionState};
+        use std::time::Duration;
+        use tokio_stream::StreamExt;
+
+        let client = KuksaClientV2::new(to_uri("unix:///nonexistent/databroker.sock").unwrap());
+        client.basic_client.set_reconnect_policy(Some(
+            ReconnectPolicy::default()
+                .with_initial_backoff(Duration::from_millis(1))
+                .with_max_attempts(Some(3)),
+        ));
+        let mut states = client.basic_client.subscribe_to_connection_state();
+
+        assert!(resubscribe(&client, &SubscribeRequest::default())
+            .await
+            .is_err());
+
+        let mut attempts = Vec::new();
+        while let Some(Ok(state)) = states.next().await {
+            match state {
+                ConnectionState::Connecting => attempts.push(1),
+                ConnectionState::Reconnecting { attempt } => attempts.push(attempt),
+                ConnectionState::Disconnected => break,
+                _ => {}
+            }
+        }
+        assert_eq!(attempts, vec![1, 2, 3]);
+    }
+}
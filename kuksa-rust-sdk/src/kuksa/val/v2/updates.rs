@@ -0,0 +1,315 @@
+/*
+ * *******************************************************************************
+ *  Copyright (c) 2025 Contributors to the Eclipse Foundation
+ *
+ *  See the NOTICE file(s) distributed with this work for additional
+ *  information regarding copyright ownership.
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License 2.0 which is available at
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ * ******************************************************************************
+ */
+
+//! Subscriptions yielding one decoded [`SignalUpdate`] per signal instead of the
+//! raw `SubscribeResponse`s.
+
+use super::KuksaClientV2;
+use super::SubscribeOptions;
+use crate::kuksa::common::{Client, ClientError, ClientTraitV2};
+use crate::proto::kuksa::val::v2::value::TypedValue;
+use crate::proto::kuksa::val::v2::{Datapoint, SubscribeResponse};
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::fmt;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::SystemTime;
+
+type Responses = Pin<Box<dyn Stream<Item = Result<SubscribeResponse, tonic::Status>> + Send>>;
+
+/// Update of a single signal, `T` is the type the value has been decoded into.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalUpdate<T = TypedValue> {
+    pub path: String,
+    /// `None` if the signal has no value (yet).
+    pub value: Option<T>,
+    /// Time the value has been set at, or received at if the databroker sent none.
+    pub timestamp: SystemTime,
+}
+
+impl SignalUpdate {
+    fn from_datapoint(path: String, datapoint: Datapoint, received: SystemTime) -> Self {
+        let timestamp = datapoint
+            .timestamp
+            .and_then(|timestamp| SystemTime::try_from(timestamp).ok())
+            .unwrap_or(received);
+        SignalUpdate {
+            path,
+            value: datapoint.value.and_then(|value| value.typed_value),
+            timestamp,
+        }
+    }
+
+    /// Decodes the value into `T`, e.g. `f32` for a signal of type float.
+    pub fn decode<T: TryFrom<TypedValue>>(self) -> Result<SignalUpdate<T>, SignalUpdateError> {
+        let value = match self.value.map(T::try_from) {
+            None => None,
+            Some(Ok(value)) => Some(value),
+            Some(Err(_)) => {
+                return Err(SignalUpdateError::Decode {
+                    path: self.path,
+                    expected: std::any::type_name::<T>(),
+                })
+            }
+        };
+        Ok(SignalUpdate {
+            path: self.path,
+            value,
+            timestamp: self.timestamp,
+        })
+    }
+}
+
+/// Error yielded by a [`TypedSignalUpdates`] stream.
+#[derive(Debug, Clone)]
+pub enum SignalUpdateError {
+    /// The value of the signal at `path` cannot be decoded into `expected`. Only the
+    /// update is skipped, the stream continues.
+    Decode {
+        path: String,
+        expected: &'static str,
+    },
+    /// The subscription failed, this is the last item of the stream.
+    Subscription(Box<ClientError>),
+}
+
+impl std::error::Error for SignalUpdateError {}
+
+impl fmt::Display for SignalUpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignalUpdateError::Decode { path, expected } => {
+                write!(f, "Value of {path} cannot be decoded into {expected}")
+            }
+            SignalUpdateError::Subscription(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<ClientError> for SignalUpdateError {
+    fn from(err: ClientError) -> Self {
+        SignalUpdateError::Subscription(Box::new(err))
+    }
+}
+
+/// Stream of the [`SignalUpdate`]s of a subscription, yielding the entries of each
+/// response one by one.
+///
+/// Ends when the databroker closes the subscription, an error ends it as well.
+pub struct SignalUpdates {
+    responses: Responses,
+    pending: VecDeque<SignalUpdate>,
+    // Drops the channel if the subscription failed because of it
+    client: Option<Client>,
+}
+
+impl SignalUpdates {
+    /// Wraps the responses of a subscription, e.g. the stream returned by
+    /// [`ClientTraitV2::subscribe`](super::ClientTraitV2::subscribe).
+    pub fn new(
+        responses: impl Stream<Item = Result<SubscribeResponse, tonic::Status>> + Send + 'static,
+    ) -> Self {
+        SignalUpdates {
+            responses: Box::pin(responses),
+            pending: VecDeque::new(),
+            client: None,
+        }
+    }
+
+    /// Decodes the values into `T`, see [`TypedSignalUpdates`].
+    pub fn typed<T: TryFrom<TypedValue>>(self) -> TypedSignalUpdates<T> {
+        TypedSignalUpdates {
+            updates: self,
+            value_type: PhantomData,
+        }
+    }
+
+    fn error(&self, status: tonic::Status) -> ClientError {
+        let err = match &self.client {
+            Some(client) => client.handle_status(status),
+            None => ClientError::Status(status),
+        };
+        err.with_context("subscribe", vec![])
+    }
+}
+
+impl fmt::Debug for SignalUpdates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignalUpdates")
+            .field("pending", &self.pending)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Stream for SignalUpdates {
+    type Item = Result<SignalUpdate, ClientError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(update) = self.pending.pop_front() {
+                return Poll::Ready(Some(Ok(update)));
+            }
+            match ready!(self.responses.as_mut().poll_next(cx)) {
+                Some(Ok(response)) => {
+                    let received = SystemTime::now();
+                    let updates = response.entries.into_iter().map(|(path, datapoint)| {
+                        SignalUpdate::from_datapoint(path, datapoint, received)
+                    });
+                    self.pending.extend(updates);
+                }
+                Some(Err(status)) => return Poll::Ready(Some(Err(self.error(status)))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+/// Stream of [`SignalUpdate`]s with the values decoded into `T`, created with
+/// [`SignalUpdates::typed`].
+///
+/// A value which cannot be decoded is reported as [`SignalUpdateError::Decode`]
+/// without ending the stream.
+#[derive(Debug)]
+pub struct TypedSignalUpdates<T> {
+    updates: SignalUpdates,
+    value_type: PhantomData<fn() -> T>,
+}
+
+impl<T: TryFrom<TypedValue>> Stream for TypedSignalUpdates<T> {
+    type Item = Result<SignalUpdate<T>, SignalUpdateError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = match ready!(Pin::new(&mut self.updates).poll_next(cx)) {
+            Some(Ok(update)) => update.decode(),
+            Some(Err(err)) => Err(err.into()),
+            None => return Poll::Ready(None),
+        };
+        Poll::Ready(Some(item))
+    }
+}
+
+impl KuksaClientV2 {
+    /// Subscribe to a set of signals using string path parameters and receive one
+    /// [`SignalUpdate`] per signal and response.
+    ///
+    /// Takes the same parameters and returns the same errors as
+    /// [`ClientTraitV2::subscribe`](super::ClientTraitV2::subscribe).
+    pub async fn subscribe_updates(
+        &self,
+        signal_paths: Vec<String>,
+        options: SubscribeOptions,
+    ) -> Result<SignalUpdates, ClientError> {
+        let stream = self.subscribe(signal_paths, options).await?;
+        let mut updates = SignalUpdates::new(stream);
+        updates.client = Some(self.basic_client.clone());
+        Ok(updates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::kuksa::val::v2::Value;
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tokio_stream::StreamExt;
+
+    fn datapoint(value: Option<TypedValue>, seconds: Option<i64>) -> Datapoint {
+        Datapoint {
+            timestamp: seconds.map(|seconds| prost_types::Timestamp { seconds, nanos: 0 }),
+            value: value.map(|value| Value {
+                typed_value: Some(value),
+            }),
+        }
+    }
+
+    fn response(entries: &[(&str, Datapoint)]) -> SubscribeResponse {
+        let entries: HashMap<String, Datapoint> = entries
+            .iter()
+            .map(|(path, datapoint)| (path.to_string(), datapoint.clone()))
+            .collect();
+        SubscribeResponse { entries }
+    }
+
+    #[test]
+    fn test_update_from_datapoint() {
+        let received = SystemTime::now();
+        let update = SignalUpdate::from_datapoint(
+            "Vehicle.Speed".to_owned(),
+            datapoint(Some(TypedValue::Float(30.0)), Some(10)),
+            received,
+        );
+        assert_eq!(update.value, Some(TypedValue::Float(30.0)));
+        assert_eq!(
+            update.timestamp,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(10)
+        );
+
+        let update = SignalUpdate::from_datapoint(
+            "Vehicle.Speed".to_owned(),
+            datapoint(None, None),
+            received,
+        );
+        assert_eq!(update.value, None);
+        assert_eq!(update.timestamp, received);
+    }
+
+    #[tokio::test]
+    async fn test_updates_are_yielded_per_entry() {
+        let responses = tokio_stream::iter(vec![
+            Ok(response(&[
+                (
+                    "Vehicle.Speed",
+                    datapoint(Some(TypedValue::Float(30.0)), None),
+                ),
+                ("Vehicle.AverageSpeed", datapoint(None, None)),
+            ])),
+            Ok(response(&[(
+                "Vehicle.Speed",
+                datapoint(Some(TypedValue::Float(40.0)), None),
+            )])),
+            Err(tonic::Status::permission_denied("no access")),
+        ]);
+        let updates: Vec<_> = SignalUpdates::new(responses).collect().await;
+
+        assert_eq!(updates.len(), 4);
+        assert!(updates[..3].iter().all(Result::is_ok));
+        let err = updates[3].as_ref().unwrap_err();
+        assert!(matches!(err, ClientError::PermissionDenied(_)), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn test_decode_failures_do_not_end_typed_updates() {
+        let responses = tokio_stream::iter(vec![
+            Ok(response(&[(
+                "Vehicle.Speed",
+                datapoint(Some(TypedValue::Bool(true)), None),
+            )])),
+            Ok(response(&[(
+                "Vehicle.Speed",
+                datapoint(Some(TypedValue::Float(40.0)), None),
+            )])),
+        ]);
+        let mut updates = SignalUpdates::new(responses).typed::<f32>();
+
+        let err = updates.next().await.unwrap().unwrap_err();
+        assert!(matches!(err, SignalUpdateError::Decode { .. }), "{err:?}");
+        let update = updates.next().await.unwrap().unwrap();
+        assert_eq!(update.value, Some(40.0));
+        assert!(updates.next().await.is_none());
+    }
+}
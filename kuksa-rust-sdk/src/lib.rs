@@ -32,6 +32,69 @@ pub mod sdv {
     }
 }
 
+// The value enums of the protocols share the variants for the primitive types
+// and arrays of them, only the names of the variants differ.
+macro_rules! impl_from_for_value {
+    (
+        $value:ident,
+        [$($type:ty => $variant:ident),* $(,)?],
+        [$($elem:ty => $array_variant:ident($array:ident)),* $(,)?] $(,)?
+    ) => {
+        $(
+            impl From<$type> for $value {
+                fn from(value: $type) -> Self {
+                    $value::$variant(value)
+                }
+            }
+        )*
+        $(
+            impl From<Vec<$elem>> for $value {
+                fn from(values: Vec<$elem>) -> Self {
+                    $value::$array_variant($array { values })
+                }
+            }
+        )*
+        impl From<&str> for $value {
+            fn from(value: &str) -> Self {
+                Self::from(value.to_owned())
+            }
+        }
+    };
+}
+
+// Inverse of `impl_from_for_value`, only the variant matching the type is accepted
+macro_rules! impl_try_from_value {
+    (
+        $value:ident,
+        $error:ident,
+        [$($type:ty => $variant:ident),* $(,)?],
+        [$($elem:ty => $array_variant:ident($array:ident)),* $(,)?] $(,)?
+    ) => {
+        $(
+            impl TryFrom<$value> for $type {
+                type Error = $error;
+                fn try_from(value: $value) -> Result<Self, Self::Error> {
+                    match value {
+                        $value::$variant(v) => Ok(v),
+                        _ => Err($error {}),
+                    }
+                }
+            }
+        )*
+        $(
+            impl TryFrom<$value> for Vec<$elem> {
+                type Error = $error;
+                fn try_from(value: $value) -> Result<Self, Self::Error> {
+                    match value {
+                        $value::$array_variant(v) => Ok(v.values),
+                        _ => Err($error {}),
+                    }
+                }
+            }
+        )*
+    };
+}
+
 pub mod proto {
     pub mod kuksa {
         pub mod val {
@@ -40,6 +103,7 @@ pub mod proto {
                     tonic::include_file_descriptor_set!("kuksa.val.v1_descriptor");
                 tonic::include_proto!("kuksa.val.v1");
 
+                use super::v2::IncompatibleValueTypeError;
                 use datapoint::Value;
                 use std::{any::Any, fmt::Display, str::FromStr};
 
@@ -156,6 +220,55 @@ pub mod proto {
                         }
                     }
                 }
+
+                impl_from_for_value!(
+                    Value,
+                    [
+                        String => String,
+                        bool => Bool,
+                        i32 => Int32,
+                        i64 => Int64,
+                        u32 => Uint32,
+                        u64 => Uint64,
+                        f32 => Float,
+                        f64 => Double,
+                    ],
+                    [
+                        String => StringArray(StringArray),
+                        bool => BoolArray(BoolArray),
+                        i32 => Int32Array(Int32Array),
+                        i64 => Int64Array(Int64Array),
+                        u32 => Uint32Array(Uint32Array),
+                        u64 => Uint64Array(Uint64Array),
+                        f32 => FloatArray(FloatArray),
+                        f64 => DoubleArray(DoubleArray),
+                    ],
+                );
+
+                impl_try_from_value!(
+                    Value,
+                    IncompatibleValueTypeError,
+                    [
+                        String => String,
+                        bool => Bool,
+                        i32 => Int32,
+                        i64 => Int64,
+                        u32 => Uint32,
+                        u64 => Uint64,
+                        f32 => Float,
+                        f64 => Double,
+                    ],
+                    [
+                        String => StringArray(StringArray),
+                        bool => BoolArray(BoolArray),
+                        i32 => Int32Array(Int32Array),
+                        i64 => Int64Array(Int64Array),
+                        u32 => Uint32Array(Uint32Array),
+                        u64 => Uint64Array(Uint64Array),
+                        f32 => FloatArray(FloatArray),
+                        f64 => DoubleArray(DoubleArray),
+                    ],
+                );
             }
             pub mod v2 {
                 use value::TypedValue;
@@ -165,8 +278,9 @@ pub mod proto {
                 pub const FILE_DESCRIPTOR_SET: &[u8] =
                     tonic::include_file_descriptor_set!("kuksa.val.v2_descriptor");
 
-                /// Indicates that a [`TypedValue`] cannot be converted to the
-                /// desired type because it its value has an incompatible type.
+                /// Indicates that a [`TypedValue`], or a value of the v1 and sdv
+                /// protocols, cannot be converted to the desired type because its
+                /// value has an incompatible type.
                 #[derive(Debug)]
                 pub struct IncompatibleValueTypeError {}
 
@@ -507,6 +621,66 @@ pub mod proto {
                         }
                     }
                 }
+
+                impl_from_for_value!(
+                    TypedValue,
+                    [
+                        String => String,
+                        bool => Bool,
+                        i32 => Int32,
+                        i64 => Int64,
+                        u32 => Uint32,
+                        u64 => Uint64,
+                        f32 => Float,
+                        f64 => Double,
+                    ],
+                    [
+                        String => StringArray(StringArray),
+                        bool => BoolArray(BoolArray),
+                        i32 => Int32Array(Int32Array),
+                        i64 => Int64Array(Int64Array),
+                        u32 => Uint32Array(Uint32Array),
+                        u64 => Uint64Array(Uint64Array),
+                        f32 => FloatArray(FloatArray),
+                        f64 => DoubleArray(DoubleArray),
+                    ],
+                );
+
+                // A blanket impl over `Into<TypedValue>` would conflict with `From<T> for T`
+                macro_rules! impl_from_for_v2_value {
+                    ($($type:ty),* $(,)?) => {
+                        $(
+                            impl From<$type> for Value {
+                                fn from(value: $type) -> Self {
+                                    Value {
+                                        typed_value: Some(value.into()),
+                                    }
+                                }
+                            }
+                        )*
+                    };
+                }
+
+                impl_from_for_v2_value!(
+                    TypedValue,
+                    &str,
+                    String,
+                    bool,
+                    i32,
+                    i64,
+                    u32,
+                    u64,
+                    f32,
+                    f64,
+                    Vec<String>,
+                    Vec<bool>,
+                    Vec<i32>,
+                    Vec<i64>,
+                    Vec<u32>,
+                    Vec<u64>,
+                    Vec<f32>,
+                    Vec<f64>,
+                );
             }
         }
     }
@@ -516,6 +690,58 @@ pub mod proto {
                 pub const FILE_DESCRIPTOR_SET: &[u8] =
                     tonic::include_file_descriptor_set!("sdv.databroker.v1_descriptor");
                 tonic::include_proto!("sdv.databroker.v1");
+
+                use crate::proto::kuksa::val::v2::IncompatibleValueTypeError;
+                use datapoint::Value;
+
+                impl_from_for_value!(
+                    Value,
+                    [
+                        String => StringValue,
+                        bool => BoolValue,
+                        i32 => Int32Value,
+                        i64 => Int64Value,
+                        u32 => Uint32Value,
+                        u64 => Uint64Value,
+                        f32 => FloatValue,
+                        f64 => DoubleValue,
+                    ],
+                    [
+                        String => StringArray(StringArray),
+                        bool => BoolArray(BoolArray),
+                        i32 => Int32Array(Int32Array),
+                        i64 => Int64Array(Int64Array),
+                        u32 => Uint32Array(Uint32Array),
+                        u64 => Uint64Array(Uint64Array),
+                        f32 => FloatArray(FloatArray),
+                        f64 => DoubleArray(DoubleArray),
+                    ],
+                );
+
+                impl_try_from_value!(
+                    Value,
+                    IncompatibleValueTypeError,
+                    [
+                        String => StringValue,
+                        bool => BoolValue,
+                        i32 => Int32Value,
+                        i64 => Int64Value,
+                        u32 => Uint32Value,
+                        u64 => Uint64Value,
+                        f32 => FloatValue,
+                        f64 => DoubleValue,
+                    ],
+                    [
+                        String => StringArray(StringArray),
+                        bool => BoolArray(BoolArray),
+                        i32 => Int32Array(Int32Array),
+                        i64 => Int64Array(Int64Array),
+                        u32 => Uint32Array(Uint32Array),
+                        u64 => Uint64Array(Uint64Array),
+                        f32 => FloatArray(FloatArray),
+                        f64 => DoubleArray(DoubleArray),
+                    ],
+                );
             }
         }
     }
@@ -709,4 +935,92 @@ mod tests {
         });
         assert!(Vec::<i64>::try_from(v).is_err());
     }
+
+    #[test_case(TypedValue::from("one"), TypedValue::String("one".to_string()); "from str")]
+    #[test_case(TypedValue::from(true), TypedValue::Bool(true); "from bool")]
+    #[test_case(TypedValue::from(-7_i32), TypedValue::Int32(-7); "from i32")]
+    #[test_case(TypedValue::from(7_u64), TypedValue::Uint64(7); "from u64")]
+    #[test_case(TypedValue::from(1.5_f32), TypedValue::Float(1.5); "from f32")]
+    #[test_case(
+        TypedValue::from(vec![1.5_f64]),
+        TypedValue::DoubleArray(proto::kuksa::val::v2::DoubleArray { values: vec![1.5] });
+        "from Vec<f64>")]
+    fn test_from_for_typedvalue(value: TypedValue, expected: TypedValue) {
+        assert_eq!(value, expected);
+    }
+
+    #[test_case(vec!["one".to_string()]; "for Vec<String>")]
+    #[test_case(vec![1_u32, 2]; "for Vec<u32>")]
+    #[test_case(-7_i64; "for i64")]
+    fn test_from_for_typedvalue_roundtrip<T>(value: T)
+    where
+        T: Clone + Into<TypedValue> + TryFrom<TypedValue> + PartialEq + std::fmt::Debug,
+    {
+        let typed_value: TypedValue = value.clone().into();
+        assert!(T::try_from(typed_value).is_ok_and(|v| v == value));
+    }
+
+    #[test_case(v2_proto::Value::from(1.5_f32), TypedValue::Float(1.5); "from f32")]
+    #[test_case(v2_proto::Value::from("one"), TypedValue::String("one".to_string()); "from str")]
+    #[test_case(
+        v2_proto::Value::from(vec![true]),
+        TypedValue::BoolArray(v2_proto::BoolArray { values: vec![true] });
+        "from Vec<bool>")]
+    #[test_case(v2_proto::Value::from(TypedValue::Int64(-7)), TypedValue::Int64(-7); "from TypedValue")]
+    fn test_from_for_v2_value(value: v2_proto::Value, expected: TypedValue) {
+        assert_eq!(value.typed_value, Some(expected));
+    }
+
+    #[test_case(v1_proto::datapoint::Value::from("one"), v1_proto::datapoint::Value::String("one".to_string()); "from str")]
+    #[test_case(v1_proto::datapoint::Value::from(7_u32), v1_proto::datapoint::Value::Uint32(7); "from u32")]
+    #[test_case(
+        v1_proto::datapoint::Value::from(vec![-7_i64]),
+        v1_proto::datapoint::Value::Int64Array(v1_proto::Int64Array { values: vec![-7] });
+        "from Vec<i64>")]
+    fn test_from_for_v1_value(
+        value: v1_proto::datapoint::Value,
+        expected: v1_proto::datapoint::Value,
+    ) {
+        assert_eq!(value, expected);
+    }
+
+    #[test_case(sdv_proto::datapoint::Value::from("one"), sdv_proto::datapoint::Value::StringValue("one".to_string()); "from str")]
+    #[test_case(sdv_proto::datapoint::Value::from(1.5_f64), sdv_proto::datapoint::Value::DoubleValue(1.5); "from f64")]
+    #[test_case(
+        sdv_proto::datapoint::Value::from(vec![true]),
+        sdv_proto::datapoint::Value::BoolArray(sdv_proto::BoolArray { values: vec![true] });
+        "from Vec<bool>")]
+    fn test_from_for_sdv_value(
+        value: sdv_proto::datapoint::Value,
+        expected: sdv_proto::datapoint::Value,
+    ) {
+        assert_eq!(value, expected);
+    }
+
+    #[test_case("one".to_string(); "for String")]
+    #[test_case(vec![1.5_f32]; "for Vec<f32>")]
+    #[test_case(7_u64; "for u64")]
+    fn test_v1_and_sdv_value_roundtrip<T>(value: T)
+    where
+        T: Clone
+            + Into<v1_proto::datapoint::Value>
+            + TryFrom<v1_proto::datapoint::Value>
+            + Into<sdv_proto::datapoint::Value>
+            + TryFrom<sdv_proto::datapoint::Value>
+            + PartialEq
+            + std::fmt::Debug,
+    {
+        let v1_value: v1_proto::datapoint::Value = value.clone().into();
+        assert!(T::try_from(v1_value).is_ok_and(|v| v == value));
+        let sdv_value: sdv_proto::datapoint::Value = value.clone().into();
+        assert!(T::try_from(sdv_value).is_ok_and(|v| v == value));
+    }
+
+    #[test]
+    fn test_try_from_v1_and_sdv_value_with_other_type_fails() {
+        assert!(u64::try_from(v1_proto::datapoint::Value::Uint32(7)).is_err());
+        assert!(Vec::<bool>::try_from(v1_proto::datapoint::Value::Bool(true)).is_err());
+        assert!(i32::try_from(sdv_proto::datapoint::Value::FailureValue(0)).is_err());
+        assert!(String::try_from(sdv_proto::datapoint::Value::BoolValue(true)).is_err());
+    }
 }
@@ -13,10 +13,12 @@
  * ******************************************************************************
  */
 
+use crate::kuksa::common::builder::ClientBuilder;
+use crate::kuksa::common::options::CallOptions;
 use crate::kuksa::common::types;
 use std::collections::HashMap;
 
-use crate::kuksa::common::{Client, ClientError, SDVClientTraitV1};
+use crate::kuksa::common::{Client, ClientError, RequestError, SDVClientTraitV1};
 use crate::proto::sdv::databroker::v1 as proto;
 use http::Uri;
 use tonic::async_trait;
@@ -25,6 +27,12 @@ pub struct SDVClient {
     pub basic_client: Client,
 }
 
+impl From<Client> for SDVClient {
+    fn from(basic_client: Client) -> Self {
+        SDVClient { basic_client }
+    }
+}
+
 impl SDVClient {
     pub fn new(uri: Uri) -> Self {
         SDVClient {
@@ -33,8 +41,21 @@ impl SDVClient {
     }
 
     pub fn from_host(host: &'static str) -> Self {
-        let uri = Uri::from_static(host);
-        Self::new(uri)
+        ClientBuilder::new(host)
+            .build()
+            .expect("host should be a valid uri")
+    }
+
+    pub fn builder(address: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(address)
+    }
+
+    /// Returns a client sharing the connection with this one, which applies
+    /// `call_options` (timeout, metadata, cancellation) to all its calls.
+    pub fn with_call_options(&self, call_options: CallOptions) -> Self {
+        SDVClient {
+            basic_client: self.basic_client.with_call_options(call_options),
+        }
     }
 }
 
@@ -53,97 +74,129 @@ impl SDVClientTraitV1 for SDVClient {
 
     async fn update_datapoints(
         &mut self,
-        datapoints: Self::SensorUpdateType,
+        mut datapoints: Self::SensorUpdateType,
     ) -> Result<Self::PublishResponseType, ClientError> {
         let metadata = self
             .get_metadata(datapoints.keys().cloned().collect())
-            .await
-            .unwrap();
+            .await?;
         let id_datapoints: HashMap<i32, proto::Datapoint> = metadata
             .into_iter()
-            .map(|meta| meta.id)
-            .zip(datapoints.into_values())
+            .filter_map(|meta| Some((meta.id, datapoints.remove(&meta.name)?)))
             .collect();
+        if !datapoints.is_empty() {
+            let paths: Vec<String> = datapoints.into_keys().collect();
+            return Err(ClientError::NotFound(RequestError {
+                operation: "update_datapoints",
+                status: tonic::Status::not_found(format!("Unknown datapoints {paths:?}")),
+                signals: paths,
+            }));
+        }
 
-        let mut client = proto::collector_client::CollectorClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
-
-        let request = tonic::Request::new(proto::UpdateDatapointsRequest {
+        let request = proto::UpdateDatapointsRequest {
             datapoints: id_datapoints,
-        });
-        match client.update_datapoints(request).await {
-            Ok(response) => Ok(response.into_inner()),
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        };
+        let response = self
+            .basic_client
+            .call(|channel| {
+                let request = request.clone();
+                async move {
+                    proto::collector_client::CollectorClient::new(channel)
+                        .update_datapoints(request)
+                        .await
+                }
+            })
+            .await
+            .map_err(|err| {
+                let ids = request.datapoints.keys().map(i32::to_string);
+                err.with_context("update_datapoints", ids.collect())
+            })?;
+        Ok(response.into_inner())
     }
 
     async fn get_datapoints(
         &mut self,
         paths: Self::PathType,
     ) -> Result<Self::GetResponseType, ClientError> {
-        let mut client = proto::broker_client::BrokerClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
-        let args = tonic::Request::new(proto::GetDatapointsRequest { datapoints: paths });
-        match client.get_datapoints(args).await {
-            Ok(response) => {
-                let message = response.into_inner();
-                Ok(message.datapoints)
-            }
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        let args = proto::GetDatapointsRequest { datapoints: paths };
+        let response = self
+            .basic_client
+            .call_read_only(|channel| {
+                let args = args.clone();
+                async move {
+                    proto::broker_client::BrokerClient::new(channel)
+                        .get_datapoints(args)
+                        .await
+                }
+            })
+            .await
+            .map_err(|err| err.with_context("get_datapoints", args.datapoints.clone()))?;
+        let message = response.into_inner();
+        Ok(message.datapoints)
     }
 
     async fn subscribe(
         &mut self,
         paths: Self::SubscribeType,
     ) -> Result<Self::SubscribeResponseType, ClientError> {
-        let mut client = proto::broker_client::BrokerClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
-        let args = tonic::Request::new(proto::SubscribeRequest { query: paths });
-
-        match client.subscribe(args).await {
-            Ok(response) => Ok(response.into_inner()),
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        let args = proto::SubscribeRequest { query: paths };
+
+        let response = self
+            .basic_client
+            .call_streaming(|channel| {
+                let args = args.clone();
+                async move {
+                    proto::broker_client::BrokerClient::new(channel)
+                        .subscribe(args)
+                        .await
+                }
+            })
+            .await
+            .map_err(|err| err.with_context("subscribe", vec![args.query.clone()]))?;
+        Ok(response.into_inner())
     }
 
     async fn set_datapoints(
         &mut self,
         datapoints: Self::UpdateActuationType,
     ) -> Result<Self::ActuateResponseType, ClientError> {
-        let args = tonic::Request::new(proto::SetDatapointsRequest { datapoints });
-        let mut client = proto::broker_client::BrokerClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
-        match client.set_datapoints(args).await {
-            Ok(response) => Ok(response.into_inner()),
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        let args = proto::SetDatapointsRequest { datapoints };
+        let response = self
+            .basic_client
+            .call(|channel| {
+                let args = args.clone();
+                async move {
+                    proto::broker_client::BrokerClient::new(channel)
+                        .set_datapoints(args)
+                        .await
+                }
+            })
+            .await
+            .map_err(|err| {
+                let paths = args.datapoints.keys().cloned();
+                err.with_context("set_datapoints", paths.collect())
+            })?;
+        Ok(response.into_inner())
     }
 
     async fn get_metadata(
         &mut self,
         paths: Self::PathType,
     ) -> Result<Self::MetadataResponseType, ClientError> {
-        let mut client = proto::broker_client::BrokerClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
         // Empty vec == all property metadata
-        let args = tonic::Request::new(proto::GetMetadataRequest { names: paths });
-        match client.get_metadata(args).await {
-            Ok(response) => {
-                let message = response.into_inner();
-                Ok(message.list)
-            }
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        let args = proto::GetMetadataRequest { names: paths };
+        let response = self
+            .basic_client
+            .call_read_only(|channel| {
+                let args = args.clone();
+                async move {
+                    proto::broker_client::BrokerClient::new(channel)
+                        .get_metadata(args)
+                        .await
+                }
+            })
+            .await
+            .map_err(|err| err.with_context("get_metadata", args.names.clone()))?;
+        let message = response.into_inner();
+        Ok(message.list)
     }
 }